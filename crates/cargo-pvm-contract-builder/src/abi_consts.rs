@@ -0,0 +1,188 @@
+//! The `pvm-abi-consts.json` sidecar `cargo pvm-contract` writes next to no-alloc
+//! contracts, and the check [`PvmBuilder::with_verify_abi_consts`](crate::PvmBuilder::with_verify_abi_consts)
+//! runs against it.
+//!
+//! No-alloc contracts dispatch on hard-coded selector, event and error constants.
+//! The sidecar records which signature each constant was generated for, so the
+//! build can recompute them from the `.sol` file and catch constants that were
+//! edited by hand or left behind when the interface changed.
+
+use crate::{ErrorContext, Result, hex_string, keccak256};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// File name of the sidecar, in the contract's manifest directory.
+pub const ABI_CONSTS_FILE: &str = "pvm-abi-consts.json";
+
+/// Contents of a `pvm-abi-consts.json` sidecar.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiConsts {
+    /// Solidity file the constants were generated from, relative to the manifest directory.
+    pub sol_file: PathBuf,
+    /// Contract in `sol_file` whose ABI the constants follow.
+    pub contract: String,
+    /// Rust file declaring the constants, relative to the manifest directory.
+    pub source: PathBuf,
+    pub constants: Vec<AbiConst>,
+}
+
+/// A constant generated from an ABI item.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AbiConst {
+    /// Name of the Rust constant, e.g. `TRANSFER_SELECTOR`.
+    pub name: String,
+    pub kind: AbiConstKind,
+    /// Canonical signature the constant was generated for, e.g. `transfer(address,uint256)`.
+    pub signature: String,
+}
+
+/// What an [`AbiConst`] holds.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AbiConstKind {
+    /// 4-byte function selector.
+    Function,
+    /// 32-byte event topic.
+    Event,
+    /// 4-byte error selector.
+    Error,
+}
+
+impl AbiConstKind {
+    /// The value of a constant of this kind for `signature`.
+    pub fn value(self, signature: &str) -> Vec<u8> {
+        let hash = keccak256(signature.as_bytes());
+        match self {
+            Self::Event => hash.to_vec(),
+            Self::Function | Self::Error => hash[..4].to_vec(),
+        }
+    }
+
+    fn abi_type(self) -> &'static str {
+        match self {
+            Self::Function => "function",
+            Self::Event => "event",
+            Self::Error => "error",
+        }
+    }
+}
+
+impl AbiConsts {
+    /// Read the sidecar from `manifest_dir`, if there is one.
+    pub fn read(manifest_dir: &Path) -> Result<Option<Self>> {
+        let path = manifest_dir.join(ABI_CONSTS_FILE);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let json = fs::read_to_string(&path)
+            .context_with(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json)
+            .context_with(|| format!("Failed to parse {}", path.display()))
+            .map(Some)
+    }
+
+    /// Compare the constants declared in `source` with what `abi` gives for them.
+    ///
+    /// Returns one line per drifted constant, empty if they all match.
+    pub fn check(&self, source: &str, abi: &serde_json::Value) -> Vec<String> {
+        let mut drifted = Vec::new();
+        for constant in &self.constants {
+            let Some(found) = const_bytes(source, &constant.name) else {
+                drifted.push(format!(
+                    "`{}` is not declared in {}",
+                    constant.name,
+                    self.source.display()
+                ));
+                continue;
+            };
+            let Some(signature) = current_signature(abi, constant) else {
+                drifted.push(format!(
+                    "`{}`: `{}` is no longer in the ABI",
+                    constant.name, constant.signature
+                ));
+                continue;
+            };
+            let expected = constant.kind.value(&signature);
+            if found == expected {
+                continue;
+            }
+            let mut line = format!(
+                "`{}`: {}, expected {} for `{signature}`",
+                constant.name,
+                hex_string(&found),
+                hex_string(&expected)
+            );
+            if signature != constant.signature {
+                line.push_str(&format!(" (generated for `{}`)", constant.signature));
+            }
+            drifted.push(line);
+        }
+        drifted
+    }
+}
+
+/// The signature in `abi` that `constant` stands for: the recorded one if it is still
+/// there, otherwise that of the only item of the same kind and name.
+fn current_signature(abi: &serde_json::Value, constant: &AbiConst) -> Option<String> {
+    let name = constant.signature.split('(').next()?;
+    let candidates: Vec<String> = abi
+        .as_array()?
+        .iter()
+        .filter(|item| item["type"] == constant.kind.abi_type() && item["name"] == name)
+        .map(|item| format!("{name}({})", canonical_params(&item["inputs"])))
+        .collect();
+    if candidates.contains(&constant.signature) {
+        return Some(constant.signature.clone());
+    }
+    match candidates.as_slice() {
+        [signature] => Some(signature.clone()),
+        _ => None,
+    }
+}
+
+/// Canonical parameter list of ABI `inputs`, with tuples expanded.
+fn canonical_params(inputs: &serde_json::Value) -> String {
+    let Some(inputs) = inputs.as_array() else {
+        return String::new();
+    };
+    inputs
+        .iter()
+        .map(|input| {
+            let type_name = input["type"].as_str().unwrap_or_default();
+            match type_name.strip_prefix("tuple") {
+                Some(suffix) => format!("({}){suffix}", canonical_params(&input["components"])),
+                None => type_name.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The bytes of `const NAME: [u8; N] = [0x.., ...];` in `source`.
+fn const_bytes(source: &str, name: &str) -> Option<Vec<u8>> {
+    let declaration = format!("const {name}:");
+    let start = source
+        .match_indices(&declaration)
+        .map(|(index, _)| index)
+        .find(|&index| {
+            source[..index]
+                .chars()
+                .next_back()
+                .is_none_or(|c| !c.is_alphanumeric() && c != '_')
+        })?;
+    let rest = &source[start + declaration.len()..];
+    let value = &rest[rest.find('=')?..];
+    let value = &value[value.find('[')? + 1..];
+    let value = &value[..value.find(']')?];
+    value
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or_default())
+        .flat_map(|line| line.split(','))
+        .map(str::trim)
+        .filter(|byte| !byte.is_empty())
+        .map(|byte| u8::from_str_radix(byte.strip_prefix("0x")?, 16).ok())
+        .collect()
+}
@@ -0,0 +1,81 @@
+//! The `<bin>.contract.json` bundle written by [`PvmBuilder::with_bundle`](crate::PvmBuilder::with_bundle).
+//!
+//! A bundle keeps everything needed to deploy and call a contract in one file:
+//! the hex-encoded bytecode, its code hash, the Solidity ABI and how it was built.
+//!
+//! ```no_run
+//! use cargo_pvm_contract_builder::bundle::ContractBundle;
+//!
+//! let json = std::fs::read_to_string("target/mytoken.contract.json").unwrap();
+//! let bundle: ContractBundle = serde_json::from_str(&json).unwrap();
+//! println!("{} has code hash {}", bundle.name, bundle.code_hash);
+//! ```
+
+use crate::{ErrorContext, PvmBuildError, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Where the ABI of a bundle comes from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AbiSource {
+    /// Compile it with `solc` from the package's `.sol` file named after the binary,
+    /// as [`PvmBuilder::with_abi_output`](crate::PvmBuilder::with_abi_output) does.
+    Solidity,
+    /// Read it from a JSON file, relative to the contract's manifest directory: a bare
+    /// ABI array or a build artifact with an `abi` field, as Hardhat and Foundry write.
+    File(PathBuf),
+}
+
+/// Contents of a `<bin>.contract.json` bundle.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ContractBundle {
+    /// Name of the binary target.
+    pub name: String,
+    /// `0x`-prefixed hex of the `.polkavm` blob, the code to upload.
+    pub bytecode: String,
+    /// `0x`-prefixed keccak-256 of the blob, the code hash pallet-revive reports.
+    pub code_hash: String,
+    /// Solidity ABI of the contract, the JSON array `solc` emits.
+    pub abi: serde_json::Value,
+    /// How the blob was built.
+    pub build_info: BuildInfo,
+}
+
+/// Toolchain and settings a bundled blob was built with.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildInfo {
+    /// Output of `rustc --version` for the toolchain that built the contract.
+    pub rustc_version: Option<String>,
+    /// Version of `polkavm-linker` the blob was linked with.
+    pub polkavm_linker_version: String,
+    /// Cargo profile of the contract build, e.g. `release`.
+    pub profile: String,
+    /// Instruction set the blob was linked for, e.g. `revive_v1`.
+    pub instruction_set: String,
+    /// The blob was built by [`PvmBuilder::with_debug_blob`](crate::PvmBuilder::with_debug_blob) and must not be deployed.
+    #[serde(default)]
+    pub debug_blob: bool,
+}
+
+/// Read the ABI from `path`, a bare ABI array or an artifact with an `abi` field.
+pub(crate) fn read_abi(path: &Path) -> Result<serde_json::Value> {
+    let content =
+        fs::read_to_string(path).context_with(|| format!("Failed to read {}", path.display()))?;
+    let json: serde_json::Value = serde_json::from_str(&content)
+        .context_with(|| format!("Failed to parse {}", path.display()))?;
+    match json {
+        serde_json::Value::Array(_) => Ok(json),
+        serde_json::Value::Object(mut artifact)
+            if artifact.get("abi").is_some_and(serde_json::Value::is_array) =>
+        {
+            Ok(artifact.remove("abi").unwrap_or_default())
+        }
+        _ => Err(PvmBuildError::invalid_config(format!(
+            "{} is neither an ABI array nor an artifact with an `abi` field",
+            path.display()
+        ))),
+    }
+}
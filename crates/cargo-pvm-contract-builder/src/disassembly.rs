@@ -0,0 +1,97 @@
+//! Human-readable listings of linked `.polkavm` blobs.
+
+use crate::{PvmBuildError, Result};
+use polkavm_linker::ProgramBlob;
+use std::{collections::BTreeMap, fmt::Write, path::Path};
+
+/// Disassemble `bytes`, the `.polkavm` blob read from `path`.
+///
+/// The listing starts with a summary of the exports and of the number of
+/// instructions per function, followed by the instructions with a label at
+/// each export and jump target. Functions are split at exports and, when the
+/// blob was linked without stripping, at each change of the enclosing function.
+pub(crate) fn disassemble(path: &Path, bytes: Vec<u8>) -> Result<String> {
+    let size = bytes.len();
+    let blob = ProgramBlob::parse(bytes.into()).map_err(|e| PvmBuildError::InvalidBlob {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })?;
+
+    let exports: BTreeMap<u32, String> = blob
+        .exports()
+        .map(|export| {
+            (
+                export.program_counter().0,
+                String::from_utf8_lossy(export.symbol().as_bytes()).into_owned(),
+            )
+        })
+        .collect();
+
+    // Name of the function containing a program counter, if the blob carries debug info
+    let function_name = |pc| -> Option<String> {
+        let mut program = blob.get_debug_line_program_at(pc).ok()??;
+        let region = program.run().ok()??;
+        let frame = region.frames().next()?;
+        let name = frame.full_name().ok()?.to_string();
+        (!name.is_empty()).then_some(name)
+    };
+
+    // The iterator keeps yielding traps past the end of the code
+    let code_len = blob.code().len();
+    let mut functions: Vec<(String, usize)> = Vec::new();
+    let mut body = String::new();
+    let mut instruction_count = 0;
+    let mut block_start = true;
+    // Debug name of the function the current section belongs to, so that an export
+    // and the rest of the function it is defined by share one section
+    let mut current_function = None;
+    for inst in blob
+        .instructions()
+        .take_while(|inst| (inst.offset.0 as usize) < code_len)
+    {
+        let pc = inst.offset.0;
+        let debug_name = if block_start {
+            function_name(inst.offset)
+        } else {
+            None
+        };
+        let section = match exports.get(&pc) {
+            Some(export) => Some(export.clone()),
+            None if debug_name.is_some() && debug_name != current_function => debug_name.clone(),
+            None => None,
+        };
+        if let Some(name) = section {
+            let _ = writeln!(body, "\n{name}:");
+            functions.push((name, 0));
+            current_function = debug_name;
+        }
+        if block_start {
+            let _ = writeln!(body, "@{pc}:");
+        }
+        let _ = writeln!(body, "{inst}");
+
+        if let Some((_, count)) = functions.last_mut() {
+            *count += 1;
+        }
+        instruction_count += 1;
+        block_start = inst.starts_new_basic_block();
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "; {}", path.display());
+    let _ = writeln!(
+        out,
+        "; {size} bytes, {instruction_count} instructions, {}-bit",
+        if blob.is_64_bit() { 64 } else { 32 }
+    );
+    let _ = writeln!(out, ";\n; exports:");
+    for (pc, name) in &exports {
+        let _ = writeln!(out, ";   {name} @{pc}");
+    }
+    let _ = writeln!(out, ";\n; functions:");
+    for (name, count) in &functions {
+        let _ = writeln!(out, ";   {name}: {count} instructions");
+    }
+    out.push_str(&body);
+    Ok(out)
+}
@@ -0,0 +1,213 @@
+use std::{fmt, io, path::PathBuf};
+
+/// Why a PolkaVM build failed.
+///
+/// Returned by [`PvmBuilder::try_build`](crate::PvmBuilder::try_build). It implements
+/// [`std::error::Error`], so `?` converts it into `anyhow::Error` and friends.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PvmBuildError {
+    /// `solc` is not in `PATH` and `SOLC_PATH` is not set.
+    SolcNotFound,
+    /// `solc` ran but failed to compile the Solidity file.
+    SolcFailed { stderr: String },
+    /// The nested `cargo build` of the contract failed.
+    CargoFailed { stderr: String },
+    /// The PolkaVM linker rejected the contract's ELF.
+    LinkerFailed { elf_path: PathBuf, source: String },
+    /// A linked binary is larger than [`PvmBuilder::with_max_size`](crate::PvmBuilder::with_max_size) allows.
+    SizeLimitExceeded { bin: String, size: u64, limit: u64 },
+    /// A linked binary lacks entry points [`PvmBuilder::with_required_exports`](crate::PvmBuilder::with_required_exports) asks for.
+    MissingExports {
+        bin: String,
+        missing: Vec<String>,
+        found: Vec<String>,
+    },
+    /// Selector, event or error constants no longer match the contract's Solidity ABI,
+    /// see [`PvmBuilder::with_verify_abi_consts`](crate::PvmBuilder::with_verify_abi_consts).
+    AbiConstsDrifted {
+        source_path: PathBuf,
+        sol_path: PathBuf,
+        drifted: Vec<String>,
+    },
+    /// A linked binary imports host functions the target runtime doesn't provide.
+    DisallowedImports {
+        bin: String,
+        imports: Vec<String>,
+        /// Where the allowed imports came from.
+        allowlist: String,
+    },
+    /// The nested build succeeded without producing the expected ELF.
+    ElfNotFound { path: PathBuf },
+    /// A linked `.polkavm` file could not be parsed.
+    InvalidBlob { path: PathBuf, message: String },
+    /// There are no binary or cdylib targets to build.
+    NoBinaryTargets,
+    /// A generated file could not be rendered.
+    TemplateError { context: String },
+    /// The build toolchain is not installed.
+    ToolchainNotInstalled { toolchain: String },
+    /// The build toolchain lacks the `rust-src` component `-Zbuild-std` needs.
+    RustSrcMissing { toolchain: String },
+    /// The build toolchain's rustc is too old to build contracts.
+    UnsupportedToolchain { version: String },
+    /// A `Cargo.toml` could not be parsed or lacks something the build needs.
+    Manifest { path: PathBuf, message: String },
+    /// The builder was configured with conflicting or unsupported options.
+    InvalidConfig { message: String },
+    /// Running a tool or reading or writing a file failed.
+    Io { context: String, source: io::Error },
+    /// JSON exchanged with cargo or solc, or written to a report, was malformed.
+    Json {
+        context: String,
+        source: serde_json::Error,
+    },
+    /// Several binaries failed independently, e.g. while linking in parallel.
+    Multiple { errors: Vec<PvmBuildError> },
+}
+
+impl PvmBuildError {
+    pub(crate) fn invalid_config(message: impl Into<String>) -> Self {
+        Self::InvalidConfig {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for PvmBuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SolcNotFound => write!(
+                f,
+                "solc (Solidity compiler) not found in PATH. Install it via `npm install -g solc` or from https://github.com/ethereum/solidity/releases, or set the SOLC_PATH environment variable to its location."
+            ),
+            Self::SolcFailed { stderr } => write!(f, "solc failed: {stderr}"),
+            Self::CargoFailed { stderr } => write!(f, "Cargo build failed:\n{stderr}"),
+            Self::LinkerFailed { elf_path, source } => write!(
+                f,
+                "Failed to link PolkaVM program {}: {source}",
+                elf_path.display()
+            ),
+            Self::SizeLimitExceeded { bin, size, limit } => write!(
+                f,
+                "PolkaVM binary `{bin}` is {size} bytes, which exceeds the limit of {limit} bytes"
+            ),
+            Self::MissingExports {
+                bin,
+                missing,
+                found,
+            } => write!(
+                f,
+                "PolkaVM binary `{bin}` does not export {}; exports found: {}. Entry points need `#[polkavm_derive::polkavm_export]`",
+                quoted_list(missing),
+                if found.is_empty() {
+                    "none".to_string()
+                } else {
+                    quoted_list(found)
+                }
+            ),
+            Self::AbiConstsDrifted {
+                source_path,
+                sol_path,
+                drifted,
+            } => {
+                write!(
+                    f,
+                    "ABI constants in {} no longer match {}:",
+                    source_path.display(),
+                    sol_path.display()
+                )?;
+                for line in drifted {
+                    write!(f, "\n  {line}")?;
+                }
+                Ok(())
+            }
+            Self::DisallowedImports {
+                bin,
+                imports,
+                allowlist,
+            } => write!(
+                f,
+                "PolkaVM binary `{bin}` imports host functions missing from {allowlist}: {}",
+                quoted_list(imports)
+            ),
+            Self::ElfNotFound { path } => {
+                write!(f, "ELF binary not found at: {}", path.display())
+            }
+            Self::InvalidBlob { path, message } => {
+                write!(f, "Invalid PolkaVM blob {}: {message}", path.display())
+            }
+            Self::NoBinaryTargets => write!(f, "No binary targets found in Cargo.toml"),
+            Self::TemplateError { context } => write!(f, "{context}"),
+            Self::ToolchainNotInstalled { toolchain } => write!(
+                f,
+                "Toolchain `{toolchain}` is not installed; run `rustup toolchain install {toolchain} --component rust-src`"
+            ),
+            Self::RustSrcMissing { toolchain } => write!(
+                f,
+                "rust-src component is required; run `rustup component add rust-src --toolchain {toolchain}`"
+            ),
+            Self::UnsupportedToolchain { version } => write!(
+                f,
+                "{version} does not support `-Cpanic=immediate-abort`, which contracts are built with; use a toolchain from 1.92 on, e.g. `rustup update nightly`"
+            ),
+            Self::Manifest { path, message } => write!(f, "{}: {message}", path.display()),
+            Self::InvalidConfig { message } => write!(f, "{message}"),
+            Self::Io { context, .. } | Self::Json { context, .. } => write!(f, "{context}"),
+            Self::Multiple { errors } => {
+                write!(f, "{} binaries failed:", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  {error}")?;
+                    let mut source = std::error::Error::source(error);
+                    while let Some(cause) = source {
+                        write!(f, ": {cause}")?;
+                        source = cause.source();
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Join `names` as "`a`, `b`".
+fn quoted_list(names: &[String]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+impl std::error::Error for PvmBuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Attach a description of what was being done to I/O and JSON errors.
+pub(crate) trait ErrorContext<T> {
+    fn context_with(self, context: impl FnOnce() -> String) -> Result<T, PvmBuildError>;
+}
+
+impl<T> ErrorContext<T> for Result<T, io::Error> {
+    fn context_with(self, context: impl FnOnce() -> String) -> Result<T, PvmBuildError> {
+        self.map_err(|source| PvmBuildError::Io {
+            context: context(),
+            source,
+        })
+    }
+}
+
+impl<T> ErrorContext<T> for Result<T, serde_json::Error> {
+    fn context_with(self, context: impl FnOnce() -> String) -> Result<T, PvmBuildError> {
+        self.map_err(|source| PvmBuildError::Json {
+            context: context(),
+            source,
+        })
+    }
+}
@@ -7,16 +7,139 @@
 //! ```no_run
 //! cargo_pvm_contract_builder::PvmBuilder::new().build();
 //! ```
+//!
+//! The path of each linked binary is exported to the crate as
+//! `PVM_BINARY_<BIN_NAME>` (upper-cased, non-alphanumerics replaced by `_`):
+//!
+//! ```ignore
+//! const CONTRACT: &[u8] = include_bytes!(env!("PVM_BINARY_MYTOKEN"));
+//! ```
+//!
+//! Use [`PvmBuilder::try_build`] to handle failures yourself and get a
+//! [`BuildOutput`] describing the produced `.polkavm` files:
+//!
+//! ```no_run
+//! fn main() -> anyhow::Result<()> {
+//!     let output = cargo_pvm_contract_builder::PvmBuilder::new().try_build()?;
+//!     for artifact in output.artifacts {
+//!         println!(
+//!             "cargo:warning=built {} ({} bytes)",
+//!             artifact.path.display(),
+//!             artifact.size
+//!         );
+//!     }
+//!     Ok(())
+//! }
+//! ```
 
-use anyhow::{Context, Result};
+use error::ErrorContext;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
-    env, fs,
+    collections::HashMap,
+    env,
+    ffi::OsString,
+    fs,
+    io::{BufRead, BufReader},
     path::{Path, PathBuf},
-    process::Command,
+    process::{Command, Stdio},
+    time::Instant,
 };
+use symbols::ImportAllowlist;
+use tiny_keccak::{Hasher, Keccak};
+
+pub mod abi_consts;
+pub mod bundle;
+mod disassembly;
+mod error;
+mod symbols;
+
+pub use bundle::AbiSource;
+pub use error::PvmBuildError;
+pub use polkavm_linker::TargetInstructionSet;
+
+type Result<T, E = PvmBuildError> = std::result::Result<T, E>;
+
+/// Environment variable set on the nested PolkaVM build to prevent recursive builds.
+///
+/// Build scripts that do expensive work of their own can check it, or call
+/// [`PvmBuilder::is_recursive_build`], to skip that work in the nested build.
+pub const INTERNAL_BUILD_ENV: &str = "CARGO_PVM_CONTRACT_INTERNAL";
+
+/// Environment variable overriding where the `.polkavm` files are written.
+const OUTPUT_DIR_ENV: &str = "PVM_OUTPUT_DIR";
+
+/// Environment variable overriding the nested build's target directory.
+const BUILD_DIR_ENV: &str = "PVM_BUILD_DIR";
+
+/// Environment variable overriding the contract's build profile, e.g. `release`.
+const PROFILE_ENV: &str = "PVM_PROFILE";
+
+/// Cargo's environment variable for `net.offline`, honoured by the nested build.
+const NET_OFFLINE_ENV: &str = "CARGO_NET_OFFLINE";
+
+/// Environment variable pointing at the `solc` executable (default: `solc` from `PATH`).
+const SOLC_PATH_ENV: &str = "SOLC_PATH";
 
-/// Internal environment variable to prevent recursive builds.
-const INTERNAL_BUILD_ENV: &str = "CARGO_PVM_CONTRACT_INTERNAL";
+/// Environment variable enabling live output of the nested cargo build.
+const VERBOSE_ENV: &str = "PVM_BUILD_VERBOSE";
+
+/// File name of the report written by [`PvmBuilder::with_report`] into a directory.
+const REPORT_FILE: &str = "pvm-build-report.json";
+
+/// Version of the `polkavm-linker` dependency; keep in sync with the workspace `Cargo.toml`.
+const POLKAVM_LINKER_VERSION: &str = "0.30.0";
+
+/// File name of the module written by [`PvmBuilder::with_generated_module`].
+const GENERATED_MODULE_FILE: &str = "pvm_contracts.rs";
+
+/// Environment variable pointing scaffolded projects at a local checkout of this crate.
+const BUILDER_PATH_ENV: &str = "CARGO_PVM_CONTRACT_BUILDER_PATH";
+
+/// Environment variable enabling the per-phase timing summary of a build.
+const TIMINGS_ENV: &str = "CARGO_PVM_CONTRACT_TIMINGS";
+
+/// Environment variable turning [`PvmBuilder::build`] into [`PvmBuilder::check`].
+const CHECK_ONLY_ENV: &str = "PVM_CHECK_ONLY";
+
+/// Rustc wrappers of tools that only need the contract to type-check.
+const CHECK_ONLY_WRAPPERS: &[&str] = &["rust-analyzer", "clippy-driver"];
+
+/// Environment variable the scaffolded allocator reads its heap size from at compile time.
+const HEAP_SIZE_ENV: &str = "PVM_HEAP_SIZE";
+
+/// Environment variables the builder reads, through [`env_var_os`], or leaves to the
+/// nested build. A change to any of them re-runs the build script.
+///
+/// Variables cargo sets for the build script itself (`OUT_DIR`, `CARGO`, ...) are
+/// not listed: cargo already re-runs it when they change.
+const WATCHED_ENV: &[&str] = &[
+    "PROFILE",
+    PROFILE_ENV,
+    "RUSTFLAGS",
+    NET_OFFLINE_ENV,
+    INTERNAL_BUILD_ENV,
+    OUTPUT_DIR_ENV,
+    BUILD_DIR_ENV,
+    "CARGO_TARGET_DIR",
+    BUILDER_PATH_ENV,
+    VERBOSE_ENV,
+    CHECK_ONLY_ENV,
+    TIMINGS_ENV,
+    SOLC_PATH_ENV,
+    "SOURCE_DATE_EPOCH",
+    "RUSTUP_TOOLCHAIN",
+    "CARGO_HOME",
+    "HOME",
+    "RUSTC_WORKSPACE_WRAPPER",
+    "RUSTC_WRAPPER",
+];
+
+/// Static memory (RW data plus stack) pallet-revive allows a contract to declare.
+///
+/// Mirrors `pallet_revive::limits::code::STATIC_MEMORY_BYTES` in polkadot-sdk
+/// (`substrate/frame/revive/src/limits.rs`).
+const PALLET_REVIVE_STATIC_MEMORY_LIMIT: u32 = 2 * 1024 * 1024;
 
 /// The builder for building a PolkaVM binary.
 pub struct PvmBuilder {
@@ -24,6 +147,304 @@ pub struct PvmBuilder {
     project_cargo_toml: PathBuf,
     /// Specific binaries to build (None = all binaries).
     bin_names: Option<Vec<String>>,
+    /// Binaries to leave out of the selection.
+    excluded_bins: Vec<String>,
+    /// Build the library target instead of binaries (otherwise detected from the manifest).
+    lib: bool,
+    /// Workspace packages to build instead of the manifest's own package.
+    packages: Vec<String>,
+    /// Build every workspace member with contract targets.
+    workspace: bool,
+    /// Build workspace members into one nested target directory instead of one each.
+    shared_target_dir: bool,
+    /// Cargo features to enable for the contract build.
+    features: Vec<String>,
+    /// Features of the calling crate enabled on the contract build when they're enabled here.
+    forwarded_features: Vec<String>,
+    /// Pass `--all-features` to the contract build.
+    all_features: bool,
+    /// Pass `--no-default-features` to the contract build.
+    no_default_features: bool,
+    /// Pass `--locked` to the contract build.
+    locked: bool,
+    /// Pass `--offline` to the contract build, as `CARGO_NET_OFFLINE` also does.
+    offline: bool,
+    /// Pass `--frozen` to the contract build, implying `--locked` and `--offline`.
+    frozen: bool,
+    /// Raw arguments appended to the contract's `cargo build` after the builder's own.
+    cargo_args: Vec<OsString>,
+    /// Environment variables set on the contract build.
+    envs: Vec<(String, OsString)>,
+    /// Environment variables copied from the current environment into the contract build.
+    env_passthrough: Vec<String>,
+    /// Start the contract build from a minimal environment instead of inheriting ours.
+    clean_env: bool,
+    /// Crates passed to `-Zbuild-std` (None = `core` and `alloc`).
+    build_std: Option<Vec<String>>,
+    /// Features passed to `-Zbuild-std-features`.
+    build_std_features: Vec<String>,
+    /// Directory the `.polkavm` files are written to (None = target directory).
+    output_dir: Option<PathBuf>,
+    /// Profile for the contract build (None = `PVM_PROFILE` or the host's `PROFILE`).
+    profile: Option<Profile>,
+    /// Root directory for the nested build (None = `target/pvmbuild`).
+    target_dir: Option<PathBuf>,
+    /// Build in `target/pvmbuild/<package>` unless a target directory is set.
+    package_target_dir: bool,
+    /// Options passed to the PolkaVM linker.
+    linker_options: LinkerOptions,
+    /// Additional flags appended to the nested build's `RUSTFLAGS`.
+    extra_rustflags: Vec<String>,
+    /// Build for the 64-bit PolkaVM target (false = 32-bit).
+    is_64_bit: bool,
+    /// Instruction set the binaries are linked for (None = `ReviveV1`, or `Latest` for 32-bit).
+    instruction_set: Option<TargetInstructionSet>,
+    /// Name binaries `<bin>.<isa>.<profile>.polkavm`.
+    instruction_set_in_file_name: bool,
+    /// Rustup toolchain for the nested build (None = the contract's `rust-toolchain.toml`).
+    toolchain: Option<String>,
+    /// Merge the caller's `RUSTFLAGS` into the nested build's flags.
+    inherit_rustflags: bool,
+    /// Value of `PVM_HEAP_SIZE` in the nested build (None = unset, the contract default).
+    heap_size: Option<u32>,
+    /// File or directory the linked binary is copied to after the build.
+    copy_to: Option<PathBuf>,
+    /// Write `$OUT_DIR/pvm_contracts.rs` embedding every linked binary.
+    generated_module: bool,
+    /// Stream the nested build's output (None = `PVM_BUILD_VERBOSE`).
+    verbose: Option<bool>,
+    /// File or directory the JSON build report is written to.
+    report: Option<PathBuf>,
+    /// Remap host paths and pin `SOURCE_DATE_EPOCH` so builds are byte-identical across machines.
+    reproducible: bool,
+    /// Write the hex digest of each binary to `<bin>.polkavm.<algorithm>` next to it.
+    code_hash_file: Option<CodeHash>,
+    /// Skip the nested build when its fingerprint matches the previous build.
+    fingerprint: bool,
+    /// Write `<bin>.abi.json` from the package's Solidity file next to each binary.
+    abi_output: bool,
+    /// Check the constants recorded in `pvm-abi-consts.json` against the Solidity ABI.
+    verify_abi_consts: bool,
+    /// Write a `<bin>.contract.json` bundle with the ABI from this source next to each binary.
+    bundle: Option<AbiSource>,
+    /// Fail the build if a linked binary is larger than this many bytes.
+    max_size: Option<u64>,
+    /// Warn if a linked binary is larger than this many bytes.
+    size_warning: Option<u64>,
+    /// Entry points every linked binary must export.
+    required_exports: Vec<String>,
+    /// Host functions linked binaries may import.
+    allowed_imports: ImportAllowlist,
+    /// Write a `<bin>.exports.json` listing of exports and imports next to each binary.
+    exports_listing: bool,
+    /// Write a `<bin>.pvmasm` disassembly listing next to each binary.
+    disassembly: bool,
+    /// Copy the intermediate ELF to `<bin>.elf` next to each binary.
+    keep_elf: bool,
+    /// Encodings of the binaries written to the output directory.
+    output_format: OutputFormat,
+    /// Write a gzip-compressed `<bin>.polkavm.gz` next to each binary.
+    gzip_output: bool,
+    /// Pass `--timings` to the nested build and copy its report next to the binaries.
+    cargo_timings: bool,
+    /// Collect the executables of the nested build in [`BuildOutput::compiler_artifacts`].
+    compiler_artifacts: bool,
+    /// Link unstripped and unoptimized, and keep panic messages, for local debugging.
+    debug_blob: bool,
+    /// Only type-check the contract with `cargo check`, see [`PvmBuilder::check`].
+    check_only: bool,
+}
+
+/// Everything produced by a build.
+///
+/// This is also the format of the report written by [`PvmBuilder::with_report`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct BuildOutput {
+    /// One entry per linked binary.
+    pub artifacts: Vec<Artifact>,
+    /// Output of `rustc --version` for the toolchain that built the contract.
+    pub rustc_version: Option<String>,
+    /// Version of `polkavm-linker` the binaries were linked with.
+    pub polkavm_linker_version: String,
+    /// Wall-clock time of the nested cargo build in milliseconds.
+    pub cargo_build_ms: u64,
+    /// Wall-clock time of linking all binaries in milliseconds.
+    #[serde(default)]
+    pub link_ms: u64,
+    /// Wall-clock time of the whole build in milliseconds.
+    #[serde(default)]
+    pub total_ms: u64,
+    /// Executables reported by the nested build, see [`PvmBuilder::with_compiler_artifacts`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub compiler_artifacts: Vec<PathBuf>,
+    /// Instruction set the binaries were linked for, e.g. `revive_v1`.
+    #[serde(default)]
+    pub instruction_set: String,
+    /// The binaries were built by [`PvmBuilder::with_debug_blob`] and must not be deployed.
+    #[serde(default)]
+    pub debug_blob: bool,
+}
+
+/// Word size of the PolkaVM target a contract is built for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Bits {
+    /// `riscv32emac`.
+    B32,
+    /// `riscv64emac`, the only target pallet-revive runs.
+    #[default]
+    B64,
+}
+
+/// Encodings each linked program is written to the output directory in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// `<bin>.polkavm`, the program blob itself.
+    #[default]
+    Raw,
+    /// `<bin>.polkavm.hex`, the blob as a `0x`-prefixed hex string, e.g. for JSON-RPC payloads.
+    Hex,
+    /// Both the blob and its hex encoding.
+    Both,
+}
+
+impl OutputFormat {
+    fn raw(self) -> bool {
+        matches!(self, Self::Raw | Self::Both)
+    }
+
+    fn hex(self) -> bool {
+        matches!(self, Self::Hex | Self::Both)
+    }
+}
+
+/// Digest written by [`PvmBuilder::with_code_hash_file`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CodeHash {
+    /// Keccak-256, the code hash pallet-revive reports, in `<bin>.polkavm.keccak`.
+    Keccak256,
+    /// SHA-256, e.g. for artifact stores that index by it, in `<bin>.polkavm.sha256`.
+    Sha256,
+}
+
+impl CodeHash {
+    fn digest(self, data: &[u8]) -> [u8; 32] {
+        match self {
+            Self::Keccak256 => keccak256(data),
+            Self::Sha256 => Sha256::digest(data).into(),
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Keccak256 => "keccak",
+            Self::Sha256 => "sha256",
+        }
+    }
+}
+
+/// A linked PolkaVM binary.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Artifact {
+    /// Name of the binary target.
+    pub name: String,
+    /// Path of the `.polkavm` file.
+    pub path: PathBuf,
+    /// Path of the intermediate ELF file.
+    pub elf_path: PathBuf,
+    /// Path of the copy of the ELF made by [`PvmBuilder::with_keep_elf`].
+    #[serde(default)]
+    pub kept_elf_path: Option<PathBuf>,
+    /// Path of the `.polkavm.hex` file written for [`OutputFormat::Hex`].
+    #[serde(default)]
+    pub hex_path: Option<PathBuf>,
+    /// Path of the `.polkavm.gz` file made by [`PvmBuilder::with_gzip_output`].
+    #[serde(default)]
+    pub gzip_path: Option<PathBuf>,
+    /// Path of the `.contract.json` bundle made by [`PvmBuilder::with_bundle`].
+    #[serde(default)]
+    pub bundle_path: Option<PathBuf>,
+    /// Path of the digest file made by [`PvmBuilder::with_code_hash_file`].
+    #[serde(default)]
+    pub code_hash_path: Option<PathBuf>,
+    /// Size of the `.polkavm` file in bytes.
+    pub size: u64,
+    /// Size of the intermediate ELF file in bytes.
+    pub elf_size: u64,
+    /// Time spent linking the ELF in milliseconds.
+    pub link_ms: u64,
+    /// `0x`-prefixed keccak-256 of the `.polkavm` file, the code hash pallet-revive reports.
+    #[serde(default)]
+    pub code_hash: String,
+    /// Names of the entry points the binary exports, e.g. `deploy` and `call`.
+    #[serde(default)]
+    pub exports: Vec<String>,
+    /// Host functions the binary imports.
+    #[serde(default)]
+    pub imports: Vec<Import>,
+}
+
+/// A host function imported by a PolkaVM binary.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Import {
+    /// Index the binary's `ecalli` instructions refer to the import by.
+    pub index: u32,
+    /// Symbol of the host function, e.g. `call_data_size`.
+    pub name: String,
+}
+
+/// Options controlling how the ELF is linked into a PolkaVM program.
+#[derive(Clone, Debug)]
+pub struct LinkerOptions {
+    /// Strip symbol names and debug info from the program.
+    pub strip: bool,
+    /// Run the linker's optimization passes.
+    pub optimize: bool,
+    /// Exports placed in the dispatch table, in order.
+    pub dispatch_table: Vec<String>,
+    /// Minimum stack size in bytes (None = the linker default).
+    pub min_stack_size: Option<u32>,
+}
+
+/// PolkaVM page size; the linker rounds the stack size up to a multiple of it.
+const VM_PAGE_SIZE: u32 = 0x1000;
+
+/// Largest stack size that can still be rounded up to a whole page.
+const MAX_MIN_STACK_SIZE: u32 = u32::MAX - (VM_PAGE_SIZE - 1);
+
+impl Default for LinkerOptions {
+    fn default() -> Self {
+        Self {
+            strip: true,
+            optimize: true,
+            dispatch_table: Vec::new(),
+            min_stack_size: None,
+        }
+    }
+}
+
+impl LinkerOptions {
+    fn to_config(&self) -> Result<polkavm_linker::Config> {
+        let mut config = polkavm_linker::Config::default();
+        config.set_strip(self.strip);
+        config.set_optimize(self.optimize);
+        config.set_dispatch_table(
+            self.dispatch_table
+                .iter()
+                .map(|name| name.as_bytes().to_vec())
+                .collect(),
+        );
+
+        if let Some(size) = self.min_stack_size {
+            if size == 0 || size > MAX_MIN_STACK_SIZE {
+                return Err(PvmBuildError::invalid_config(format!(
+                    "Invalid minimum stack size {size}: must be between 1 and {MAX_MIN_STACK_SIZE} bytes"
+                )));
+            }
+            config.set_min_stack_size(size);
+        }
+
+        Ok(config)
+    }
 }
 
 impl Default for PvmBuilder {
@@ -35,9 +456,92 @@ impl Default for PvmBuilder {
 impl PvmBuilder {
     /// Create a new builder for the current project.
     pub fn new() -> Self {
+        Self::for_manifest(get_manifest_dir().join("Cargo.toml"))
+    }
+
+    /// Create a builder for a contract crate other than the current one, e.g. one
+    /// of several under `contracts/*/Cargo.toml`.
+    ///
+    /// A relative `manifest_path` is resolved against the directory of the crate
+    /// whose build script runs; other relative paths, e.g. of
+    /// [`with_output_dir`](Self::with_output_dir), against the contract's directory.
+    /// Unless [`with_target_dir`](Self::with_target_dir) is set, the contract is
+    /// built in `target/pvmbuild/<package>`, so several contracts don't share one.
+    ///
+    /// ```no_run
+    /// use cargo_pvm_contract_builder::PvmBuilder;
+    ///
+    /// for contract in ["token", "vault"] {
+    ///     PvmBuilder::new_with_manifest(format!("contracts/{contract}/Cargo.toml")).build();
+    /// }
+    /// ```
+    pub fn new_with_manifest(manifest_path: impl AsRef<Path>) -> Self {
+        let mut builder = Self::for_manifest(get_manifest_dir().join(manifest_path));
+        builder.package_target_dir = true;
+        builder
+    }
+
+    /// Create a builder for the project described by the given `Cargo.toml`.
+    ///
+    /// Outside of a build script, set the profile, target and output directories
+    /// explicitly since there is no host build to derive them from.
+    pub fn for_manifest(project_cargo_toml: PathBuf) -> Self {
         Self {
-            project_cargo_toml: get_manifest_dir().join("Cargo.toml"),
+            project_cargo_toml,
             bin_names: None,
+            excluded_bins: Vec::new(),
+            lib: false,
+            packages: Vec::new(),
+            workspace: false,
+            features: Vec::new(),
+            forwarded_features: Vec::new(),
+            all_features: false,
+            no_default_features: false,
+            locked: false,
+            offline: false,
+            frozen: false,
+            cargo_args: Vec::new(),
+            envs: Vec::new(),
+            env_passthrough: Vec::new(),
+            clean_env: false,
+            build_std: None,
+            build_std_features: Vec::new(),
+            output_dir: None,
+            profile: None,
+            target_dir: None,
+            package_target_dir: false,
+            linker_options: LinkerOptions::default(),
+            extra_rustflags: Vec::new(),
+            is_64_bit: true,
+            instruction_set: None,
+            instruction_set_in_file_name: false,
+            toolchain: None,
+            inherit_rustflags: true,
+            heap_size: None,
+            copy_to: None,
+            generated_module: false,
+            verbose: None,
+            report: None,
+            reproducible: false,
+            shared_target_dir: false,
+            code_hash_file: None,
+            fingerprint: true,
+            abi_output: false,
+            verify_abi_consts: false,
+            bundle: None,
+            max_size: None,
+            size_warning: None,
+            required_exports: vec!["deploy".to_string(), "call".to_string()],
+            allowed_imports: ImportAllowlist::default(),
+            exports_listing: false,
+            disassembly: false,
+            keep_elf: false,
+            output_format: OutputFormat::Raw,
+            gzip_output: false,
+            cargo_timings: false,
+            compiler_artifacts: false,
+            debug_blob: false,
+            check_only: false,
         }
     }
 
@@ -57,216 +561,5123 @@ impl PvmBuilder {
         self
     }
 
-    /// Build the PolkaVM binary.
-    pub fn build(self) {
-        // Check if we're in a recursive build
-        if env::var(INTERNAL_BUILD_ENV).is_ok() {
-            return;
-        }
+    /// Don't build the specified binary, e.g. a host-side helper in the same crate.
+    ///
+    /// Can be called multiple times, and combined with [`with_bins`](Self::with_bins).
+    pub fn without_bin(mut self, name: impl Into<String>) -> Self {
+        self.excluded_bins.push(name.into());
+        self
+    }
 
-        if let Err(e) = build_project(&self.project_cargo_toml, self.bin_names) {
-            eprintln!("PolkaVM build failed: {e}");
-            std::process::exit(1);
-        }
+    /// Build the crate's `cdylib` library target instead of its binaries.
+    ///
+    /// This is detected automatically for crates with a `cdylib` library and no `[[bin]]`.
+    pub fn with_lib(mut self) -> Self {
+        self.lib = true;
+        self
     }
-}
 
-/// Returns the manifest dir from the `CARGO_MANIFEST_DIR` env.
-fn get_manifest_dir() -> PathBuf {
-    env::var("CARGO_MANIFEST_DIR")
-        .expect("`CARGO_MANIFEST_DIR` is always set for `build.rs` files")
-        .into()
-}
+    /// Build the contracts of the given workspace package instead of this crate's own.
+    ///
+    /// Can be called multiple times. Each package is built into `pvmbuild/<package>/`.
+    pub fn with_package(mut self, name: impl Into<String>) -> Self {
+        self.packages.push(name.into());
+        self
+    }
 
-/// Detect the build profile from the environment.
-#[derive(Clone, Debug)]
-struct Profile {
-    name: String,
-}
+    /// Build every workspace member with `[[bin]]` or `cdylib` targets, except this crate.
+    ///
+    /// Each package is built into `pvmbuild/<package>/`, unless the directory is shared
+    /// with [`with_shared_target_dir`](Self::with_shared_target_dir).
+    pub fn with_workspace(mut self) -> Self {
+        self.workspace = true;
+        self
+    }
 
-impl Profile {
-    fn detect() -> Self {
-        let name = env::var("PROFILE").unwrap_or_else(|_| "debug".to_string());
-        Self { name }
+    /// Build the packages selected with [`with_package`](Self::with_package) or
+    /// [`with_workspace`](Self::with_workspace) into a single nested target directory.
+    ///
+    /// By default each package gets its own, so `core` and `alloc` are compiled from
+    /// source once per contract. Sharing the directory compiles them once; cargo's
+    /// lock on the directory serializes concurrent builds.
+    pub fn with_shared_target_dir(mut self) -> Self {
+        self.shared_target_dir = true;
+        self
     }
 
-    fn cargo_arg(&self) -> &str {
-        if self.name == "debug" {
-            "dev"
-        } else {
-            self.name.as_str()
-        }
+    /// Enable the given cargo features for the contract build.
+    ///
+    /// Can be called multiple times; features accumulate.
+    pub fn with_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.features.extend(features.into_iter().map(Into::into));
+        self
     }
 
-    fn directory(&self) -> &str {
-        self.name.as_str()
+    /// Enable the given features on the contract build whenever they're enabled on the crate
+    /// whose build script runs the builder, e.g. a `testnet` feature switching constants.
+    ///
+    /// The contract crate must declare each of them. Can be called multiple times;
+    /// features accumulate.
+    pub fn with_forwarded_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.forwarded_features
+            .extend(features.into_iter().map(Into::into));
+        self
     }
-}
 
-/// Get the workspace target directory.
-fn get_target_root() -> PathBuf {
-    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set"));
+    /// Enable all features of the contract. Cannot be combined with [`Self::with_features`].
+    pub fn with_all_features(mut self) -> Self {
+        self.all_features = true;
+        self
+    }
 
-    for ancestor in out_dir.ancestors() {
-        if ancestor.file_name().map(|n| n == "target").unwrap_or(false) {
-            return ancestor.to_path_buf();
-        }
+    /// Disable the contract's default features.
+    pub fn with_no_default_features(mut self) -> Self {
+        self.no_default_features = true;
+        self
     }
 
-    out_dir
-}
+    /// Require the contract's `Cargo.lock` to be up to date, like `cargo build --locked`.
+    ///
+    /// A build script can't tell whether the outer build was run with `--locked`,
+    /// so this has to be enabled explicitly.
+    pub fn with_locked(mut self) -> Self {
+        self.locked = true;
+        self
+    }
 
-/// Get the build output directory.
-fn get_build_dir() -> PathBuf {
-    get_target_root().join("pvmbuild")
-}
+    /// Build the contract without network access, like `cargo build --offline`.
+    ///
+    /// Also enabled when the `CARGO_NET_OFFLINE` environment variable is set.
+    pub fn with_offline(mut self) -> Self {
+        self.offline = true;
+        self
+    }
 
-/// Get the list of binary targets from Cargo.toml.
-fn get_bin_targets(cargo_toml: &Path) -> Result<Vec<String>> {
-    let content = fs::read_to_string(cargo_toml)
-        .with_context(|| format!("Failed to read {}", cargo_toml.display()))?;
+    /// Build the contract like `cargo build --frozen`: both locked and offline.
+    ///
+    /// Takes precedence over [`PvmBuilder::with_locked`] and [`PvmBuilder::with_offline`].
+    pub fn with_frozen(mut self) -> Self {
+        self.frozen = true;
+        self
+    }
 
-    let doc: toml_edit::DocumentMut = content.parse().context("Failed to parse Cargo.toml")?;
+    /// Append a raw argument to the contract's `cargo build`.
+    ///
+    /// An escape hatch for flags the builder doesn't model; see [`PvmBuilder::with_cargo_args`].
+    pub fn with_cargo_arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.cargo_args.push(arg.into());
+        self
+    }
 
-    let mut bins = Vec::new();
+    /// Append raw arguments to the contract's `cargo build`, e.g.
+    /// `["--config", "profile.release.lto='fat'"]`.
+    ///
+    /// Can be called multiple times; arguments accumulate and come after the
+    /// builder's own flags so they can override them. Beyond rejecting flags that
+    /// would move the ELF somewhere the builder doesn't look (`--target-dir`,
+    /// `--manifest-path`, `--target`, `--profile`, `--release`), the arguments are
+    /// not checked: making sense of them is up to the caller.
+    pub fn with_cargo_args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        self.cargo_args.extend(args.into_iter().map(Into::into));
+        self
+    }
 
-    if let Some(bin_array) = doc.get("bin").and_then(|b| b.as_array_of_tables()) {
-        for bin in bin_array {
-            if let Some(name) = bin.get("name").and_then(|n| n.as_str()) {
-                bins.push(name.to_string());
-            }
-        }
+    /// Set an environment variable for the contract build, e.g. one the contract reads with `env!`.
+    ///
+    /// Variables the builder manages itself, like `RUSTFLAGS`, can't be overridden this way.
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<OsString>) -> Self {
+        self.envs.push((key.into(), value.into()));
+        self
     }
 
-    if bins.is_empty()
-        && let Some(name) = doc
-            .get("package")
-            .and_then(|p| p.get("name"))
-            .and_then(|n| n.as_str())
+    /// Pass the given variables of the current environment on to the contract build.
+    ///
+    /// Needed with [`PvmBuilder::with_clean_env`]; either way the build script is
+    /// re-run and the contract rebuilt when one of them changes.
+    pub fn with_env_passthrough<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
     {
-        bins.push(name.to_string());
+        self.env_passthrough
+            .extend(keys.into_iter().map(Into::into));
+        self
     }
 
-    Ok(bins)
-}
+    /// Build the contract in a minimal environment instead of inheriting the caller's.
+    ///
+    /// Only `PATH`, the cargo and rustup variables, a few OS essentials and the
+    /// variables given to [`PvmBuilder::with_env`] and
+    /// [`PvmBuilder::with_env_passthrough`] reach the contract build. This keeps
+    /// variables of the host build, like `CARGO_FEATURE_*`, out of the contract.
+    pub fn with_clean_env(mut self, enabled: bool) -> Self {
+        self.clean_env = enabled;
+        self
+    }
 
-/// Build the project.
-fn build_project(project_cargo_toml: &Path, bin_names: Option<Vec<String>>) -> Result<()> {
-    let profile = Profile::detect();
-    let build_dir = get_build_dir();
-    let target_root = get_target_root();
+    /// Rebuild only the given standard library crates instead of `core` and `alloc`.
+    ///
+    /// Contracts that don't allocate can pass just `["core"]` to skip building `alloc`.
+    /// The list must not be empty.
+    pub fn with_build_std<I, S>(mut self, crates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.build_std = Some(crates.into_iter().map(Into::into).collect());
+        self
+    }
 
-    let bins_to_build = match bin_names {
-        Some(names) => names,
-        None => get_bin_targets(project_cargo_toml)?,
-    };
+    /// Enable features of the rebuilt standard library, e.g. `optimize_for_size`.
+    ///
+    /// Can be called multiple times; features accumulate.
+    pub fn with_build_std_features<I, S>(mut self, features: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.build_std_features
+            .extend(features.into_iter().map(Into::into));
+        self
+    }
 
-    if bins_to_build.is_empty() {
-        anyhow::bail!("No binary targets found in Cargo.toml");
+    /// Write the `.polkavm` files to the given directory.
+    ///
+    /// Relative paths are resolved against the contract's manifest directory, and the
+    /// directory is created if missing. The `PVM_OUTPUT_DIR` environment variable takes
+    /// precedence over this setting.
+    pub fn with_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(dir.into());
+        self
     }
 
-    let target_dir = build_dir;
-    build_elf(project_cargo_toml, &target_dir, &profile, &bins_to_build)?;
+    /// Use the given directory as the nested build's target directory instead of
+    /// `target/pvmbuild`, e.g. to keep intermediate state on a RAM disk or in a shared
+    /// cache. Relative paths are resolved against the manifest directory. The
+    /// `PVM_BUILD_DIR` environment variable takes precedence over this setting.
+    pub fn with_target_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.target_dir = Some(dir.into());
+        self
+    }
 
-    // Link each ELF to PolkaVM
-    let elf_dir = target_dir
-        .join("riscv64emac-unknown-none-polkavm")
-        .join(profile.directory());
+    /// The target directory of the nested build, where the intermediate ELF files live.
+    pub fn target_dir(&self) -> PathBuf {
+        resolve_dir(
+            self.target_dir.as_deref(),
+            env_dir(BUILD_DIR_ENV),
+            self.manifest_dir(),
+            get_build_dir,
+        )
+    }
 
-    for bin in &bins_to_build {
-        let elf_path = elf_dir.join(bin);
-        if !elf_path.exists() {
-            anyhow::bail!("ELF binary not found at: {}", elf_path.display());
+    /// The directory the `.polkavm` files are written to.
+    pub fn output_dir(&self) -> PathBuf {
+        resolve_dir(
+            self.output_dir.as_deref(),
+            env_dir(OUTPUT_DIR_ENV),
+            self.manifest_dir(),
+            get_target_root,
+        )
+    }
+
+    /// The target directory `cargo build` would use for the contract's workspace:
+    /// `CARGO_TARGET_DIR`, `build.target-dir` or `<workspace root>/target`.
+    ///
+    /// Runs `cargo metadata`; useful outside build scripts, where there is no `OUT_DIR`
+    /// to derive [`target_dir`](Self::target_dir) and [`output_dir`](Self::output_dir) from.
+    pub fn cargo_target_dir(&self) -> Result<PathBuf> {
+        Ok(workspace_metadata(&self.project_cargo_toml)?.target_directory)
+    }
+
+    /// The directory containing the contract's `Cargo.toml`.
+    fn manifest_dir(&self) -> &Path {
+        self.project_cargo_toml
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+    }
+
+    /// Append flags to the `RUSTFLAGS` of the nested build, e.g. `-C opt-level=z`.
+    ///
+    /// Can be called multiple times; flags accumulate after any inherited `RUSTFLAGS`.
+    /// The builder's own flags come last so `-Cpanic=immediate-abort` always wins.
+    pub fn with_extra_rustflags(mut self, flags: impl Into<String>) -> Self {
+        self.extra_rustflags.push(flags.into());
+        self
+    }
+
+    /// Build for the 32-bit PolkaVM target (`riscv32emac`) instead of the 64-bit one.
+    ///
+    /// pallet-revive only runs 64-bit programs, so 32-bit builds are linked for the
+    /// latest generic instruction set instead.
+    pub fn with_32bit(self) -> Self {
+        self.with_bits(Bits::B32)
+    }
+
+    /// Build for the PolkaVM target of the given word size (64-bit by default).
+    ///
+    /// Selects both the target JSON (`riscv32emac` or `riscv64emac`) and the ELF
+    /// directory the nested build writes to; see [`PvmBuilder::with_32bit`].
+    pub fn with_bits(mut self, bits: Bits) -> Self {
+        self.is_64_bit = bits == Bits::B64;
+        self
+    }
+
+    /// Link for `instruction_set` instead of [`TargetInstructionSet::ReviveV1`].
+    ///
+    /// The default is what pallet-revive runs; other instruction sets are useful to
+    /// test against nodes with a newer PolkaVM.
+    pub fn with_instruction_set(mut self, instruction_set: TargetInstructionSet) -> Self {
+        self.instruction_set = Some(instruction_set);
+        self
+    }
+
+    /// Include the instruction set in the name of each binary, e.g.
+    /// `my-token.revive_v1.release.polkavm`, so builds for several can sit side by side.
+    pub fn with_instruction_set_in_file_name(mut self, enabled: bool) -> Self {
+        self.instruction_set_in_file_name = enabled;
+        self
+    }
+
+    /// Build blobs for debugging with the PolkaVM interpreter or tracer.
+    ///
+    /// Linking skips stripping and optimization, and the contract is built without
+    /// `-Cpanic=immediate-abort` so panic messages survive. Such blobs are much larger
+    /// and must not be deployed: they are named `<bin>.<profile>.debug.polkavm` and
+    /// flagged as [`BuildOutput::debug_blob`] in the report.
+    pub fn with_debug_blob(mut self, enabled: bool) -> Self {
+        self.debug_blob = enabled;
+        self
+    }
+
+    /// Options the binaries are linked with, taking [`PvmBuilder::with_debug_blob`] into account.
+    fn effective_linker_options(&self) -> LinkerOptions {
+        if self.debug_blob {
+            LinkerOptions {
+                strip: false,
+                optimize: false,
+                ..self.linker_options.clone()
+            }
+        } else {
+            self.linker_options.clone()
         }
+    }
 
-        let output_path = target_root.join(format!("{}.{}.polkavm", bin, profile.directory()));
-        link_to_polkavm(&elf_path, &output_path)?;
+    /// Whether the nested build's output is streamed, see [`PvmBuilder::with_verbose`].
+    fn is_verbose(&self) -> bool {
+        self.verbose
+            .unwrap_or_else(|| is_truthy(env_var(VERBOSE_ENV).as_deref()))
     }
 
-    Ok(())
-}
+    /// Instruction set the binaries are linked for.
+    fn target_instruction_set(&self) -> TargetInstructionSet {
+        match self.instruction_set {
+            Some(instruction_set) => instruction_set,
+            None if self.is_64_bit => TargetInstructionSet::ReviveV1,
+            None => TargetInstructionSet::Latest,
+        }
+    }
 
-/// Build the ELF binary using cargo.
-fn build_elf(
-    manifest_path: &Path,
-    target_dir: &Path,
-    profile: &Profile,
-    bins: &[String],
-) -> Result<()> {
-    let rustflags = "-Zunstable-options -Cpanic=immediate-abort";
+    /// Don't merge the `RUSTFLAGS` of the surrounding build into the nested build.
+    ///
+    /// Useful when those flags only make sense for the host, e.g. `-C target-cpu=native`.
+    pub fn without_inherited_rustflags(mut self) -> Self {
+        self.inherit_rustflags = false;
+        self
+    }
 
-    let mut args = polkavm_linker::TargetJsonArgs::default();
-    args.is_64_bit = true;
-    let target_json = polkavm_linker::target_json_path(args)
-        .map_err(|e| anyhow::anyhow!("Failed to get target JSON: {e}"))?;
+    /// Build the contract with the given rustup toolchain, e.g. `nightly-2025-01-01`.
+    ///
+    /// The nested cargo and every rustc query run through rustup's proxies with
+    /// `RUSTUP_TOOLCHAIN` set, so a host project on stable can build contracts
+    /// that need a specific nightly. The toolchain must be installed along with
+    /// its `rust-src` component.
+    pub fn with_toolchain(mut self, toolchain: impl Into<String>) -> Self {
+        self.toolchain = Some(toolchain.into());
+        self
+    }
 
-    let cargo = env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
-    let work_dir = manifest_path.parent().context("Invalid manifest path")?;
+    /// Link with the given options instead of the default (stripped and optimized).
+    pub fn with_linker_options(mut self, options: LinkerOptions) -> Self {
+        self.linker_options = options;
+        self
+    }
 
-    let mut cmd = Command::new(&cargo);
-    cmd.current_dir(work_dir)
-        .env_remove("CARGO_ENCODED_RUSTFLAGS") // We set RUSTFLAGS, but cargo prefers this one
-        .env_remove("RUSTC") // Prevent host toolchain override from build.rs
-        .env("RUSTFLAGS", rustflags)
-        .env("CARGO_TARGET_DIR", target_dir)
-        // Disable strip during ELF build - it conflicts with --emit-relocs required by PolkaVM.
-        // Stripping is done later by polkavm_linker after processing relocations.
-        .env("CARGO_PROFILE_RELEASE_STRIP", "false")
-        .env("RUSTC_BOOTSTRAP", "1")
-        .env(INTERNAL_BUILD_ENV, "1")
-        .arg("build")
-        .arg("--manifest-path")
-        .arg(manifest_path)
-        .arg("--profile")
-        .arg(profile.cargo_arg())
-        .arg("--target")
-        .arg(&target_json)
-        .arg("-Zbuild-std=core,alloc");
+    /// Reserve at least `bytes` of stack for the contract, e.g. for deeply recursive code.
+    ///
+    /// The linker rounds the size up to a whole page.
+    pub fn with_min_stack_size(mut self, bytes: u32) -> Self {
+        self.linker_options.min_stack_size = Some(bytes);
+        self
+    }
 
-    for bin in bins {
-        cmd.arg("--bin").arg(bin);
+    /// Copy the linked `.polkavm` file to `dest` after the build.
+    ///
+    /// If `dest` is an existing directory the file is named `<bin>.polkavm`,
+    /// otherwise `dest` is used as the file path, which requires a single binary.
+    /// This pairs well with `include_bytes!(concat!(env!("OUT_DIR"), "/<bin>.polkavm"))`.
+    pub fn build_to(mut self, dest: impl Into<PathBuf>) -> Self {
+        self.copy_to = Some(dest.into());
+        self
     }
 
-    eprintln!("Building PolkaVM binary with profile: {profile:?}");
+    /// Write `$OUT_DIR/pvm_contracts.rs` with the bytecode and code hash of every binary.
+    ///
+    /// For a binary `mytoken` the module contains `MYTOKEN: &[u8]` and
+    /// `MYTOKEN_CODE_HASH: [u8; 32]` (the keccak-256 of the blob):
+    ///
+    /// ```ignore
+    /// include!(concat!(env!("OUT_DIR"), "/pvm_contracts.rs"));
+    /// ```
+    pub fn with_generated_module(mut self, enabled: bool) -> Self {
+        self.generated_module = enabled;
+        self
+    }
 
-    let output = cmd.output().context("Failed to execute cargo build")?;
+    /// Stream the output of the nested cargo build as it runs.
+    ///
+    /// Defaults to the `PVM_BUILD_VERBOSE` environment variable. Without it, only
+    /// warnings are forwarded, as `cargo:warning` lines.
+    pub fn with_verbose(mut self, verbose: bool) -> Self {
+        self.verbose = Some(verbose);
+        self
+    }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Cargo build failed:\n{stderr}");
+    /// Choose how the linked programs are written to the output directory.
+    ///
+    /// With [`OutputFormat::Hex`] alone, the blobs stay in the nested build's target
+    /// directory, where [`Artifact::path`] and the `PVM_BINARY_*` variables point.
+    pub fn with_output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
     }
 
-    Ok(())
-}
+    /// Also write each program gzip-compressed to `<bin>.polkavm.gz`, e.g. for artifact storage.
+    pub fn with_gzip_output(mut self, enabled: bool) -> Self {
+        self.gzip_output = enabled;
+        self
+    }
 
-/// Link an ELF binary to PolkaVM bytecode.
-fn link_to_polkavm(elf_path: &Path, output_path: &Path) -> Result<()> {
-    let elf_bytes = fs::read(elf_path)
-        .with_context(|| format!("Failed to read ELF from {}", elf_path.display()))?;
+    /// Copy the intermediate ELF of each binary to `<bin>.elf` next to it.
+    ///
+    /// Disabled by default. The copy is what tools like `objdump` and `addr2line`
+    /// need; its path is reported as [`Artifact::kept_elf_path`].
+    pub fn with_keep_elf(mut self, enabled: bool) -> Self {
+        self.keep_elf = enabled;
+        self
+    }
 
-    let mut config = polkavm_linker::Config::default();
-    config.set_strip(true);
-    config.set_optimize(true);
+    /// Run the nested build with `cargo build --timings` and copy cargo's HTML
+    /// report to `cargo-timing.html` next to the binaries.
+    ///
+    /// The report shows how much of the build goes into the standard library
+    /// versus the contract and its dependencies.
+    pub fn with_cargo_timings(mut self, enabled: bool) -> Self {
+        self.cargo_timings = enabled;
+        self
+    }
 
-    let linked = polkavm_linker::program_from_elf(
-        config,
-        polkavm_linker::TargetInstructionSet::ReviveV1,
-        &elf_bytes,
-    )
-    .map_err(|e| anyhow::anyhow!("Failed to link PolkaVM program: {e}"))?;
+    /// List the executables the nested build reports in [`BuildOutput::compiler_artifacts`].
+    ///
+    /// Diagnostics are handled the same either way: the nested build always runs with
+    /// `--message-format json`, errors are re-emitted with their rendered spans on stderr
+    /// and included in [`PvmBuildError::CargoFailed`], and warnings are forwarded tagged
+    /// with their binary.
+    pub fn with_compiler_artifacts(mut self) -> Self {
+        self.compiler_artifacts = true;
+        self
+    }
 
-    fs::write(output_path, &linked).with_context(|| {
-        format!(
-            "Failed to write PolkaVM bytecode to {}",
-            output_path.display()
-        )
-    })?;
+    /// Write a JSON report of the build (a serialized [`BuildOutput`]) to `path`.
+    ///
+    /// If `path` is an existing directory the report is named `pvm-build-report.json`.
+    /// Relative paths are resolved against the contract's manifest directory.
+    pub fn with_report(mut self, path: impl Into<PathBuf>) -> Self {
+        self.report = Some(path.into());
+        self
+    }
 
-    eprintln!(
-        "Created PolkaVM binary: {} ({} bytes)",
-        output_path.display(),
-        linked.len()
-    );
+    /// Make the build independent of where the project and toolchain live.
+    ///
+    /// The manifest and target directories, `CARGO_HOME` and the rustc sysroot are
+    /// remapped with `--remap-path-prefix`, and `SOURCE_DATE_EPOCH` is pinned to 0
+    /// unless already set. A warning is emitted if a linked binary still contains
+    /// one of these host paths.
+    pub fn with_reproducible(mut self, reproducible: bool) -> Self {
+        self.reproducible = reproducible;
+        self
+    }
 
-    Ok(())
+    /// Write the hex digest of each binary to `<bin>.polkavm.keccak` or
+    /// `<bin>.polkavm.sha256` in the output directory, depending on `algorithm`.
+    ///
+    /// The file sits next to the blob, or next to `<bin>.polkavm.hex` with
+    /// [`OutputFormat::Hex`] alone, as the blob then stays in the target directory.
+    ///
+    /// [`CodeHash::Keccak256`] is what pallet-revive reports as the `code_hash` after
+    /// upload; the report carries that hash whether or not a file is written.
+    pub fn with_code_hash_file(mut self, algorithm: CodeHash) -> Self {
+        self.code_hash_file = Some(algorithm);
+        self
+    }
+
+    /// Skip the nested build and linking when nothing it depends on changed.
+    ///
+    /// Enabled by default. The fingerprint covers each package's `Cargo.toml`, `src/`
+    /// and `sol!` files, the workspace's `Cargo.lock`, the same files of every local
+    /// package reached through `path` dependencies, the nested cargo invocation
+    /// (profile, features, flags), the linker options and the rustc and
+    /// `polkavm-linker` versions. Disable this if contracts pull in sources from
+    /// elsewhere, e.g. with `include!` or from a path dependency's `build.rs`.
+    pub fn with_fingerprint(mut self, enabled: bool) -> Self {
+        self.fingerprint = enabled;
+        self
+    }
+
+    /// Write the contract's ABI to `<bin>.abi.json` next to each binary.
+    ///
+    /// The ABI is compiled with `solc` from the `.sol` file in the package
+    /// directory named after the binary (`my-token` matches `MyToken.sol`).
+    /// Binaries without such a file are skipped.
+    pub fn with_abi_output(mut self) -> Self {
+        self.abi_output = true;
+        self
+    }
+
+    /// Before building, check the selector, event and error constants of no-alloc
+    /// contracts against their Solidity file.
+    ///
+    /// Applies to packages with a [`pvm-abi-consts.json`](abi_consts::ABI_CONSTS_FILE)
+    /// sidecar, as `cargo pvm-contract` scaffolds them. The ABI is compiled with `solc`
+    /// and the build fails listing every constant whose value no longer matches, with
+    /// the value found and the expected one. Packages can opt in on their own with
+    /// `verify-abi-consts = true` under `[package.metadata.pvm]`; those skip the check
+    /// with a warning when `solc` is not installed.
+    pub fn with_verify_abi_consts(mut self, enabled: bool) -> Self {
+        self.verify_abi_consts = enabled;
+        self
+    }
+
+    /// Write `<bin>.contract.json` next to each binary, bundling the hex bytecode,
+    /// its code hash, the ABI from `abi` and the toolchain and profile it was built
+    /// with. See [`bundle::ContractBundle`] for the format.
+    ///
+    /// With [`AbiSource::Solidity`], binaries without a `.sol` file fail the build.
+    pub fn with_bundle(mut self, abi: AbiSource) -> Self {
+        self.bundle = Some(abi);
+        self
+    }
+
+    /// Fail the build if a linked binary is larger than `bytes`.
+    ///
+    /// pallet-revive rejects blobs above its configured limit on upload; this
+    /// surfaces the problem at build time instead.
+    pub fn with_max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Warn, without failing the build, if a linked binary is larger than `bytes`.
+    pub fn with_size_warning(mut self, bytes: u64) -> Self {
+        self.size_warning = Some(bytes);
+        self
+    }
+
+    /// Fail the build if a linked binary doesn't export all of `exports`.
+    ///
+    /// Defaults to pallet-revive's `deploy` and `call`, so a missing
+    /// `#[polkavm_derive::polkavm_export]` is caught at build time rather than on
+    /// instantiation. Pass the entry points of other runtimes, or none to skip the check.
+    pub fn with_required_exports<I, S>(mut self, exports: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.required_exports = exports.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Fail the build if a linked binary imports a host function not in `imports`.
+    ///
+    /// Defaults to the host functions of the current pallet-revive, so code calling
+    /// an API the runtime doesn't provide fails at build time rather than on chain.
+    pub fn with_allowed_imports<I, S>(mut self, imports: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_imports =
+            ImportAllowlist::Names(imports.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Like [`with_allowed_imports`](Self::with_allowed_imports), reading the names from
+    /// a JSON array in `path`, relative to the manifest directory.
+    pub fn with_allowed_imports_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.allowed_imports = ImportAllowlist::File(path.into());
+        self
+    }
+
+    /// Write the exports and imported host functions of each binary to
+    /// `<bin>.exports.json` next to it.
+    ///
+    /// Disabled by default. The build checks them against
+    /// [`with_required_exports`](Self::with_required_exports) and
+    /// [`with_allowed_imports`](Self::with_allowed_imports) either way.
+    pub fn with_exports_listing(mut self, enabled: bool) -> Self {
+        self.exports_listing = enabled;
+        self
+    }
+
+    /// Write a disassembly listing of each binary to `<bin>.pvmasm` next to it.
+    ///
+    /// Disabled by default. The listing names the exports and marks jump targets;
+    /// instructions are attributed to the functions they belong to only if the
+    /// binary keeps its debug info, i.e. with [`LinkerOptions::strip`] off.
+    pub fn with_disassembly(mut self, enabled: bool) -> Self {
+        self.disassembly = enabled;
+        self
+    }
+
+    /// Set `PVM_HEAP_SIZE` to `bytes` in the nested build's environment.
+    ///
+    /// This does not configure the linker: the heap of a PolkaVM contract is whatever
+    /// static arena its allocator declares. The contracts `cargo pvm-contract` scaffolds
+    /// with an allocator read `PVM_HEAP_SIZE` with `option_env!` to size that arena;
+    /// other contracts have to read it themselves for the setting to have any effect.
+    pub fn with_heap_size_env(mut self, bytes: u32) -> Self {
+        self.heap_size = Some(bytes);
+        self
+    }
+
+    /// Build the contract with the given profile instead of the host's `PROFILE`,
+    /// e.g. always [`Profile::Release`] for small blobs during debug host builds.
+    ///
+    /// The `PVM_PROFILE` environment variable overrides this.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = Some(profile);
+        self
+    }
+
+    /// Build the contract with the profile of the given name, e.g. a custom
+    /// `[profile.production]` defined in the manifest.
+    pub fn with_profile_name(self, name: &str) -> Self {
+        self.with_profile(Profile::from_name(name))
+    }
+
+    /// Build the PolkaVM binary, exiting the build script on failure.
+    pub fn build(self) {
+        if let Err(e) = self.try_build() {
+            eprintln!("PolkaVM build failed: {e}");
+            let mut source = std::error::Error::source(&e);
+            while let Some(cause) = source {
+                eprintln!("  caused by: {cause}");
+                source = cause.source();
+            }
+            std::process::exit(1);
+        }
+    }
+
+    /// Build the PolkaVM binary and describe the produced `.polkavm` files.
+    ///
+    /// Returns no artifacts when called from within the nested PolkaVM build.
+    /// Failures are reported as a [`PvmBuildError`], so callers can tell e.g. a
+    /// failed cargo build from a linker error.
+    pub fn try_build(mut self) -> Result<BuildOutput> {
+        if Self::is_recursive_build() {
+            return Ok(BuildOutput::default());
+        }
+
+        self.check_only |= check_only_requested();
+        build_project(&self)
+    }
+
+    /// Type-check the contract, exiting the build script on failure.
+    ///
+    /// Runs `cargo check` for the PolkaVM target instead of building and linking,
+    /// which is much faster while editing. The `PVM_BINARY_*` variables and the
+    /// [generated module](PvmBuilder::with_generated_module) point at empty
+    /// placeholder files, so host code embedding the binaries still compiles.
+    ///
+    /// [`PvmBuilder::build`] switches to this mode when `PVM_CHECK_ONLY=1` is set,
+    /// or when the build script runs under rust-analyzer or clippy. A plain
+    /// `cargo check` can't be told apart from a build, so set `PVM_CHECK_ONLY`
+    /// for it.
+    pub fn check(self) {
+        if let Err(e) = self.try_check() {
+            eprintln!("PolkaVM check failed: {e}");
+            let mut source = std::error::Error::source(&e);
+            while let Some(cause) = source {
+                eprintln!("  caused by: {cause}");
+                source = cause.source();
+            }
+            std::process::exit(1);
+        }
+    }
+
+    /// Type-check the contract, see [`PvmBuilder::check`].
+    pub fn try_check(mut self) -> Result<()> {
+        if Self::is_recursive_build() {
+            return Ok(());
+        }
+
+        self.check_only = true;
+        build_project(&self).map(drop)
+    }
+
+    /// Whether the current build script is running inside the nested PolkaVM build.
+    ///
+    /// ```no_run
+    /// use cargo_pvm_contract_builder::PvmBuilder;
+    ///
+    /// if !PvmBuilder::is_recursive_build() {
+    ///     // Host-only work, e.g. generating bindings
+    /// }
+    /// PvmBuilder::new().build();
+    /// ```
+    pub fn is_recursive_build() -> bool {
+        env_var_os(INTERNAL_BUILD_ENV).is_some()
+    }
+}
+
+/// Returns the manifest dir from the `CARGO_MANIFEST_DIR` env.
+fn get_manifest_dir() -> PathBuf {
+    env::var("CARGO_MANIFEST_DIR")
+        .expect("`CARGO_MANIFEST_DIR` is always set for `build.rs` files")
+        .into()
+}
+
+/// The cargo profile used for the contract build.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Profile {
+    /// The `dev` profile, built into the `debug` directory.
+    Debug,
+    /// The `release` profile.
+    Release,
+    /// A custom `[profile.<name>]`, built into the directory of the same name.
+    Custom(String),
+}
+
+impl Profile {
+    /// Detect the build profile from the `PROFILE` environment variable.
+    pub fn detect() -> Self {
+        env_var("PROFILE").map_or(Self::Debug, |name| Self::from_name(&name))
+    }
+
+    /// The profile with the given name; both `dev` and `debug` select [`Profile::Debug`].
+    pub fn from_name(name: &str) -> Self {
+        match name.trim() {
+            "dev" | "debug" => Self::Debug,
+            "release" => Self::Release,
+            name => Self::Custom(name.to_string()),
+        }
+    }
+
+    /// The name passed to `cargo build --profile`.
+    pub fn cargo_arg(&self) -> &str {
+        match self {
+            Profile::Debug => "dev",
+            Profile::Release => "release",
+            Profile::Custom(name) => name,
+        }
+    }
+
+    /// The directory under the target directory cargo writes the profile's artifacts to.
+    pub fn directory(&self) -> &str {
+        match self {
+            Profile::Debug => "debug",
+            Profile::Release => "release",
+            Profile::Custom(name) => name,
+        }
+    }
+}
+
+/// Resolve the contract's profile: `PVM_PROFILE`, then the configured one, then the host's.
+fn resolve_profile(
+    configured: Option<&Profile>,
+    env_override: Option<&str>,
+    detect: impl FnOnce() -> Profile,
+) -> Profile {
+    match env_override.filter(|name| !name.trim().is_empty()) {
+        Some(name) => Profile::from_name(name),
+        None => configured.cloned().unwrap_or_else(detect),
+    }
+}
+
+/// Check that a custom profile is defined by the manifest or its workspace root.
+fn validate_profile(profile: &Profile, cargo_toml: &Path) -> Result<()> {
+    let Profile::Custom(name) = profile else {
+        return Ok(());
+    };
+    // Built into cargo like `dev` and `release`
+    if matches!(name.as_str(), "test" | "bench") {
+        return Ok(());
+    }
+
+    let mut defined = Vec::new();
+    let mut manifest = Some(cargo_toml.to_path_buf());
+    while let Some(path) = manifest {
+        let doc = read_manifest(&path)?;
+        if let Some(profiles) = doc.get("profile").and_then(|p| p.as_table_like()) {
+            if profiles.contains_key(name) {
+                return Ok(());
+            }
+            defined.extend(profiles.iter().map(|(key, _)| key.to_string()));
+        }
+        if doc.contains_key("workspace") {
+            break;
+        }
+        manifest = workspace_root_manifest(&path);
+    }
+
+    let hint = if defined.is_empty() {
+        String::new()
+    } else {
+        format!(" (defined profiles: {})", defined.join(", "))
+    };
+    Err(PvmBuildError::Manifest {
+        path: cargo_toml.to_path_buf(),
+        message: format!("Profile `{name}` is not defined{hint}; add a `[profile.{name}]` section"),
+    })
+}
+
+/// The closest `Cargo.toml` above `cargo_toml`'s directory, which may be its workspace root.
+fn workspace_root_manifest(cargo_toml: &Path) -> Option<PathBuf> {
+    cargo_toml
+        .parent()?
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("Cargo.toml"))
+        .find(|path| path.is_file())
+}
+
+/// Get the workspace target directory.
+fn get_target_root() -> PathBuf {
+    let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR is set"));
+    target_root(&out_dir, env_dir("CARGO_TARGET_DIR").as_deref())
+}
+
+/// The target directory of the build whose build script writes to `out_dir`.
+///
+/// That is `cargo_target_dir` (`CARGO_TARGET_DIR`) if `out_dir` is inside it, the
+/// closest ancestor named `target` otherwise, then `cargo_target_dir` itself, e.g.
+/// for a `target-dir` set in `.cargo/config.toml`, and `out_dir` as a last resort.
+fn target_root(out_dir: &Path, cargo_target_dir: Option<&Path>) -> PathBuf {
+    // A relative `CARGO_TARGET_DIR` is relative to where cargo was invoked
+    if let Some(target_dir) = cargo_target_dir
+        && let Some(root) = out_dir
+            .ancestors()
+            .find(|ancestor| ancestor.ends_with(target_dir))
+    {
+        return root.to_path_buf();
+    }
+    if let Some(root) = out_dir
+        .ancestors()
+        .find(|ancestor| ancestor.file_name().is_some_and(|name| name == "target"))
+    {
+        return root.to_path_buf();
+    }
+    cargo_target_dir.map_or_else(|| out_dir.to_path_buf(), Path::to_path_buf)
+}
+
+/// The non-empty directory in the environment variable `key`.
+fn env_dir(key: &str) -> Option<PathBuf> {
+    env_var_os(key)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Read the environment variable `key`, which must be one of [`WATCHED_ENV`].
+fn env_var_os(key: &str) -> Option<OsString> {
+    debug_assert!(
+        WATCHED_ENV.contains(&key),
+        "`{key}` is read by the builder but missing from `WATCHED_ENV`"
+    );
+    env::var_os(key)
+}
+
+/// [`env_var_os`] for variables that are only meaningful as UTF-8.
+fn env_var(key: &str) -> Option<String> {
+    env_var_os(key).and_then(|value| value.into_string().ok())
+}
+
+/// Get the build output directory.
+fn get_build_dir() -> PathBuf {
+    get_target_root().join("pvmbuild")
+}
+
+/// Resolve a directory: the environment override, then the configured one, both
+/// relative to `manifest_dir`, then the default.
+fn resolve_dir(
+    configured: Option<&Path>,
+    env_override: Option<PathBuf>,
+    manifest_dir: &Path,
+    default: impl FnOnce() -> PathBuf,
+) -> PathBuf {
+    match env_override.as_deref().or(configured) {
+        Some(dir) => manifest_dir.join(dir),
+        None => default(),
+    }
+}
+
+/// A target of the contract crate that is linked into a PolkaVM program.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum ContractTarget {
+    /// A `[[bin]]` target, by name.
+    Bin(String),
+    /// The `cdylib` library target, by crate name.
+    Lib(String),
+}
+
+impl ContractTarget {
+    fn name(&self) -> &str {
+        match self {
+            ContractTarget::Bin(name) | ContractTarget::Lib(name) => name,
+        }
+    }
+
+    /// File name of the ELF cargo produces; the target JSON gives cdylibs an `.elf` suffix.
+    fn elf_file_name(&self) -> String {
+        match self {
+            ContractTarget::Bin(name) => name.clone(),
+            ContractTarget::Lib(name) => format!("{name}.elf"),
+        }
+    }
+}
+
+/// A package whose contract targets are built by one nested cargo invocation.
+#[derive(Debug)]
+struct ContractPackage {
+    /// Workspace package passed to `--package` (None = the manifest's own package).
+    name: Option<String>,
+    cargo_toml: PathBuf,
+    targets: Vec<ContractTarget>,
+    /// Target directory of the package's nested build.
+    target_dir: PathBuf,
+}
+
+impl ContractPackage {
+    fn manifest_dir(&self) -> &Path {
+        self.cargo_toml.parent().unwrap_or_else(|| Path::new("."))
+    }
+}
+
+/// A workspace member as reported by `cargo metadata`.
+#[derive(Debug, Deserialize)]
+struct WorkspaceMember {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<MemberTarget>,
+    #[serde(default)]
+    dependencies: Vec<MemberDependency>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberDependency {
+    /// `dev` or `build`, None for normal dependencies.
+    kind: Option<String>,
+    /// Directory of a `path` dependency.
+    path: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MemberTarget {
+    name: String,
+    kind: Vec<String>,
+}
+
+impl WorkspaceMember {
+    /// The member's `[[bin]]` targets, or its `cdylib` library if there are none.
+    fn contract_targets(&self) -> Vec<ContractTarget> {
+        let has_kind = |target: &MemberTarget, kind: &str| target.kind.iter().any(|k| k == kind);
+
+        let bins: Vec<_> = self
+            .targets
+            .iter()
+            .filter(|target| has_kind(target, "bin"))
+            .map(|target| ContractTarget::Bin(target.name.clone()))
+            .collect();
+        if !bins.is_empty() {
+            return bins;
+        }
+
+        self.targets
+            .iter()
+            .find(|target| has_kind(target, "cdylib"))
+            .map(|target| ContractTarget::Lib(target.name.replace('-', "_")))
+            .into_iter()
+            .collect()
+    }
+}
+
+/// What `cargo metadata --no-deps` reports about the workspace of a manifest.
+#[derive(Deserialize)]
+struct WorkspaceMetadata {
+    packages: Vec<WorkspaceMember>,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
+}
+
+/// List the members of the workspace `cargo_toml` belongs to.
+fn workspace_members(cargo_toml: &Path) -> Result<Vec<WorkspaceMember>> {
+    Ok(workspace_metadata(cargo_toml)?.packages)
+}
+
+/// The `Cargo.lock` of the workspace `cargo_toml` belongs to, if there is one yet.
+fn workspace_lock_file(cargo_toml: &Path) -> Result<Option<PathBuf>> {
+    let lock_file = workspace_metadata(cargo_toml)?
+        .workspace_root
+        .join("Cargo.lock");
+    Ok(lock_file.exists().then_some(lock_file))
+}
+
+/// Directories of the local packages `packages` reach through `path` dependencies,
+/// other than dev-dependencies, directly or through each other.
+///
+/// Packages outside the workspace are looked up with `cargo metadata` on their own
+/// manifest, so this follows path dependencies into other workspaces too.
+fn path_dependency_dirs(packages: &[ContractPackage]) -> Result<Vec<PathBuf>> {
+    let canonical = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+
+    let mut members = HashMap::new();
+    let mut seen: Vec<_> = packages
+        .iter()
+        .map(|package| canonical(&package.cargo_toml))
+        .collect();
+    let mut pending = seen.clone();
+    let mut dirs = Vec::new();
+    while let Some(cargo_toml) = pending.pop() {
+        if !members.contains_key(&cargo_toml) {
+            for member in workspace_metadata(&cargo_toml)?.packages {
+                members
+                    .entry(canonical(&member.manifest_path))
+                    .or_insert(member);
+            }
+        }
+        let Some(member) = members.get(&cargo_toml) else {
+            continue;
+        };
+        for dependency in &member.dependencies {
+            let Some(dir) = &dependency.path else {
+                continue;
+            };
+            let dependency_toml = canonical(&dir.join("Cargo.toml"));
+            if dependency.kind.as_deref() != Some("dev") && !seen.contains(&dependency_toml) {
+                seen.push(dependency_toml.clone());
+                pending.push(dependency_toml);
+                dirs.push(dir.clone());
+            }
+        }
+    }
+    dirs.sort();
+    Ok(dirs)
+}
+
+/// Files outside the contract packages that their build depends on: the workspace
+/// lock file and the sources of local path dependencies.
+fn external_inputs(builder: &PvmBuilder, packages: &[ContractPackage]) -> Result<Vec<PathBuf>> {
+    let mut paths: Vec<_> = workspace_lock_file(&builder.project_cargo_toml)?
+        .into_iter()
+        .collect();
+    for dir in path_dependency_dirs(packages)? {
+        paths.extend(rerun_if_changed_paths(&dir)?);
+    }
+    Ok(paths)
+}
+
+/// Run `cargo metadata --no-deps` for the workspace `cargo_toml` belongs to.
+///
+/// Runs from the manifest's directory so its `.cargo/config.toml` applies.
+fn workspace_metadata(cargo_toml: &Path) -> Result<WorkspaceMetadata> {
+    let mut command = cargo_command(None);
+    if let Some(dir) = cargo_toml.parent().filter(|dir| dir.is_dir()) {
+        command.current_dir(dir);
+    }
+    let output = command
+        .env_remove("CARGO_ENCODED_RUSTFLAGS")
+        .arg("metadata")
+        .arg("--format-version=1")
+        .arg("--no-deps")
+        .arg("--manifest-path")
+        .arg(cargo_toml)
+        .stderr(Stdio::inherit())
+        .output()
+        .context_with(|| "Failed to execute cargo metadata".to_string())?;
+    if !output.status.success() {
+        return Err(PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: "cargo metadata failed".to_string(),
+        });
+    }
+
+    serde_json::from_slice(&output.stdout)
+        .context_with(|| "Failed to parse cargo metadata".to_string())
+}
+
+/// Pick the members to build: the requested ones, plus every member with
+/// contract targets other than `own_manifest` when building the whole workspace.
+fn select_members(
+    members: Vec<WorkspaceMember>,
+    requested: &[String],
+    workspace: bool,
+    own_manifest: &Path,
+) -> Result<Vec<WorkspaceMember>> {
+    if let Some(missing) = requested
+        .iter()
+        .find(|name| !members.iter().any(|member| &member.name == *name))
+    {
+        return Err(PvmBuildError::invalid_config(format!(
+            "Package `{missing}` is not a member of the workspace"
+        )));
+    }
+
+    let own_manifest = fs::canonicalize(own_manifest).unwrap_or_else(|_| own_manifest.into());
+    let selected: Vec<_> = members
+        .into_iter()
+        .filter(|member| {
+            requested.contains(&member.name)
+                || (workspace
+                    && member.manifest_path != own_manifest
+                    && !member.contract_targets().is_empty())
+        })
+        .collect();
+
+    if let Some(member) = selected
+        .iter()
+        .find(|member| member.contract_targets().is_empty())
+    {
+        return Err(PvmBuildError::Manifest {
+            path: member.manifest_path.clone(),
+            message: format!("Package `{}` has no binary or cdylib targets", member.name),
+        });
+    }
+
+    Ok(selected)
+}
+
+/// The packages to build: the manifest's own, or the selected workspace members.
+fn contract_packages(builder: &PvmBuilder) -> Result<Vec<ContractPackage>> {
+    let project_cargo_toml = &builder.project_cargo_toml;
+
+    if builder.packages.is_empty() && !builder.workspace {
+        let doc = package_manifest(project_cargo_toml)?;
+        let bins_selected = builder.bin_names.is_some() || !builder.excluded_bins.is_empty();
+        let targets = match (bins_selected, builder.lib) {
+            (true, true) => {
+                return Err(PvmBuildError::invalid_config(
+                    "`with_lib` cannot be combined with `with_bin` or `without_bin`",
+                ));
+            }
+            (true, false) => select_bins(
+                &package_bins(project_cargo_toml, &doc)?,
+                builder.bin_names.as_deref(),
+                &builder.excluded_bins,
+            )?,
+            (false, true) => vec![ContractTarget::Lib(lib_name(project_cargo_toml, &doc)?)],
+            (false, false) => get_contract_targets(project_cargo_toml)?,
+        };
+
+        if targets.is_empty() {
+            return Err(PvmBuildError::NoBinaryTargets);
+        }
+
+        let mut target_dir = builder.target_dir();
+        if builder.package_target_dir
+            && builder.target_dir.is_none()
+            && env_dir(BUILD_DIR_ENV).is_none()
+            && let Some(name) = package_name(&doc)
+        {
+            target_dir.push(name);
+        }
+
+        return Ok(vec![ContractPackage {
+            name: None,
+            cargo_toml: project_cargo_toml.clone(),
+            targets,
+            target_dir,
+        }]);
+    }
+
+    if builder.bin_names.is_some() || !builder.excluded_bins.is_empty() || builder.lib {
+        return Err(PvmBuildError::invalid_config(
+            "`with_bin`, `without_bin` and `with_lib` cannot be combined with `with_package` or `with_workspace`",
+        ));
+    }
+
+    let members = select_members(
+        workspace_members(project_cargo_toml)?,
+        &builder.packages,
+        builder.workspace,
+        project_cargo_toml,
+    )?;
+    if members.is_empty() {
+        return Err(PvmBuildError::NoBinaryTargets);
+    }
+
+    let target_dir = builder.target_dir();
+    Ok(members
+        .into_iter()
+        .map(|member| ContractPackage {
+            targets: member.contract_targets(),
+            target_dir: if builder.shared_target_dir {
+                target_dir.clone()
+            } else {
+                target_dir.join(&member.name)
+            },
+            name: Some(member.name),
+            cargo_toml: member.manifest_path,
+        })
+        .collect())
+}
+
+/// Names of the package's binaries, including those cargo discovers in `src/bin`.
+fn package_bins(cargo_toml: &Path, doc: &toml_edit::DocumentMut) -> Result<Vec<String>> {
+    let name = package_name(doc);
+    let member = workspace_members(cargo_toml)?
+        .into_iter()
+        .find(|member| Some(member.name.as_str()) == name)
+        .ok_or_else(|| PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: "cargo metadata doesn't list this package".to_string(),
+        })?;
+    Ok(member
+        .targets
+        .into_iter()
+        .filter(|target| target.kind.iter().any(|kind| kind == "bin"))
+        .map(|target| target.name)
+        .collect())
+}
+
+/// Apply `with_bins` and `without_bin` to the package's binaries, rejecting names it doesn't have.
+fn select_bins(
+    available: &[String],
+    included: Option<&[String]>,
+    excluded: &[String],
+) -> Result<Vec<ContractTarget>> {
+    if let Some(unknown) = included
+        .unwrap_or_default()
+        .iter()
+        .chain(excluded)
+        .find(|name| !available.contains(name))
+    {
+        return Err(PvmBuildError::invalid_config(format!(
+            "Unknown binary `{unknown}`, available: {}",
+            available.join(", ")
+        )));
+    }
+    if let Some(both) = included
+        .unwrap_or_default()
+        .iter()
+        .find(|name| excluded.contains(name))
+    {
+        return Err(PvmBuildError::invalid_config(format!(
+            "Binary `{both}` is both selected with `with_bin` and excluded with `without_bin`"
+        )));
+    }
+
+    Ok(included
+        .unwrap_or(available)
+        .iter()
+        .filter(|name| !excluded.contains(name))
+        .cloned()
+        .map(ContractTarget::Bin)
+        .collect())
+}
+
+fn read_manifest(cargo_toml: &Path) -> Result<toml_edit::DocumentMut> {
+    let content = fs::read_to_string(cargo_toml)
+        .context_with(|| format!("Failed to read {}", cargo_toml.display()))?;
+    content
+        .parse()
+        .map_err(|err: toml_edit::TomlError| PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: err.to_string(),
+        })
+}
+
+/// Read the manifest of a single contract package, explaining what's wrong if it isn't one.
+fn package_manifest(cargo_toml: &Path) -> Result<toml_edit::DocumentMut> {
+    if !cargo_toml.is_file() {
+        return Err(PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: "No such manifest".to_string(),
+        });
+    }
+    let doc = read_manifest(cargo_toml)?;
+    if doc.get("package").is_none() {
+        let message = if doc.get("workspace").is_some() {
+            "This is a virtual workspace manifest; point at a member's Cargo.toml, or select members with `with_package` or `with_workspace`"
+        } else {
+            "The manifest has no [package] section"
+        };
+        return Err(PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: message.to_string(),
+        });
+    }
+    Ok(doc)
+}
+
+/// Targets to build when none were selected: the `[[bin]]` targets, a `cdylib`
+/// library if there are none, or else the implicit binary named after the package.
+fn get_contract_targets(cargo_toml: &Path) -> Result<Vec<ContractTarget>> {
+    let doc = read_manifest(cargo_toml)?;
+
+    let mut targets = Vec::new();
+
+    if let Some(bin_array) = doc.get("bin").and_then(|b| b.as_array_of_tables()) {
+        for bin in bin_array {
+            if let Some(name) = bin.get("name").and_then(|n| n.as_str()) {
+                targets.push(ContractTarget::Bin(name.to_string()));
+            }
+        }
+    }
+
+    if targets.is_empty() && is_cdylib(&doc) {
+        targets.push(ContractTarget::Lib(lib_name(cargo_toml, &doc)?));
+    }
+
+    if targets.is_empty()
+        && let Some(name) = package_name(&doc)
+    {
+        targets.push(ContractTarget::Bin(name.to_string()));
+    }
+
+    Ok(targets)
+}
+
+fn package_name(doc: &toml_edit::DocumentMut) -> Option<&str> {
+    doc.get("package")
+        .and_then(|p| p.get("name"))
+        .and_then(|n| n.as_str())
+}
+
+/// Whether the manifest declares a `cdylib` library.
+fn is_cdylib(doc: &toml_edit::DocumentMut) -> bool {
+    doc.get("lib")
+        .and_then(|lib| lib.get("crate-type"))
+        .and_then(|types| types.as_array())
+        .is_some_and(|types| types.iter().any(|t| t.as_str() == Some("cdylib")))
+}
+
+/// Name of the library target: `lib.name`, or the package name with `-` replaced by `_`.
+fn lib_name(cargo_toml: &Path, doc: &toml_edit::DocumentMut) -> Result<String> {
+    if let Some(name) = doc
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .and_then(|n| n.as_str())
+    {
+        return Ok(name.to_string());
+    }
+    package_name(doc)
+        .map(|name| name.replace('-', "_"))
+        .ok_or_else(|| PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: "no package name".to_string(),
+        })
+}
+
+/// Collect the files of a package whose changes should re-run the build script.
+///
+/// This covers the manifest, every file under `src/` and any Solidity file pulled
+/// in through `sol!("...")`. The lock file and path dependencies are covered by
+/// [`external_inputs`].
+fn rerun_if_changed_paths(manifest_dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut paths = vec![manifest_dir.join("Cargo.toml")];
+    let abi_consts = manifest_dir.join(abi_consts::ABI_CONSTS_FILE);
+    if abi_consts.is_file() {
+        paths.push(abi_consts);
+    }
+
+    let src_dir = manifest_dir.join("src");
+    if src_dir.exists() {
+        // Watching the directory itself catches newly added files
+        paths.push(src_dir.clone());
+
+        let mut pending = vec![src_dir];
+        while let Some(dir) = pending.pop() {
+            let mut entries = fs::read_dir(&dir)
+                .context_with(|| format!("Failed to read {}", dir.display()))?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<std::io::Result<Vec<_>>>()
+                .context_with(|| format!("Failed to read {}", dir.display()))?;
+            entries.sort();
+
+            for path in entries {
+                if path.is_dir() {
+                    pending.push(path);
+                    continue;
+                }
+
+                if path.extension().is_some_and(|ext| ext == "rs") {
+                    let content = fs::read_to_string(&path)
+                        .context_with(|| format!("Failed to read {}", path.display()))?;
+                    for sol_file in sol_macro_paths(&content) {
+                        let sol_path = manifest_dir.join(sol_file);
+                        if !paths.contains(&sol_path) {
+                            paths.push(sol_path);
+                        }
+                    }
+                }
+                paths.push(path);
+            }
+        }
+    }
+
+    Ok(paths)
+}
+
+/// Extract the file paths passed to `sol!("...")` invocations.
+fn sol_macro_paths(content: &str) -> Vec<&str> {
+    content
+        .match_indices("sol!(\"")
+        .filter_map(|(idx, pattern)| {
+            let rest = &content[idx + pattern.len()..];
+            rest.find('"').map(|end| &rest[..end])
+        })
+        .collect()
+}
+
+/// Whether we're running inside a build script, where `cargo:` directives are understood.
+fn in_build_script() -> bool {
+    env::var_os("OUT_DIR").is_some()
+}
+
+/// Surface a warning through cargo when running in a build script, on stderr otherwise.
+fn warn(message: &str) {
+    if in_build_script() {
+        // A directive ends at the newline, so multi-line messages take one each
+        for line in message.lines() {
+            println!("cargo:warning={line}");
+        }
+    } else {
+        eprintln!("warning: {message}");
+    }
+}
+
+/// Tell cargo which files and environment variables should re-run the build script.
+fn emit_rerun_directives(manifest_dir: &Path) -> Result<()> {
+    for path in rerun_if_changed_paths(manifest_dir)? {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
+    for key in WATCHED_ENV {
+        println!("cargo:rerun-if-env-changed={key}");
+    }
+    Ok(())
+}
+
+/// Build the project, returning the paths of the linked PolkaVM binaries.
+fn build_project(builder: &PvmBuilder) -> Result<BuildOutput> {
+    let started = Instant::now();
+    if in_build_script() {
+        emit_rerun_directives(builder.manifest_dir())?;
+        for key in &builder.env_passthrough {
+            println!("cargo:rerun-if-env-changed={key}");
+        }
+    }
+
+    let profile = resolve_profile(
+        builder.profile.as_ref(),
+        env_var(PROFILE_ENV).as_deref(),
+        Profile::detect,
+    );
+    validate_profile(&profile, &builder.project_cargo_toml)?;
+    let output_dir = builder.output_dir();
+    fs::create_dir_all(&output_dir)
+        .context_with(|| format!("Failed to create output directory {}", output_dir.display()))?;
+
+    let packages = contract_packages(builder)?;
+    let external_inputs = if in_build_script() || builder.fingerprint {
+        external_inputs(builder, &packages)?
+    } else {
+        Vec::new()
+    };
+    if in_build_script() {
+        for package in packages.iter().filter(|package| package.name.is_some()) {
+            for path in rerun_if_changed_paths(package.manifest_dir())? {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+        }
+        for path in &external_inputs {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+
+    let names: Vec<String> = packages
+        .iter()
+        .flat_map(|package| &package.targets)
+        .map(|target| target.name().to_string())
+        .collect();
+    let env_vars = binary_env_vars(&names)?;
+
+    let generated_module_path = if builder.generated_module {
+        let out_dir = env::var_os("OUT_DIR").ok_or_else(|| {
+            PvmBuildError::invalid_config(
+                "`with_generated_module` requires running in a build script (OUT_DIR is not set)",
+            )
+        })?;
+        Some(PathBuf::from(out_dir).join(GENERATED_MODULE_FILE))
+    } else {
+        None
+    };
+
+    if let Some(dest) = &builder.copy_to
+        && names.len() > 1
+        && !dest.is_dir()
+    {
+        return Err(PvmBuildError::invalid_config(format!(
+            "`build_to` destination {} must be an existing directory when building {} binaries",
+            dest.display(),
+            names.len()
+        )));
+    }
+
+    if let Some(size) = builder.heap_size
+        && size > PALLET_REVIVE_STATIC_MEMORY_LIMIT
+    {
+        warn(&format!(
+            "Heap size of {size} bytes exceeds the {PALLET_REVIVE_STATIC_MEMORY_LIMIT} bytes of static memory pallet-revive allows; the contract will fail to deploy"
+        ));
+    }
+
+    let target_json = resolve_target_json(builder.is_64_bit)?;
+    let toolchain = builder.toolchain.as_deref();
+    let rustc_version = rustc_version(builder.manifest_dir(), toolchain);
+    if let Some(toolchain) = toolchain
+        && rustc_version.is_none()
+    {
+        return Err(PvmBuildError::ToolchainNotInstalled {
+            toolchain: toolchain.to_string(),
+        });
+    }
+    for package in &packages {
+        verify_abi_consts(builder, package)?;
+    }
+
+    if builder.check_only {
+        return check_project(
+            builder,
+            &packages,
+            &names,
+            &env_vars,
+            &profile,
+            &target_json,
+            generated_module_path.as_deref(),
+            rustc_version,
+        );
+    }
+
+    let mut elf_paths = Vec::with_capacity(names.len());
+    for package in &packages {
+        let elf_dir = elf_dir(&package.target_dir, &target_json, &profile);
+        elf_paths.extend(
+            package
+                .targets
+                .iter()
+                .map(|target| (package, target, elf_dir.join(target.elf_file_name()))),
+        );
+    }
+    let instruction_set = builder.target_instruction_set();
+    let file_name = |bin: &str| {
+        let mut name = bin.to_string();
+        if builder.instruction_set_in_file_name {
+            name = format!("{name}.{}", instruction_set_name(instruction_set));
+        }
+        name = format!("{name}.{}", profile.directory());
+        if builder.debug_blob {
+            name.push_str(".debug");
+        }
+        format!("{name}.polkavm")
+    };
+    let blob_dir = blob_dir(builder, &output_dir);
+    fs::create_dir_all(&blob_dir)
+        .context_with(|| format!("Failed to create {}", blob_dir.display()))?;
+    let output_path = |bin: &str| blob_dir.join(file_name(bin));
+
+    // Keyed by manifest, as the crates of a workspace share the nested target directory
+    let manifest_key = hex_string(&keccak256(
+        builder.project_cargo_toml.as_os_str().as_encoded_bytes(),
+    ));
+    let fingerprint_path = builder.target_dir().join("fingerprints").join(format!(
+        "{}-{}",
+        &manifest_key[2..18],
+        profile.directory()
+    ));
+    let fingerprint = if builder.fingerprint {
+        Some(build_fingerprint(
+            builder,
+            &packages,
+            &external_inputs,
+            &profile,
+            &target_json,
+            rustc_version.as_deref(),
+        )?)
+    } else {
+        None
+    };
+    let up_to_date = fingerprint.as_ref().is_some_and(|fingerprint| {
+        fs::read_to_string(&fingerprint_path).is_ok_and(|previous| previous == *fingerprint)
+    }) && elf_paths
+        .iter()
+        .all(|(_, target, elf_path)| elf_path.exists() && output_path(target.name()).exists());
+
+    let mut cargo_build_ms = 0;
+    let mut package_build_ms = vec![0; packages.len()];
+    let mut compiler_artifacts = Vec::new();
+    if up_to_date {
+        eprintln!("PolkaVM binaries are up to date");
+    } else {
+        // A build that fails halfway must not leave a matching fingerprint behind
+        if fingerprint_path.exists() {
+            fs::remove_file(&fingerprint_path)
+                .context_with(|| format!("Failed to remove {}", fingerprint_path.display()))?;
+        }
+        // Without rust-src, -Zbuild-std fails with a wall of errors about core's manifest
+        if let Some(sysroot) = rustc_sysroot(builder.manifest_dir(), toolchain) {
+            check_rust_src(&sysroot, toolchain)?;
+        }
+        if !builder.debug_blob {
+            check_immediate_abort_support(rustc_version.as_deref())?;
+        }
+        let mut forwarded_warnings = Vec::new();
+        for (package, build_ms) in packages.iter().zip(&mut package_build_ms) {
+            let build_started = Instant::now();
+            let executables = build_elf(
+                builder,
+                package,
+                &profile,
+                &target_json,
+                &mut forwarded_warnings,
+            )?;
+            if builder.compiler_artifacts {
+                compiler_artifacts.extend(executables);
+            }
+            *build_ms = build_started.elapsed().as_millis() as u64;
+            cargo_build_ms += *build_ms;
+            if builder.cargo_timings {
+                copy_cargo_timings(package, &output_dir)?;
+            }
+        }
+    }
+
+    // Link each ELF to PolkaVM
+
+    let mut links = Vec::with_capacity(elf_paths.len());
+    for (_, target, elf_path) in &elf_paths {
+        if !elf_path.exists() {
+            return Err(PvmBuildError::ElfNotFound {
+                path: elf_path.clone(),
+            });
+        }
+        links.push((elf_path.as_path(), output_path(target.name())));
+    }
+    let link_started = Instant::now();
+    let linked = if up_to_date {
+        None
+    } else {
+        let linker_options = builder.effective_linker_options();
+        let hash_dir = builder.target_dir().join("fingerprints");
+        Some(link_all(
+            &links,
+            &hash_dir,
+            instruction_set,
+            &linker_options,
+        )?)
+    };
+    let link_ms = link_started.elapsed().as_millis() as u64;
+
+    let verbose = builder.is_verbose();
+    let (allowed_imports, allowlist) = builder.allowed_imports.load(builder.manifest_dir())?;
+    let file_abi = match &builder.bundle {
+        Some(AbiSource::File(path)) => {
+            let path = builder.manifest_dir().join(path);
+            if in_build_script() {
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+            Some(bundle::read_abi(&path)?)
+        }
+        _ => None,
+    };
+    let settings = ArtifactSettings {
+        builder,
+        output_dir: &output_dir,
+        build_info: bundle::BuildInfo {
+            rustc_version: rustc_version.clone(),
+            polkavm_linker_version: POLKAVM_LINKER_VERSION.to_string(),
+            profile: profile.cargo_arg().to_string(),
+            instruction_set: instruction_set_name(instruction_set).to_string(),
+            debug_blob: builder.debug_blob,
+        },
+        file_abi,
+    };
+    let mut artifacts = Vec::with_capacity(elf_paths.len());
+    for (i, ((package, target, elf_path), env_var)) in
+        elf_paths.into_iter().zip(&env_vars).enumerate()
+    {
+        let bin = target.name();
+        let output_path = output_path(bin);
+        let (size, link_ms) = match &linked {
+            Some(linked) => linked[i],
+            None => {
+                let size = fs::metadata(&output_path)
+                    .context_with(|| format!("Failed to read {}", output_path.display()))?
+                    .len();
+                (size, 0)
+            }
+        };
+        let binary = fs::read(&output_path)
+            .context_with(|| format!("Failed to read {}", output_path.display()))?;
+        let elf_size = fs::metadata(&elf_path)
+            .context_with(|| format!("Failed to read {}", elf_path.display()))?
+            .len();
+        let linked_binary = LinkedBinary {
+            package,
+            bin,
+            file_name: file_name(bin),
+            output_path,
+            elf_path,
+            binary,
+            size,
+            elf_size,
+            link_ms,
+        };
+
+        if linked.is_some() {
+            report_linked(&linked_binary, verbose);
+        }
+        let symbols = validate_binary(builder, &linked_binary, &allowed_imports, &allowlist)?;
+        artifacts.push(emit_artifacts(&settings, linked_binary, symbols, env_var)?);
+    }
+
+    if let Some(module_path) = generated_module_path {
+        write_generated_module(&module_path, &artifacts)?;
+    }
+
+    if let Some(fingerprint) = fingerprint
+        && !up_to_date
+    {
+        write_fingerprint(&fingerprint_path, &fingerprint)?;
+    }
+
+    let output = BuildOutput {
+        artifacts,
+        rustc_version,
+        polkavm_linker_version: POLKAVM_LINKER_VERSION.to_string(),
+        cargo_build_ms,
+        link_ms,
+        total_ms: started.elapsed().as_millis() as u64,
+        compiler_artifacts,
+        instruction_set: instruction_set_name(instruction_set).to_string(),
+        debug_blob: builder.debug_blob,
+    };
+    report_build(builder, &output, &packages, &package_build_ms)?;
+
+    Ok(output)
+}
+
+/// A binary of the build after linking, before its artifacts are written.
+struct LinkedBinary<'a> {
+    package: &'a ContractPackage,
+    bin: &'a str,
+    /// File name of the blob, e.g. `my-token.release.polkavm`.
+    file_name: String,
+    output_path: PathBuf,
+    elf_path: PathBuf,
+    binary: Vec<u8>,
+    size: u64,
+    elf_size: u64,
+    link_ms: u64,
+}
+
+/// What the artifacts of every binary of a build have in common.
+struct ArtifactSettings<'a> {
+    builder: &'a PvmBuilder,
+    output_dir: &'a Path,
+    /// Build info of the bundles written by [`PvmBuilder::with_bundle`].
+    build_info: bundle::BuildInfo,
+    /// ABI of [`AbiSource::File`], shared by all bundles.
+    file_abi: Option<serde_json::Value>,
+}
+
+/// Print the size of a freshly linked binary.
+fn report_linked(linked: &LinkedBinary, verbose: bool) {
+    // Reported here rather than by the linking threads to keep the order stable
+    let sections = polkavm_linker::ProgramBlob::parse(linked.binary.clone().into())
+        .ok()
+        .map(|blob| {
+            let data = blob.ro_data().len() + blob.rw_data().len();
+            (blob.code().len() as u64, data as u64)
+        });
+    let mut message = format!(
+        "Created PolkaVM binary: {} ({})",
+        linked.output_path.display(),
+        size_summary(linked.size, linked.elf_size, sections)
+    );
+    if verbose {
+        message.push_str(&format!(", linked from {}", linked.elf_path.display()));
+    }
+    eprintln!("{message}");
+}
+
+/// Check a linked binary against the size budget, the required exports and the
+/// allowed imports, returning its symbols. Host paths left in reproducible builds
+/// are only warned about.
+fn validate_binary(
+    builder: &PvmBuilder,
+    linked: &LinkedBinary,
+    allowed_imports: &[String],
+    allowlist: &str,
+) -> Result<symbols::Symbols> {
+    let bin = linked.bin;
+    check_size(bin, linked.size, builder.max_size, builder.size_warning)?;
+
+    let symbols = symbols::symbols(&linked.output_path, linked.binary.clone())?;
+    check_exports(bin, &symbols.exports, &builder.required_exports)?;
+    symbols::check_imports(bin, &symbols.imports, allowed_imports, allowlist)?;
+
+    if builder.reproducible {
+        let paths = package_host_paths(builder, linked.package);
+        for leaked in leaked_host_paths(&linked.binary, &paths) {
+            warn(&format!(
+                "{} still contains the host path {}, so it may differ between machines. \
+                 Other sources of nondeterminism are paths embedded by build scripts or proc macros, \
+                 differing toolchains (pin one in rust-toolchain.toml) and unlocked dependencies (commit Cargo.lock)",
+                linked.output_path.display(),
+                leaked.display()
+            ));
+        }
+    }
+    Ok(symbols)
+}
+
+/// Write the files the builder is configured to produce next to a linked binary,
+/// copy it to [`PvmBuilder::build_to`]'s destination and export its path to the crate.
+fn emit_artifacts(
+    settings: &ArtifactSettings,
+    linked: LinkedBinary,
+    symbols: symbols::Symbols,
+    env_var: &str,
+) -> Result<Artifact> {
+    let ArtifactSettings {
+        builder,
+        output_dir,
+        ..
+    } = *settings;
+    let LinkedBinary {
+        package,
+        bin,
+        file_name,
+        output_path,
+        elf_path,
+        binary,
+        size,
+        elf_size,
+        link_ms,
+    } = linked;
+
+    let code_hash = hex_string(&keccak256(&binary));
+    let code_hash_path = match builder.code_hash_file {
+        Some(algorithm) => {
+            let hash_path = code_hash_path(&output_dir.join(&file_name), algorithm);
+            let digest = hex_string(&algorithm.digest(&binary));
+            fs::write(&hash_path, format!("{digest}\n"))
+                .context_with(|| format!("Failed to write {}", hash_path.display()))?;
+            Some(hash_path)
+        }
+        None => None,
+    };
+    let sol_abi = if builder.abi_output || builder.bundle == Some(AbiSource::Solidity) {
+        match find_sol_file(package.manifest_dir(), bin)? {
+            Some(sol_path) => Some(solidity_abi(&sol_path, bin)?),
+            None => None,
+        }
+    } else {
+        None
+    };
+    if builder.abi_output
+        && let Some(abi) = &sol_abi
+    {
+        let abi_path = output_dir.join(format!("{bin}.abi.json"));
+        let json = serde_json::to_string_pretty(abi)
+            .context_with(|| "Failed to serialize ABI".to_string())?;
+        fs::write(&abi_path, json + "\n")
+            .context_with(|| format!("Failed to write {}", abi_path.display()))?;
+    }
+    if builder.exports_listing {
+        let symbols_path = output_dir.join(format!("{bin}.exports.json"));
+        let json = serde_json::to_string_pretty(&symbols)
+            .context_with(|| "Failed to serialize exports".to_string())?;
+        fs::write(&symbols_path, json + "\n")
+            .context_with(|| format!("Failed to write {}", symbols_path.display()))?;
+    }
+    if builder.disassembly {
+        let listing = disassembly::disassemble(&output_path, binary.clone())?;
+        let listing_path = output_dir.join(format!("{bin}.pvmasm"));
+        fs::write(&listing_path, listing)
+            .context_with(|| format!("Failed to write {}", listing_path.display()))?;
+    }
+    let hex_path = if builder.output_format.hex() {
+        let hex_path = output_dir.join(format!("{file_name}.hex"));
+        fs::write(&hex_path, hex_string(&binary))
+            .context_with(|| format!("Failed to write {}", hex_path.display()))?;
+        Some(hex_path)
+    } else {
+        None
+    };
+    let gzip_path = if builder.gzip_output {
+        let gzip_path = output_dir.join(format!("{file_name}.gz"));
+        fs::write(&gzip_path, gzip(&binary))
+            .context_with(|| format!("Failed to write {}", gzip_path.display()))?;
+        Some(gzip_path)
+    } else {
+        None
+    };
+    let bundle_path = if builder.bundle.is_some() {
+        let abi = match settings.file_abi.clone().or(sol_abi) {
+            Some(abi) => abi,
+            None => {
+                return Err(PvmBuildError::invalid_config(format!(
+                    "No Solidity file named after `{bin}` in {} to take the bundle's ABI from",
+                    package.manifest_dir().display()
+                )));
+            }
+        };
+        let bundle = bundle::ContractBundle {
+            name: bin.to_string(),
+            bytecode: hex_string(&binary),
+            code_hash: code_hash.clone(),
+            abi,
+            build_info: settings.build_info.clone(),
+        };
+        let bundle_path = output_dir.join(format!("{bin}.contract.json"));
+        let json = serde_json::to_string_pretty(&bundle)
+            .context_with(|| "Failed to serialize bundle".to_string())?;
+        fs::write(&bundle_path, json + "\n")
+            .context_with(|| format!("Failed to write {}", bundle_path.display()))?;
+        Some(bundle_path)
+    } else {
+        None
+    };
+    let kept_elf_path = if builder.keep_elf {
+        let dest = output_dir.join(format!("{bin}.elf"));
+        fs::copy(&elf_path, &dest).context_with(|| {
+            format!(
+                "Failed to copy {} to {}",
+                elf_path.display(),
+                dest.display()
+            )
+        })?;
+        Some(dest)
+    } else {
+        None
+    };
+
+    let path = match &builder.copy_to {
+        Some(dest) => {
+            let dest = copy_destination(dest, bin);
+            fs::copy(&output_path, &dest).context_with(|| {
+                format!(
+                    "Failed to copy {} to {}",
+                    output_path.display(),
+                    dest.display()
+                )
+            })?;
+            if in_build_script() {
+                println!("cargo:rerun-if-changed={}", dest.display());
+            }
+            dest
+        }
+        None => output_path,
+    };
+
+    if in_build_script() {
+        let absolute_path = std::path::absolute(&path)
+            .context_with(|| format!("Failed to resolve {}", path.display()))?;
+        println!("cargo:rustc-env={env_var}={}", absolute_path.display());
+    }
+
+    Ok(Artifact {
+        name: bin.to_string(),
+        path,
+        elf_path,
+        kept_elf_path,
+        hex_path,
+        gzip_path,
+        bundle_path,
+        code_hash_path,
+        size,
+        elf_size,
+        link_ms,
+        code_hash,
+        exports: symbols.exports,
+        imports: symbols.imports,
+    })
+}
+
+/// Record the fingerprint of a completed build.
+fn write_fingerprint(path: &Path, fingerprint: &str) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context_with(|| format!("Failed to create {}", dir.display()))?;
+    }
+    fs::write(path, fingerprint).context_with(|| format!("Failed to write {}", path.display()))
+}
+
+/// Print the timings of a finished build if asked for, and write its report.
+fn report_build(
+    builder: &PvmBuilder,
+    output: &BuildOutput,
+    packages: &[ContractPackage],
+    package_build_ms: &[u64],
+) -> Result<()> {
+    if builder.is_verbose() || is_truthy(env_var(TIMINGS_ENV).as_deref()) {
+        // Binaries of one package are compiled by a single cargo invocation
+        let compile_ms = packages
+            .iter()
+            .zip(package_build_ms)
+            .flat_map(|(package, ms)| std::iter::repeat_n(*ms, package.targets.len()));
+        if output.artifacts.len() > 1 {
+            for (artifact, compile_ms) in output.artifacts.iter().zip(compile_ms) {
+                eprintln!(
+                    "PolkaVM build of {}: compile {}, link {}",
+                    artifact.name,
+                    seconds(compile_ms),
+                    seconds(artifact.link_ms)
+                );
+            }
+        }
+        eprintln!(
+            "PolkaVM build: compile {}, link {}, total {}",
+            seconds(output.cargo_build_ms),
+            seconds(output.link_ms),
+            seconds(output.total_ms)
+        );
+    }
+    if builder.debug_blob {
+        warn("Built debug blobs: they are unoptimized and keep symbols, do not deploy them");
+    }
+
+    if let Some(report) = &builder.report {
+        let report_path = builder.manifest_dir().join(report);
+        let report_path = if report_path.is_dir() {
+            report_path.join(REPORT_FILE)
+        } else {
+            report_path
+        };
+        let json = serde_json::to_string_pretty(output)
+            .context_with(|| "Failed to serialize report".to_string())?;
+        fs::write(&report_path, json + "\n")
+            .context_with(|| format!("Failed to write report to {}", report_path.display()))?;
+    }
+    Ok(())
+}
+
+/// Hex hash of everything the linked binaries of `packages` depend on.
+fn build_fingerprint(
+    builder: &PvmBuilder,
+    packages: &[ContractPackage],
+    external_inputs: &[PathBuf],
+    profile: &Profile,
+    target_json: &Path,
+    rustc_version: Option<&str>,
+) -> Result<String> {
+    let mut hasher = Keccak::v256();
+    let mut field = |bytes: &[u8]| {
+        // Length-prefix each field so adjacent fields can't run into each other
+        hasher.update(&(bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    let files = |field: &mut dyn FnMut(&[u8]), paths: &[PathBuf]| {
+        for path in paths.iter().filter(|path| !path.is_dir()) {
+            field(path.as_os_str().as_encoded_bytes());
+            // Missing files (e.g. a `sol!` path that doesn't exist yet) still count
+            field(&fs::read(path).unwrap_or_default());
+        }
+    };
+
+    field(POLKAVM_LINKER_VERSION.as_bytes());
+    field(rustc_version.unwrap_or_default().as_bytes());
+    field(format!("{:?}", builder.effective_linker_options()).as_bytes());
+    field(&[builder.is_64_bit as u8]);
+    field(format!("{:?}", builder.target_instruction_set()).as_bytes());
+    files(&mut field, external_inputs);
+
+    for package in packages {
+        let cmd = build_elf_command(builder, package, profile, target_json)?;
+        field(format!("{:?}", cmd.get_program()).as_bytes());
+        field(format!("{:?}", cmd.get_args().collect::<Vec<_>>()).as_bytes());
+        let mut envs: Vec<_> = cmd.get_envs().collect();
+        envs.sort();
+        field(format!("{envs:?}").as_bytes());
+        files(&mut field, &rerun_if_changed_paths(package.manifest_dir())?);
+    }
+
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    Ok(hex_string(&hash))
+}
+
+/// Type-check the contract packages without linking, see [`PvmBuilder::check`].
+#[allow(clippy::too_many_arguments)]
+fn check_project(
+    builder: &PvmBuilder,
+    packages: &[ContractPackage],
+    names: &[String],
+    env_vars: &[String],
+    profile: &Profile,
+    target_json: &Path,
+    generated_module_path: Option<&Path>,
+    rustc_version: Option<String>,
+) -> Result<BuildOutput> {
+    let toolchain = builder.toolchain.as_deref();
+    if let Some(sysroot) = rustc_sysroot(builder.manifest_dir(), toolchain) {
+        check_rust_src(&sysroot, toolchain)?;
+    }
+    let started = Instant::now();
+    let mut forwarded_warnings = Vec::new();
+    for package in packages {
+        build_elf(
+            builder,
+            package,
+            profile,
+            target_json,
+            &mut forwarded_warnings,
+        )?;
+    }
+    let cargo_build_ms = started.elapsed().as_millis() as u64;
+
+    // Host code embedding the binaries only needs the files to exist
+    if in_build_script() {
+        let out_dir = PathBuf::from(
+            env::var_os("OUT_DIR").expect("`OUT_DIR` is always set for `build.rs` files"),
+        );
+        let mut placeholders = Vec::with_capacity(names.len());
+        for (bin, env_var) in names.iter().zip(env_vars) {
+            let path = out_dir.join(format!("{bin}.check.polkavm"));
+            fs::write(&path, []).context_with(|| format!("Failed to write {}", path.display()))?;
+            println!("cargo:rustc-env={env_var}={}", path.display());
+            placeholders.push(Artifact {
+                name: bin.clone(),
+                path,
+                ..Artifact::default()
+            });
+        }
+        if let Some(module_path) = generated_module_path {
+            write_generated_module(module_path, &placeholders)?;
+        }
+    }
+
+    Ok(BuildOutput {
+        rustc_version,
+        polkavm_linker_version: POLKAVM_LINKER_VERSION.to_string(),
+        cargo_build_ms,
+        instruction_set: instruction_set_name(builder.target_instruction_set()).to_string(),
+        ..BuildOutput::default()
+    })
+}
+
+/// Name of the `cargo:rustc-env` variable exposing the path of `bin`'s `.polkavm` file.
+fn binary_env_var(bin: &str) -> String {
+    format!("PVM_BINARY_{}", upper_snake_case(bin))
+}
+
+/// Whether `feature` is enabled on the crate whose build script is running.
+fn host_feature_enabled(feature: &str) -> bool {
+    env::var_os(format!("CARGO_FEATURE_{}", upper_snake_case(feature))).is_some()
+}
+
+/// The `forwarded` features that are `enabled`, after checking the contract declares all of them.
+fn forwarded_features(
+    forwarded: &[String],
+    cargo_toml: &Path,
+    enabled: impl Fn(&str) -> bool,
+) -> Result<Vec<String>> {
+    if forwarded.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let declared = declared_features(&read_manifest(cargo_toml)?);
+    if let Some(missing) = forwarded.iter().find(|feature| !declared.contains(feature)) {
+        return Err(PvmBuildError::Manifest {
+            path: cargo_toml.to_path_buf(),
+            message: format!(
+                "Forwarded feature `{missing}` is not a feature of the contract; declared features: {}",
+                declared.join(", ")
+            ),
+        });
+    }
+
+    Ok(forwarded
+        .iter()
+        .filter(|feature| enabled(feature))
+        .cloned()
+        .collect())
+}
+
+/// Features a manifest declares: its `[features]` and its optional dependencies.
+fn declared_features(doc: &toml_edit::DocumentMut) -> Vec<String> {
+    let mut features: Vec<String> = doc
+        .get("features")
+        .and_then(|features| features.as_table_like())
+        .map(|table| table.iter().map(|(name, _)| name.to_string()).collect())
+        .unwrap_or_default();
+
+    let optional = doc
+        .get("dependencies")
+        .and_then(|deps| deps.as_table_like())
+        .into_iter()
+        .flat_map(|deps| deps.iter())
+        .filter(|(_, dep)| {
+            dep.get("optional")
+                .and_then(|optional| optional.as_bool())
+                .unwrap_or(false)
+        })
+        .map(|(name, _)| name.to_string());
+    for name in optional {
+        if !features.contains(&name) {
+            features.push(name);
+        }
+    }
+    features
+}
+
+/// Upper-case `name`, replacing anything that isn't alphanumeric with `_`.
+fn upper_snake_case(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Name of the constant holding `bin`'s bytecode in the generated module.
+fn module_const_name(bin: &str) -> String {
+    let name = upper_snake_case(bin);
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{name}")
+    } else {
+        name
+    }
+}
+
+/// Keccak-256 hash of `data`.
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hasher = Keccak::v256();
+    hasher.update(data);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hash
+}
+
+/// Name of `instruction_set` as used by the PolkaVM assembler's `%isa` directive.
+fn instruction_set_name(instruction_set: TargetInstructionSet) -> &'static str {
+    match instruction_set {
+        TargetInstructionSet::ReviveV1 => "revive_v1",
+        TargetInstructionSet::JamV1 => "jam_v1",
+        TargetInstructionSet::Latest => "latest",
+        _ => "unknown",
+    }
+}
+
+/// Describe a linked binary's size relative to its ELF, with its code and data sizes if known.
+fn size_summary(size: u64, elf_size: u64, sections: Option<(u64, u64)>) -> String {
+    let mut summary = format!("{} from {} ELF", human_size(size), human_size(elf_size));
+    if elf_size > 0 && size <= elf_size {
+        let reduction = 100.0 * (elf_size - size) as f64 / elf_size as f64;
+        summary.push_str(&format!(", {reduction:.0}% reduction"));
+    }
+    if let Some((code, data)) = sections {
+        summary.push_str(&format!(
+            "; code {}, data {}",
+            human_size(code),
+            human_size(data)
+        ));
+    }
+    summary
+}
+
+/// `bytes` in bytes below 1 KB, in KB with one decimal otherwise.
+fn human_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{bytes} bytes")
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+/// Copy the report of `cargo build --timings` for `package` into `output_dir`.
+///
+/// Packages of a workspace get their own `<package>.cargo-timing.html`.
+fn copy_cargo_timings(package: &ContractPackage, output_dir: &Path) -> Result<()> {
+    let report = package
+        .target_dir
+        .join("cargo-timings")
+        .join("cargo-timing.html");
+    let dest = match &package.name {
+        Some(name) => output_dir.join(format!("{name}.cargo-timing.html")),
+        None => output_dir.join("cargo-timing.html"),
+    };
+    fs::copy(&report, &dest)
+        .context_with(|| format!("Failed to copy {} to {}", report.display(), dest.display()))?;
+    Ok(())
+}
+
+/// `ms` milliseconds in seconds with one decimal, e.g. `8.3s`.
+fn seconds(ms: u64) -> String {
+    format!("{:.1}s", ms as f64 / 1000.0)
+}
+
+/// `0x`-prefixed lower-case hex encoding of `bytes`.
+fn hex_string(bytes: &[u8]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("0x{hex}")
+}
+
+/// Compress `data` into a gzip file with a fixed header, so the output is reproducible.
+fn gzip(data: &[u8]) -> Vec<u8> {
+    // No file name or modification time; maximum compression; unknown OS
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 2, 0xff];
+    out.extend(miniz_oxide::deflate::compress_to_vec(data, 9));
+    out.extend(crc32(data).to_le_bytes());
+    out.extend((data.len() as u32).to_le_bytes());
+    out
+}
+
+/// The CRC-32 gzip stores to check the decompressed data.
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Directory the `.polkavm` files are written to: the output directory, unless only
+/// their hex encoding is asked for there.
+fn blob_dir(builder: &PvmBuilder, output_dir: &Path) -> PathBuf {
+    if builder.output_format.raw() {
+        output_dir.to_path_buf()
+    } else {
+        builder.target_dir().join("polkavm")
+    }
+}
+
+/// Path of the file holding the `algorithm` digest of the binary at `binary_path`.
+fn code_hash_path(binary_path: &Path, algorithm: CodeHash) -> PathBuf {
+    let mut path = binary_path.as_os_str().to_owned();
+    path.push(".");
+    path.push(algorithm.extension());
+    PathBuf::from(path)
+}
+
+/// Lower-case `name` and drop `-` and `_`, so `my-token`, `my_token` and `MyToken` compare equal.
+fn normalized_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| *c != '-' && *c != '_')
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Find the `.sol` file in `dir` named after `bin`.
+fn find_sol_file(dir: &Path, bin: &str) -> Result<Option<PathBuf>> {
+    let mut entries = fs::read_dir(dir)
+        .context_with(|| format!("Failed to read {}", dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()
+        .context_with(|| format!("Failed to read {}", dir.display()))?;
+    entries.sort();
+
+    let bin = normalized_name(bin);
+    Ok(entries.into_iter().find(|path| {
+        path.extension().is_some_and(|ext| ext == "sol")
+            && path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .is_some_and(|stem| normalized_name(stem) == bin)
+    }))
+}
+
+/// Check the package's ABI constants if the builder or its `[package.metadata.pvm]` asks for it.
+fn verify_abi_consts(builder: &PvmBuilder, package: &ContractPackage) -> Result<()> {
+    let from_manifest = package_manifest(&package.cargo_toml)?
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get("pvm"))
+        .and_then(|pvm| pvm.get("verify-abi-consts"))
+        .and_then(|verify| verify.as_bool())
+        .unwrap_or(false);
+    if !builder.verify_abi_consts && !from_manifest {
+        return Ok(());
+    }
+    let Some(consts) = abi_consts::AbiConsts::read(package.manifest_dir())? else {
+        return Ok(());
+    };
+
+    let sol_path = package.manifest_dir().join(&consts.sol_file);
+    let abi = match solidity_abi(&sol_path, &consts.contract) {
+        Err(PvmBuildError::SolcNotFound) if !builder.verify_abi_consts => {
+            warn(&format!(
+                "solc not found; skipping the ABI constant check of {}",
+                package.cargo_toml.display()
+            ));
+            return Ok(());
+        }
+        abi => abi?,
+    };
+    let source_path = package.manifest_dir().join(&consts.source);
+    let source = fs::read_to_string(&source_path)
+        .context_with(|| format!("Failed to read {}", source_path.display()))?;
+    let drifted = consts.check(&source, &abi);
+    if drifted.is_empty() {
+        return Ok(());
+    }
+    Err(PvmBuildError::AbiConstsDrifted {
+        source_path,
+        sol_path,
+        drifted,
+    })
+}
+
+/// Compile `sol_path` with `solc` and return the ABI of the contract named after `bin`.
+fn solidity_abi(sol_path: &Path, bin: &str) -> Result<serde_json::Value> {
+    let file_name = sol_path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| {
+            PvmBuildError::invalid_config(format!("Invalid file name: {}", sol_path.display()))
+        })?;
+    let content = fs::read_to_string(sol_path)
+        .context_with(|| format!("Failed to read {}", sol_path.display()))?;
+    let input = serde_json::json!({
+        "language": "Solidity",
+        "sources": { file_name: { "content": content } },
+        "settings": { "outputSelection": { "*": { "*": ["abi"] } } }
+    });
+
+    let solc_path = env_var_os(SOLC_PATH_ENV);
+    let mut child = Command::new(solc_path.as_deref().unwrap_or("solc".as_ref()))
+        .arg("--standard-json")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| match &solc_path {
+            None if err.kind() == std::io::ErrorKind::NotFound => PvmBuildError::SolcNotFound,
+            None => PvmBuildError::Io {
+                context: "Failed to spawn solc".to_string(),
+                source: err,
+            },
+            Some(path) => PvmBuildError::Io {
+                context: format!(
+                    "Failed to spawn solc from {SOLC_PATH_ENV}={}",
+                    path.display()
+                ),
+                source: err,
+            },
+        })?;
+    if let Some(stdin) = child.stdin.take() {
+        serde_json::to_writer(stdin, &input)
+            .context_with(|| "Failed to write solc input".to_string())?;
+    }
+    let output = child
+        .wait_with_output()
+        .context_with(|| "Failed to wait for solc".to_string())?;
+    if !output.status.success() {
+        return Err(PvmBuildError::SolcFailed {
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+        });
+    }
+
+    let output: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context_with(|| "Failed to parse solc output".to_string())?;
+    contract_abi(&output, file_name, bin)
+}
+
+/// Pick the ABI of the contract named after `bin` from solc's standard JSON output.
+///
+/// Files declaring a single contract use that contract whatever its name.
+fn contract_abi(
+    output: &serde_json::Value,
+    file_name: &str,
+    bin: &str,
+) -> Result<serde_json::Value> {
+    let contracts = output["contracts"][file_name]
+        .as_object()
+        .filter(|contracts| !contracts.is_empty())
+        .ok_or_else(|| PvmBuildError::SolcFailed {
+            stderr: format!("No contract found in {file_name}: {}", output["errors"]),
+        })?;
+
+    let (_, contract) = contracts
+        .iter()
+        .find(|(name, _)| normalized_name(name) == normalized_name(bin))
+        .or_else(|| contracts.iter().next().filter(|_| contracts.len() == 1))
+        .ok_or_else(|| {
+            PvmBuildError::invalid_config(format!(
+                "No contract named after `{bin}` in {file_name}; available contracts: {}",
+                contracts.keys().cloned().collect::<Vec<_>>().join(", ")
+            ))
+        })?;
+    Ok(contract["abi"].clone())
+}
+
+/// Render the generated module for `artifacts`, given each blob's contents.
+fn generated_module(artifacts: &[(&Artifact, &[u8])]) -> Result<String> {
+    let mut module = String::from("// @generated by cargo-pvm-contract-builder. Do not edit.\n");
+
+    for (artifact, blob) in artifacts {
+        let name = module_const_name(&artifact.name);
+        let path = std::path::absolute(&artifact.path)
+            .context_with(|| format!("Failed to resolve {}", artifact.path.display()))?;
+        let path = path.to_str().ok_or_else(|| PvmBuildError::TemplateError {
+            context: format!("Non UTF-8 path: {}", path.display()),
+        })?;
+
+        let hash = keccak256(blob)
+            .iter()
+            .map(|byte| format!("0x{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        module.push_str(&format!(
+            "\n/// PolkaVM bytecode of the `{bin}` binary.\n\
+             pub const {name}: &[u8] = include_bytes!({path:?});\n\
+             /// Keccak-256 hash of [`{name}`].\n\
+             pub const {name}_CODE_HASH: [u8; 32] = [{hash}];\n",
+            bin = artifact.name,
+        ));
+    }
+
+    Ok(module)
+}
+
+/// Write the generated module through a temporary file so a partial module is never compiled.
+fn write_generated_module(module_path: &Path, artifacts: &[Artifact]) -> Result<()> {
+    let blobs = artifacts
+        .iter()
+        .map(|artifact| {
+            fs::read(&artifact.path)
+                .context_with(|| format!("Failed to read {}", artifact.path.display()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let entries: Vec<_> = artifacts
+        .iter()
+        .zip(&blobs)
+        .map(|(artifact, blob)| (artifact, blob.as_slice()))
+        .collect();
+    let module = generated_module(&entries)?;
+
+    let tmp_path = module_path.with_extension("rs.tmp");
+    fs::write(&tmp_path, module)
+        .context_with(|| format!("Failed to write {}", tmp_path.display()))?;
+    fs::rename(&tmp_path, module_path)
+        .context_with(|| format!("Failed to write {}", module_path.display()))?;
+
+    Ok(())
+}
+
+/// Environment variable names for `bins`, failing if two binaries map to the same name.
+fn binary_env_vars(bins: &[String]) -> Result<Vec<String>> {
+    let vars: Vec<String> = bins.iter().map(|bin| binary_env_var(bin)).collect();
+    for (i, var) in vars.iter().enumerate() {
+        if let Some(j) = vars[..i].iter().position(|other| other == var) {
+            return Err(PvmBuildError::invalid_config(format!(
+                "Binaries `{}` and `{}` both map to the environment variable {var}; rename one of them",
+                bins[j], bins[i]
+            )));
+        }
+    }
+    Ok(vars)
+}
+
+/// Resolve where `build_to` places the binary `bin`.
+fn copy_destination(dest: &Path, bin: &str) -> PathBuf {
+    if dest.is_dir() {
+        dest.join(format!("{bin}.polkavm"))
+    } else {
+        dest.to_path_buf()
+    }
+}
+
+/// Get the path of the PolkaVM target JSON for the requested bitness.
+fn resolve_target_json(is_64_bit: bool) -> Result<PathBuf> {
+    let mut args = polkavm_linker::TargetJsonArgs::default();
+    args.is_64_bit = is_64_bit;
+    polkavm_linker::target_json_path(args)
+        .map_err(|e| PvmBuildError::invalid_config(format!("Failed to get target JSON: {e}")))
+}
+
+/// Directory cargo writes the ELF files to; named after the target JSON.
+fn elf_dir(target_dir: &Path, target_json: &Path, profile: &Profile) -> PathBuf {
+    let target_name = target_json.file_stem().unwrap_or(target_json.as_os_str());
+    target_dir.join(target_name).join(profile.directory())
+}
+
+/// Build the ELF binary using cargo.
+///
+/// Returns the executables cargo reported. Warnings whose rendered text is in
+/// `forwarded_warnings` are not forwarded again; newly forwarded ones are added.
+fn build_elf(
+    builder: &PvmBuilder,
+    package: &ContractPackage,
+    profile: &Profile,
+    target_json: &Path,
+    forwarded_warnings: &mut Vec<String>,
+) -> Result<Vec<PathBuf>> {
+    let mut cmd = build_elf_command(builder, package, profile, target_json)?;
+
+    let verbose = builder.is_verbose();
+
+    if builder.check_only {
+        eprintln!("Checking PolkaVM contract with profile: {profile:?}");
+    } else {
+        eprintln!("Building PolkaVM binary with profile: {profile:?}");
+    }
+
+    // Cargo reports progress on stderr and JSON messages on stdout; stdout of a
+    // build script is reserved for directives
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context_with(|| "Failed to execute cargo build".to_string())?;
+
+    let child_stdout = child.stdout.take().expect("cargo stdout is piped");
+    let child_stderr = child.stderr.take().expect("cargo stderr is piped");
+    let (mut stderr, (errors, executables)) = std::thread::scope(|scope| {
+        // Read concurrently, so neither pipe fills up while the other is drained
+        let messages = scope.spawn(|| -> Result<(String, Vec<PathBuf>)> {
+            let mut errors = String::new();
+            let mut executables = Vec::new();
+            for line in BufReader::new(child_stdout).lines() {
+                let line = line.context_with(|| "Failed to read cargo output".to_string())?;
+                match serde_json::from_str(&line) {
+                    Ok(CargoMessage::CompilerMessage { message, target }) => {
+                        let rendered = message.rendered.as_deref().unwrap_or(&message.message);
+                        if message.level.starts_with("error") {
+                            eprint!("{rendered}");
+                            errors.push_str(rendered);
+                        } else if verbose {
+                            eprint!("{rendered}");
+                        } else if message.level == "warning"
+                            // Span-less warnings are the per-crate "N warnings emitted" summaries
+                            && !message.spans.is_empty()
+                            && let Some(warning) = forwarded_diagnostic(
+                                target.as_ref().map(|target| target.name.as_str()),
+                                rendered,
+                                forwarded_warnings,
+                            )
+                        {
+                            warn(&warning);
+                        }
+                    }
+                    Ok(CargoMessage::CompilerArtifact {
+                        executable: Some(executable),
+                    }) => executables.push(executable),
+                    _ => {}
+                }
+            }
+            Ok((errors, executables))
+        });
+
+        let mut stderr = String::new();
+        for line in BufReader::new(child_stderr).lines() {
+            let line = line.context_with(|| "Failed to read cargo output".to_string())?;
+            if verbose {
+                eprintln!("{line}");
+            } else if let Some(warning) = forwarded_warning(&line) {
+                warn(warning);
+            }
+            stderr.push_str(&line);
+            stderr.push('\n');
+        }
+        let messages = messages.join().expect("cargo output thread panicked")?;
+        Ok::<_, PvmBuildError>((stderr, messages))
+    })?;
+
+    let status = child
+        .wait()
+        .context_with(|| "Failed to wait for cargo build".to_string())?;
+    if !status.success() {
+        // With JSON messages, stderr only holds cargo's progress and summary
+        stderr.insert_str(0, &errors);
+        return Err(PvmBuildError::CargoFailed { stderr });
+    }
+
+    Ok(executables)
+}
+
+/// A line of `cargo build --message-format json` output.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "reason", rename_all = "kebab-case")]
+enum CargoMessage {
+    CompilerMessage {
+        message: CompilerDiagnostic,
+        /// The target being compiled, e.g. the contract's binary.
+        target: Option<CargoTarget>,
+    },
+    CompilerArtifact {
+        executable: Option<PathBuf>,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// A target of a package, as embedded in a [`CargoMessage`].
+#[derive(Debug, Deserialize)]
+struct CargoTarget {
+    name: String,
+}
+
+/// A diagnostic emitted by rustc, as embedded in a [`CargoMessage`].
+#[derive(Debug, Deserialize)]
+struct CompilerDiagnostic {
+    level: String,
+    message: String,
+    rendered: Option<String>,
+    spans: Vec<serde_json::Value>,
+}
+
+/// Fail if the toolchain at `sysroot` lacks the `rust-src` component `-Zbuild-std` needs.
+///
+/// `toolchain` names the toolchain in the error; otherwise the sysroot's directory
+/// name is used, which is the toolchain name for rustup installs.
+fn check_rust_src(sysroot: &Path, toolchain: Option<&str>) -> Result<()> {
+    if sysroot
+        .join("lib/rustlib/src/rust/library/core/Cargo.toml")
+        .exists()
+    {
+        return Ok(());
+    }
+
+    let toolchain = toolchain
+        .or_else(|| sysroot.file_name()?.to_str())
+        .unwrap_or("<toolchain>");
+    Err(PvmBuildError::RustSrcMissing {
+        toolchain: toolchain.to_string(),
+    })
+}
+
+/// First rustc release that accepts `-Cpanic=immediate-abort`.
+const IMMEDIATE_ABORT_MIN_VERSION: (u32, u32) = (1, 92);
+
+/// Fail if `rustc_version`, from the toolchain the nested build uses, predates
+/// `-Cpanic=immediate-abort`. Unknown versions are left for cargo to report.
+fn check_immediate_abort_support(rustc_version: Option<&str>) -> Result<()> {
+    match rustc_version {
+        Some(version)
+            if parse_rustc_version(version).is_some_and(|v| v < IMMEDIATE_ABORT_MIN_VERSION) =>
+        {
+            Err(PvmBuildError::UnsupportedToolchain {
+                version: version.to_string(),
+            })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Major and minor version of `rustc --version` output such as
+/// `rustc 1.92.0-nightly (abcdef123 2025-09-30)` or `rustc 1.91.0-beta.3 (…)`.
+fn parse_rustc_version(version: &str) -> Option<(u32, u32)> {
+    let number = version.strip_prefix("rustc ")?.split_whitespace().next()?;
+    let mut parts = number.split(['.', '-']);
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Version string of the rustc the nested build uses, if it can be determined.
+fn rustc_version(work_dir: &Path, toolchain: Option<&str>) -> Option<String> {
+    rustc_output(work_dir, toolchain, &["--version"])
+}
+
+/// Sysroot of the rustc the nested build uses, if it can be determined.
+fn rustc_sysroot(work_dir: &Path, toolchain: Option<&str>) -> Option<PathBuf> {
+    rustc_output(work_dir, toolchain, &["--print", "sysroot"]).map(PathBuf::from)
+}
+
+/// Trimmed stdout of `rustc <args>` for the contract's toolchain.
+fn rustc_output(work_dir: &Path, toolchain: Option<&str>, args: &[&str]) -> Option<String> {
+    let mut cmd = Command::new("rustc");
+    cmd.args(args).current_dir(work_dir);
+    if let Some(toolchain) = toolchain {
+        cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+    } else if !in_build_script() {
+        cmd.env_remove("RUSTUP_TOOLCHAIN");
+    }
+    let output = cmd.output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether only a type-check was asked for, explicitly or by the tool running the build script.
+fn check_only_requested() -> bool {
+    if is_truthy(env_var(CHECK_ONLY_ENV).as_deref()) {
+        return true;
+    }
+    ["RUSTC_WORKSPACE_WRAPPER", "RUSTC_WRAPPER"]
+        .iter()
+        .filter_map(|key| env_var_os(key))
+        .any(|wrapper| is_check_only_wrapper(Path::new(&wrapper)))
+}
+
+/// Whether `wrapper` is a tool that only type-checks, like rust-analyzer.
+fn is_check_only_wrapper(wrapper: &Path) -> bool {
+    wrapper
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .is_some_and(|stem| {
+            CHECK_ONLY_WRAPPERS
+                .iter()
+                .any(|tool| stem.starts_with(tool))
+        })
+}
+
+/// Whether an environment variable value switches a flag on.
+fn is_truthy(value: Option<&str>) -> bool {
+    matches!(value.map(str::trim), Some("1" | "true" | "yes" | "on"))
+}
+
+/// The message of a `warning:` line from the nested build, if it should be forwarded.
+fn forwarded_warning(line: &str) -> Option<&str> {
+    let message = line.strip_prefix("warning: ")?;
+    // The per-crate summaries only repeat what was already forwarded
+    if message.starts_with('`') && message.contains(") generated ") {
+        return None;
+    }
+    Some(message)
+}
+
+/// The `rendered` warning of the nested build's `target`, tagged as `[pvm:<target>]`,
+/// or `None` if an identical warning was already forwarded, e.g. for another binary
+/// including the same module.
+fn forwarded_diagnostic(
+    target: Option<&str>,
+    rendered: &str,
+    forwarded: &mut Vec<String>,
+) -> Option<String> {
+    if forwarded.iter().any(|seen| seen == rendered) {
+        return None;
+    }
+    forwarded.push(rendered.to_string());
+    let rendered = rendered.trim_end();
+    let rendered = rendered.strip_prefix("warning: ").unwrap_or(rendered);
+    Some(match target {
+        Some(target) => format!("[pvm:{target}] {rendered}"),
+        None => format!("[pvm] {rendered}"),
+    })
+}
+
+/// Combine inherited and extra flags with the builder's own, which go last so they take precedence.
+///
+/// `immediate_abort` adds `-Cpanic=immediate-abort`, which drops panic messages from the binary.
+fn nested_rustflags(inherited: Option<&str>, extra: &[String], immediate_abort: bool) -> String {
+    inherited
+        .into_iter()
+        .chain(extra.iter().map(String::as_str))
+        .chain(immediate_abort.then_some("-Zunstable-options -Cpanic=immediate-abort"))
+        .filter(|flags| !flags.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Host paths that can leak into a build, with the prefix reproducible builds remap them to.
+///
+/// More specific paths come last since rustc applies the last matching remap.
+fn host_paths(
+    manifest_dir: &Path,
+    target_dir: &Path,
+    sysroot: Option<PathBuf>,
+) -> Vec<(PathBuf, &'static str)> {
+    let home = env_var_os("HOME").map(PathBuf::from);
+    let cargo_home = env_var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| home.as_ref().map(|home| home.join(".cargo")));
+
+    let mut paths = Vec::new();
+    paths.extend(home.map(|home| (home, "/home")));
+    paths.extend(cargo_home.map(|cargo_home| (cargo_home, "/cargo")));
+    paths.extend(sysroot.map(|sysroot| (sysroot, "/rustc")));
+    paths.push((manifest_dir.to_path_buf(), "/contract"));
+    paths.push((target_dir.to_path_buf(), "/target"));
+    paths
+}
+
+/// `--remap-path-prefix` flags for the given paths.
+///
+/// `RUSTFLAGS` is split on whitespace, so paths containing spaces are skipped with a warning.
+fn remap_path_prefix_flags(remaps: &[(PathBuf, &str)]) -> Vec<String> {
+    remaps
+        .iter()
+        .filter_map(|(from, to)| {
+            let from = from.display().to_string();
+            if from.chars().any(char::is_whitespace) {
+                warn(&format!(
+                    "Cannot remap `{from}` for a reproducible build: the path contains whitespace"
+                ));
+                return None;
+            }
+            Some(format!("--remap-path-prefix={from}={to}"))
+        })
+        .collect()
+}
+
+/// [`host_paths`] of `package`, including the sysroot of the toolchain building it.
+fn package_host_paths(
+    builder: &PvmBuilder,
+    package: &ContractPackage,
+) -> Vec<(PathBuf, &'static str)> {
+    let sysroot = rustc_sysroot(package.manifest_dir(), builder.toolchain.as_deref());
+    host_paths(package.manifest_dir(), &package.target_dir, sysroot)
+}
+
+/// The host paths a linked binary still contains.
+fn leaked_host_paths<'a>(binary: &[u8], paths: &'a [(PathBuf, &str)]) -> Vec<&'a Path> {
+    paths
+        .iter()
+        .map(|(path, _)| path.as_path())
+        .filter(|path| {
+            let needle = path.as_os_str().as_encoded_bytes();
+            !needle.is_empty() && binary.windows(needle.len()).any(|window| window == needle)
+        })
+        .collect()
+}
+
+/// A `cargo` command for the contract's toolchain.
+fn cargo_command(toolchain: Option<&str>) -> Command {
+    if let Some(toolchain) = toolchain {
+        // `CARGO` belongs to the host toolchain, so go through rustup's proxy
+        let mut cmd = Command::new("cargo");
+        cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+        cmd
+    } else if in_build_script() {
+        Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+    } else {
+        // Outside a build script, go through rustup so the contract's rust-toolchain.toml applies
+        let mut cmd = Command::new("cargo");
+        cmd.env_remove("RUSTUP_TOOLCHAIN");
+        cmd
+    }
+}
+
+/// Construct the nested `cargo build` invocation for the contract.
+fn build_elf_command(
+    builder: &PvmBuilder,
+    package: &ContractPackage,
+    profile: &Profile,
+    target_json: &Path,
+) -> Result<Command> {
+    let manifest_path = package.cargo_toml.as_path();
+    let inherited = builder
+        .inherit_rustflags
+        .then(|| env_var("RUSTFLAGS"))
+        .flatten();
+    let work_dir = manifest_path
+        .parent()
+        .ok_or_else(|| PvmBuildError::Manifest {
+            path: manifest_path.to_path_buf(),
+            message: "Invalid manifest path".to_string(),
+        })?;
+
+    let mut extra_rustflags = builder.extra_rustflags.clone();
+    if builder.reproducible {
+        let remaps = package_host_paths(builder, package);
+        extra_rustflags.extend(remap_path_prefix_flags(&remaps));
+    }
+    let rustflags = nested_rustflags(inherited.as_deref(), &extra_rustflags, !builder.debug_blob);
+
+    let mut cmd = cargo_command(builder.toolchain.as_deref());
+    if builder.clean_env {
+        cmd.env_clear();
+        for key in CLEAN_ENV_KEPT {
+            if let Some(value) = env::var_os(key) {
+                cmd.env(key, value);
+            }
+        }
+        match &builder.toolchain {
+            Some(toolchain) => {
+                cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+            }
+            // `CARGO` is the host toolchain's, so keep rustup pointing at it
+            None if in_build_script() => {
+                if let Some(toolchain) = env_var_os("RUSTUP_TOOLCHAIN") {
+                    cmd.env("RUSTUP_TOOLCHAIN", toolchain);
+                }
+            }
+            None => {}
+        }
+    }
+    // Set explicitly, so the fingerprint covers them
+    for key in &builder.env_passthrough {
+        if let Some(value) = env::var_os(key) {
+            cmd.env(key, value);
+        }
+    }
+    for (key, value) in &builder.envs {
+        cmd.env(key, value);
+    }
+    cmd.current_dir(work_dir)
+        .env_remove("CARGO_ENCODED_RUSTFLAGS") // We set RUSTFLAGS, but cargo prefers this one
+        .env_remove("RUSTC") // Prevent host toolchain override from build.rs
+        .env("RUSTFLAGS", &rustflags)
+        // Disable strip during ELF build - it conflicts with --emit-relocs required by PolkaVM.
+        // Stripping is done later by polkavm_linker after processing relocations.
+        .env("CARGO_PROFILE_RELEASE_STRIP", "false")
+        .env("RUSTC_BOOTSTRAP", "1")
+        .env(INTERNAL_BUILD_ENV, "1")
+        .env_remove(HEAP_SIZE_ENV)
+        .arg(if builder.check_only { "check" } else { "build" })
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .arg("--target-dir")
+        .arg(&package.target_dir)
+        .arg("--profile")
+        .arg(profile.cargo_arg())
+        .arg("--target")
+        .arg(target_json)
+        .arg(build_std_arg(builder.build_std.as_deref())?);
+
+    if !builder.build_std_features.is_empty() {
+        cmd.arg(format!(
+            "-Zbuild-std-features={}",
+            builder.build_std_features.join(",")
+        ));
+    }
+
+    if let Some(name) = &package.name {
+        cmd.arg("--package").arg(name);
+    }
+
+    for target in &package.targets {
+        match target {
+            ContractTarget::Bin(name) => cmd.arg("--bin").arg(name),
+            ContractTarget::Lib(_) => cmd.arg("--lib"),
+        };
+    }
+
+    if let Some(size) = builder.heap_size {
+        cmd.env(HEAP_SIZE_ENV, size.to_string());
+    }
+
+    if builder.reproducible && env_var_os("SOURCE_DATE_EPOCH").is_none() {
+        cmd.env("SOURCE_DATE_EPOCH", "0");
+    }
+
+    if builder.all_features {
+        if !builder.features.is_empty() {
+            return Err(PvmBuildError::invalid_config(
+                "`with_all_features` cannot be combined with `with_features`",
+            ));
+        }
+        cmd.arg("--all-features");
+    }
+
+    let mut features = builder.features.clone();
+    features.extend(forwarded_features(
+        &builder.forwarded_features,
+        manifest_path,
+        host_feature_enabled,
+    )?);
+    if !features.is_empty() {
+        cmd.arg("--features").arg(features.join(","));
+    }
+
+    if builder.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+
+    if builder.frozen {
+        cmd.arg("--frozen");
+    } else {
+        if builder.locked {
+            cmd.arg("--locked");
+        }
+
+        if builder.offline || is_truthy(env_var(NET_OFFLINE_ENV).as_deref()) {
+            cmd.arg("--offline");
+        }
+    }
+
+    if builder.cargo_timings {
+        cmd.arg("--timings");
+    }
+
+    // Diagnostics are parsed to forward warnings and report errors
+    cmd.arg("--message-format").arg("json");
+
+    check_cargo_args(&builder.cargo_args)?;
+    cmd.args(&builder.cargo_args);
+
+    Ok(cmd)
+}
+
+/// The `-Zbuild-std` flag for the configured crates, `core` and `alloc` by default.
+fn build_std_arg(crates: Option<&[String]>) -> Result<String> {
+    match crates {
+        None => Ok("-Zbuild-std=core,alloc".to_string()),
+        Some([]) => Err(PvmBuildError::invalid_config(
+            "`with_build_std` needs at least one crate, e.g. `core`",
+        )),
+        Some(crates) => Ok(format!("-Zbuild-std={}", crates.join(","))),
+    }
+}
+
+/// Variables kept by [`PvmBuilder::with_clean_env`], on top of `RUSTUP_TOOLCHAIN`.
+const CLEAN_ENV_KEPT: &[&str] = &[
+    "PATH",
+    "HOME",
+    "CARGO_HOME",
+    "RUSTUP_HOME",
+    "CARGO_NET_OFFLINE",
+    "SOURCE_DATE_EPOCH",
+    "TMPDIR",
+    // Windows
+    "USERPROFILE",
+    "SYSTEMROOT",
+    "TEMP",
+    "TMP",
+];
+
+/// Flags the builder sets itself to find the ELF afterwards.
+const MANAGED_CARGO_FLAGS: &[&str] = &[
+    "--target-dir",
+    "--manifest-path",
+    "--target",
+    "--profile",
+    "--release",
+];
+
+/// Reject extra cargo arguments that would override [`MANAGED_CARGO_FLAGS`].
+fn check_cargo_args(args: &[OsString]) -> Result<()> {
+    for arg in args.iter().filter_map(|arg| arg.to_str()) {
+        let flag = arg.split_once('=').map_or(arg, |(flag, _)| flag);
+        if MANAGED_CARGO_FLAGS.contains(&flag) {
+            return Err(PvmBuildError::invalid_config(format!(
+                "Cargo argument `{arg}` is managed by the builder; passing it would put the ELF \
+                 where the builder doesn't look for it"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Link an ELF binary to PolkaVM bytecode, returning the size of the program.
+///
+/// Linking is skipped if the program at `output_path` was linked from the same ELF
+/// with the same settings, as recorded in its [`link_hash_path`] file in `hash_dir`.
+fn link_to_polkavm(
+    elf_path: &Path,
+    output_path: &Path,
+    hash_dir: &Path,
+    instruction_set: TargetInstructionSet,
+    options: &LinkerOptions,
+) -> Result<u64> {
+    let elf_bytes = fs::read(elf_path)
+        .context_with(|| format!("Failed to read ELF from {}", elf_path.display()))?;
+
+    let hash = link_input_hash(&elf_bytes, instruction_set, options);
+    let hash_path = link_hash_path(hash_dir, output_path);
+    if fs::read_to_string(&hash_path).is_ok_and(|previous| previous.trim_end() == hash)
+        && let Ok(metadata) = fs::metadata(output_path)
+    {
+        eprintln!(
+            "PolkaVM link skipped (ELF unchanged): {}",
+            output_path.display()
+        );
+        return Ok(metadata.len());
+    }
+    // A link that fails halfway must not leave a matching hash behind
+    if hash_path.exists() {
+        fs::remove_file(&hash_path)
+            .context_with(|| format!("Failed to remove {}", hash_path.display()))?;
+    }
+
+    let linked =
+        polkavm_linker::program_from_elf(options.to_config()?, instruction_set, &elf_bytes)
+            .map_err(|e| PvmBuildError::LinkerFailed {
+                elf_path: elf_path.to_path_buf(),
+                source: e.to_string(),
+            })?;
+
+    fs::write(output_path, &linked).context_with(|| {
+        format!(
+            "Failed to write PolkaVM bytecode to {}",
+            output_path.display()
+        )
+    })?;
+    fs::create_dir_all(hash_dir)
+        .context_with(|| format!("Failed to create {}", hash_dir.display()))?;
+    fs::write(&hash_path, format!("{hash}\n"))
+        .context_with(|| format!("Failed to write {}", hash_path.display()))?;
+
+    Ok(linked.len() as u64)
+}
+
+/// Hash of everything a linked program depends on: the ELF, the linker and its settings.
+fn link_input_hash(
+    elf_bytes: &[u8],
+    instruction_set: TargetInstructionSet,
+    options: &LinkerOptions,
+) -> String {
+    let settings = format!("{POLKAVM_LINKER_VERSION} {instruction_set:?} {options:?}");
+    let mut hasher = Keccak::v256();
+    hasher.update(&(settings.len() as u64).to_le_bytes());
+    hasher.update(settings.as_bytes());
+    hasher.update(elf_bytes);
+    let mut hash = [0u8; 32];
+    hasher.finalize(&mut hash);
+    hex_string(&hash)
+}
+
+/// Path of the file in `hash_dir` recording what the program at `binary_path` was
+/// linked from, keyed by the full path so copies in other output directories don't
+/// share it.
+fn link_hash_path(hash_dir: &Path, binary_path: &Path) -> PathBuf {
+    let key = hex_string(&keccak256(binary_path.as_os_str().as_encoded_bytes()));
+    let name = binary_path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy();
+    hash_dir.join(format!("{name}-{}.elf-hash", &key[2..18]))
+}
+
+/// Enforce the size budget of `bin`: fail above `max_size`, warn above `size_warning`.
+fn check_size(
+    bin: &str,
+    size: u64,
+    max_size: Option<u64>,
+    size_warning: Option<u64>,
+) -> Result<()> {
+    if let Some(limit) = max_size
+        && size > limit
+    {
+        return Err(PvmBuildError::SizeLimitExceeded {
+            bin: bin.to_string(),
+            size,
+            limit,
+        });
+    }
+    if let Some(budget) = size_warning
+        && size > budget
+    {
+        warn(&format!(
+            "PolkaVM binary `{bin}` is {size} bytes, over its budget of {budget} bytes"
+        ));
+    }
+    Ok(())
+}
+
+/// Fail if `exports` lacks any of the `required` entry points.
+fn check_exports(bin: &str, exports: &[String], required: &[String]) -> Result<()> {
+    let missing: Vec<_> = required
+        .iter()
+        .filter(|name| !exports.contains(name))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(PvmBuildError::MissingExports {
+        bin: bin.to_string(),
+        missing,
+        found: exports.to_vec(),
+    })
+}
+
+/// Link each `(elf, output)` pair in parallel, returning the size of each program and
+/// how long linking it took, in the order of `links`.
+///
+/// Every binary is attempted; if several fail, all of their errors are returned.
+fn link_all(
+    links: &[(&Path, PathBuf)],
+    hash_dir: &Path,
+    instruction_set: TargetInstructionSet,
+    options: &LinkerOptions,
+) -> Result<Vec<(u64, u64)>> {
+    let link = |(elf_path, output_path): &(&Path, PathBuf)| {
+        let started = Instant::now();
+        link_to_polkavm(elf_path, output_path, hash_dir, instruction_set, options)
+            .map(|size| (size, started.elapsed().as_millis() as u64))
+    };
+
+    let mut linked = Vec::with_capacity(links.len());
+    let mut errors = Vec::new();
+    for result in link_each(links, link) {
+        match result {
+            Ok(link) => linked.push(link),
+            Err(err) => errors.push(err),
+        }
+    }
+    match errors.len() {
+        0 => Ok(linked),
+        1 => Err(errors.remove(0)),
+        _ => Err(PvmBuildError::Multiple { errors }),
+    }
+}
+
+/// Run `link` on every item of `links` on rayon's thread pool, in the order of `links`.
+#[cfg(feature = "parallel")]
+fn link_each<T: Sync, R: Send>(links: &[T], link: impl Fn(&T) -> R + Send + Sync) -> Vec<R> {
+    use rayon::prelude::*;
+
+    links.par_iter().map(link).collect()
+}
+
+/// Run `link` on every item of `links` on up to `available_parallelism` scoped threads,
+/// in the order of `links`.
+#[cfg(not(feature = "parallel"))]
+fn link_each<T: Sync, R: Send>(links: &[T], link: impl Fn(&T) -> R + Sync) -> Vec<R> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let workers = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(links.len());
+    let next = AtomicUsize::new(0);
+
+    let mut results: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = links.get(i) else {
+                            return results;
+                        };
+                        results.push((i, link(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("linker thread panicked"))
+            .collect()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use abi_consts::{AbiConst, AbiConstKind, AbiConsts};
+
+    fn test_builder() -> PvmBuilder {
+        PvmBuilder::for_manifest(PathBuf::from("/contract/Cargo.toml"))
+    }
+
+    fn test_package(target: ContractTarget) -> ContractPackage {
+        ContractPackage {
+            name: None,
+            cargo_toml: PathBuf::from("/contract/Cargo.toml"),
+            targets: vec![target],
+            target_dir: PathBuf::from("/target/pvmbuild"),
+        }
+    }
+
+    fn try_command_args(builder: &PvmBuilder) -> Result<Vec<String>> {
+        let profile = Profile::Release;
+        let cmd = build_elf_command(
+            builder,
+            &test_package(ContractTarget::Bin("contract".to_string())),
+            &profile,
+            Path::new("/target.json"),
+        )?;
+        Ok(cmd
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect())
+    }
+
+    fn command_args(builder: &PvmBuilder) -> Vec<String> {
+        try_command_args(builder).unwrap()
+    }
+
+    #[test]
+    fn contract_targets_from_manifest() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let manifest = dir.path().join("Cargo.toml");
+        let targets = |content: &str| {
+            fs::write(&manifest, content).unwrap();
+            get_contract_targets(&manifest).unwrap()
+        };
+
+        assert_eq!(
+            targets("[package]\nname = \"my-token\"\n"),
+            [ContractTarget::Bin("my-token".to_string())]
+        );
+        assert_eq!(
+            targets(
+                "[package]\nname = \"my-token\"\n\n[[bin]]\nname = \"a\"\n\n[[bin]]\nname = \"b\"\n"
+            ),
+            [
+                ContractTarget::Bin("a".to_string()),
+                ContractTarget::Bin("b".to_string())
+            ]
+        );
+        assert_eq!(
+            targets("[package]\nname = \"my-token\"\n\n[lib]\ncrate-type = [\"cdylib\"]\n"),
+            [ContractTarget::Lib("my_token".to_string())]
+        );
+        assert_eq!(
+            targets(
+                "[package]\nname = \"my-token\"\n\n[lib]\nname = \"token\"\ncrate-type = [\"cdylib\"]\n"
+            ),
+            [ContractTarget::Lib("token".to_string())]
+        );
+        assert_eq!(
+            ContractTarget::Lib("token".to_string()).elf_file_name(),
+            "token.elf"
+        );
+    }
+
+    #[test]
+    fn bins_are_selected_and_excluded() {
+        let available: Vec<String> = ["token", "vault", "helper"].map(String::from).into();
+        let names = |included: Option<&[&str]>, excluded: &[&str]| {
+            let included: Option<Vec<String>> =
+                included.map(|names| names.iter().map(|name| name.to_string()).collect());
+            let excluded: Vec<String> = excluded.iter().map(|name| name.to_string()).collect();
+            select_bins(&available, included.as_deref(), &excluded).map(|targets| {
+                targets
+                    .iter()
+                    .map(|target| target.name().to_string())
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        assert_eq!(names(None, &[]).unwrap(), ["token", "vault", "helper"]);
+        assert_eq!(names(None, &["helper"]).unwrap(), ["token", "vault"]);
+        assert_eq!(
+            names(Some(&["vault", "token"]), &[]).unwrap(),
+            ["vault", "token"]
+        );
+        assert_eq!(
+            names(Some(&["token", "vault"]), &["helper"]).unwrap(),
+            ["token", "vault"]
+        );
+
+        let err = names(Some(&["tokn"]), &[]).unwrap_err();
+        assert!(matches!(err, PvmBuildError::InvalidConfig { .. }));
+        assert_eq!(
+            err.to_string(),
+            "Unknown binary `tokn`, available: token, vault, helper"
+        );
+        let err = names(None, &["helpr"]).unwrap_err();
+        assert!(err.to_string().contains("Unknown binary `helpr`"));
+        let err = names(Some(&["token"]), &["token"]).unwrap_err();
+        assert!(err.to_string().contains("both selected"));
+
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"token\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(dir.path().join("src/bin")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(dir.path().join("src/bin/helper.rs"), "fn main() {}").unwrap();
+        let mut bins = package_bins(&manifest, &read_manifest(&manifest).unwrap()).unwrap();
+        bins.sort();
+        assert_eq!(bins, ["helper", "token"]);
+    }
+
+    #[test]
+    fn lib_target_uses_lib_flag() {
+        let builder = test_builder();
+        let cmd = build_elf_command(
+            &builder,
+            &test_package(ContractTarget::Lib("contract".to_string())),
+            &Profile::Release,
+            Path::new("/target.json"),
+        )
+        .unwrap();
+        let args: Vec<_> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        assert!(args.contains(&"--lib".to_string()));
+        assert!(!args.contains(&"--bin".to_string()));
+    }
+
+    #[test]
+    fn workspace_package_uses_package_flag() {
+        let mut package = test_package(ContractTarget::Bin("token".to_string()));
+        package.name = Some("token".to_string());
+        let cmd = build_elf_command(
+            &test_builder(),
+            &package,
+            &Profile::Release,
+            Path::new("/target.json"),
+        )
+        .unwrap();
+        let args: Vec<_> = cmd
+            .get_args()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        let package_flag = args.iter().position(|arg| arg == "--package").unwrap();
+        assert_eq!(args[package_flag + 1], "token");
+    }
+
+    #[test]
+    fn workspace_members_are_selected() {
+        let members = || -> Vec<WorkspaceMember> {
+            serde_json::from_str(
+                r#"[
+                    {"name": "token-a", "manifest_path": "/ws/token-a/Cargo.toml",
+                     "targets": [{"name": "token-a", "kind": ["bin"]}]},
+                    {"name": "token-b", "manifest_path": "/ws/token-b/Cargo.toml",
+                     "targets": [{"name": "token-b", "kind": ["cdylib"]}]},
+                    {"name": "wrapper", "manifest_path": "/ws/wrapper/Cargo.toml",
+                     "targets": [{"name": "wrapper", "kind": ["bin"]},
+                                 {"name": "build-script-build", "kind": ["custom-build"]}]},
+                    {"name": "shared", "manifest_path": "/ws/shared/Cargo.toml",
+                     "targets": [{"name": "shared", "kind": ["lib"]}]}
+                ]"#,
+            )
+            .unwrap()
+        };
+        let own = Path::new("/ws/wrapper/Cargo.toml");
+        let names = |selected: Vec<WorkspaceMember>| -> Vec<String> {
+            selected.into_iter().map(|member| member.name).collect()
+        };
+
+        let all = select_members(members(), &[], true, own).unwrap();
+        assert_eq!(
+            all.iter()
+                .map(WorkspaceMember::contract_targets)
+                .collect::<Vec<_>>(),
+            [
+                vec![ContractTarget::Bin("token-a".to_string())],
+                vec![ContractTarget::Lib("token_b".to_string())]
+            ]
+        );
+        assert_eq!(
+            names(select_members(members(), &["token-b".to_string()], false, own).unwrap()),
+            ["token-b"]
+        );
+
+        let err = select_members(members(), &["missing".to_string()], false, own).unwrap_err();
+        assert!(err.to_string().contains("not a member"));
+        let err = select_members(members(), &["shared".to_string()], false, own).unwrap_err();
+        assert!(err.to_string().contains("no binary or cdylib targets"));
+    }
+
+    #[test]
+    fn feature_selection_flags() {
+        let args = command_args(&test_builder().with_all_features());
+        assert!(args.contains(&"--all-features".to_string()));
+        assert!(!args.contains(&"--no-default-features".to_string()));
+
+        let args = command_args(
+            &test_builder()
+                .with_no_default_features()
+                .with_features(["x"]),
+        );
+        assert!(args.contains(&"--no-default-features".to_string()));
+        assert!(args.contains(&"--features".to_string()));
+
+        let err =
+            try_command_args(&test_builder().with_all_features().with_features(["x"])).unwrap_err();
+        assert!(matches!(err, PvmBuildError::InvalidConfig { .. }));
+        assert!(err.to_string().contains("cannot be combined"));
+    }
+
+    #[test]
+    fn errors_are_typed() {
+        let err = read_manifest(Path::new("/nonexistent/Cargo.toml")).unwrap_err();
+        assert!(matches!(err, PvmBuildError::Io { .. }));
+        assert_eq!(err.to_string(), "Failed to read /nonexistent/Cargo.toml");
+        let source = std::error::Error::source(&err).expect("I/O errors keep their source");
+        assert!(source.downcast_ref::<std::io::Error>().is_some());
+
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(&manifest, "[package\n").unwrap();
+        assert!(matches!(
+            read_manifest(&manifest).unwrap_err(),
+            PvmBuildError::Manifest { path, .. } if path == manifest
+        ));
+
+        fs::write(&manifest, "[package]\nname = \"contract\"\n[lib]\n").unwrap();
+        let builder = PvmBuilder::for_manifest(manifest)
+            .with_bin("contract")
+            .with_lib();
+        assert!(matches!(
+            contract_packages(&builder).unwrap_err(),
+            PvmBuildError::InvalidConfig { .. }
+        ));
+    }
+
+    #[test]
+    fn contract_manifest_must_be_a_package() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        let message = |builder: PvmBuilder| match contract_packages(&builder).unwrap_err() {
+            PvmBuildError::Manifest { message, .. } => message,
+            err => panic!("unexpected error: {err}"),
+        };
+
+        assert_eq!(
+            message(PvmBuilder::for_manifest(manifest.clone())),
+            "No such manifest"
+        );
+
+        fs::write(&manifest, "[workspace]\nmembers = [\"contracts/*\"]\n").unwrap();
+        assert!(message(PvmBuilder::for_manifest(manifest.clone())).contains("virtual workspace"));
+
+        let builder = PvmBuilder::new_with_manifest(&manifest);
+        assert_eq!(builder.project_cargo_toml, manifest);
+        let builder = PvmBuilder::new_with_manifest("contracts/token/Cargo.toml");
+        assert_eq!(
+            builder.project_cargo_toml,
+            Path::new(env!("CARGO_MANIFEST_DIR")).join("contracts/token/Cargo.toml")
+        );
+    }
+
+    #[test]
+    fn output_dir_resolution() {
+        let manifest_dir = Path::new("/contract");
+        let default = || PathBuf::from("/contract/target");
+
+        assert_eq!(resolve_dir(None, None, manifest_dir, default), default());
+        assert_eq!(
+            resolve_dir(Some(Path::new("artifacts")), None, manifest_dir, default),
+            Path::new("/contract/artifacts")
+        );
+        assert_eq!(
+            resolve_dir(Some(Path::new("/abs")), None, manifest_dir, default),
+            Path::new("/abs")
+        );
+        assert_eq!(
+            resolve_dir(
+                Some(Path::new("artifacts")),
+                Some(PathBuf::from("ci-out")),
+                manifest_dir,
+                default
+            ),
+            Path::new("/contract/ci-out")
+        );
+    }
+
+    #[test]
+    fn build_dir_resolution() {
+        let manifest_dir = Path::new("/contract");
+        let default = || PathBuf::from("/contract/target/pvmbuild");
+        assert_eq!(
+            resolve_dir(
+                Some(Path::new("/ramdisk/pvm")),
+                Some(PathBuf::from("/cache/pvm")),
+                manifest_dir,
+                default
+            ),
+            Path::new("/cache/pvm")
+        );
+        assert_eq!(
+            resolve_dir(None, Some(PathBuf::from("pvm")), manifest_dir, default),
+            Path::new("/contract/pvm")
+        );
+
+        // The default layout
+        let out_dir = Path::new("/ws/target/release/build/host-1a2b/out");
+        assert_eq!(target_root(out_dir, None), Path::new("/ws/target"));
+
+        // A redirected target directory under an unrelated `target` directory
+        let out_dir = Path::new("/mnt/target/cache/debug/build/host-1a2b/out");
+        assert_eq!(target_root(out_dir, None), Path::new("/mnt/target"));
+        assert_eq!(
+            target_root(out_dir, Some(Path::new("/mnt/target/cache"))),
+            Path::new("/mnt/target/cache")
+        );
+
+        // A relative `CARGO_TARGET_DIR`, with a target triple in the path
+        let out_dir = Path::new("/ws/build/out/riscv64/debug/build/host-1a2b/out");
+        assert_eq!(
+            target_root(out_dir, Some(Path::new("build/out"))),
+            Path::new("/ws/build/out")
+        );
+
+        // No `target` ancestor, e.g. with `target-dir` set in `.cargo/config.toml`
+        let out_dir = Path::new("/ramdisk/debug/build/host-1a2b/out");
+        assert_eq!(
+            target_root(out_dir, Some(Path::new("/ramdisk"))),
+            Path::new("/ramdisk")
+        );
+        assert_eq!(
+            target_root(out_dir, Some(Path::new("/elsewhere"))),
+            Path::new("/elsewhere")
+        );
+        assert_eq!(target_root(out_dir, None), out_dir);
+    }
+
+    #[test]
+    fn locked_and_offline_flags() {
+        let args = command_args(&test_builder());
+        assert!(!args.contains(&"--locked".to_string()));
+        if env::var_os(NET_OFFLINE_ENV).is_none() {
+            assert!(!args.contains(&"--offline".to_string()));
+        }
+
+        let args = command_args(&test_builder().with_locked().with_offline());
+        assert!(args.contains(&"--locked".to_string()));
+        assert!(args.contains(&"--offline".to_string()));
+        assert!(!args.contains(&"--frozen".to_string()));
+
+        let args = command_args(&test_builder().with_locked().with_frozen());
+        assert!(args.contains(&"--frozen".to_string()));
+        assert!(!args.contains(&"--locked".to_string()));
+        assert!(!args.contains(&"--offline".to_string()));
+    }
+
+    #[test]
+    fn env_is_forwarded_to_the_contract_build() {
+        let builder = test_builder()
+            .with_env("MY_CONTRACT_VERSION", "1.2.3")
+            .with_env("RUSTFLAGS", "--cfg overridden")
+            .with_env_passthrough(["CARGO_MANIFEST_DIR", "PVM_UNSET_FOR_TEST"]);
+        assert_eq!(
+            command_env(&builder, "MY_CONTRACT_VERSION").as_deref(),
+            Some("1.2.3")
+        );
+        assert_eq!(
+            command_env(&builder, "CARGO_MANIFEST_DIR").as_deref(),
+            Some(env!("CARGO_MANIFEST_DIR"))
+        );
+        assert_eq!(command_env(&builder, "PVM_UNSET_FOR_TEST"), None);
+        // Managed variables win over user-provided ones
+        assert!(
+            command_env(&builder, "RUSTFLAGS")
+                .unwrap()
+                .contains("-Cpanic=immediate-abort")
+        );
+
+        assert_eq!(command_env(&test_builder(), "PATH"), None);
+        let builder = test_builder()
+            .with_clean_env(true)
+            .with_toolchain("nightly");
+        assert_eq!(command_env(&builder, "PATH"), env::var("PATH").ok());
+        assert_eq!(
+            command_env(&builder, "RUSTUP_TOOLCHAIN").as_deref(),
+            Some("nightly")
+        );
+    }
+
+    #[test]
+    fn build_std_is_configurable() {
+        let args = command_args(&test_builder());
+        assert!(args.contains(&"-Zbuild-std=core,alloc".to_string()));
+        assert!(
+            !args
+                .iter()
+                .any(|arg| arg.starts_with("-Zbuild-std-features"))
+        );
+
+        let builder = test_builder()
+            .with_build_std(["core"])
+            .with_build_std_features(["optimize_for_size"]);
+        let args = command_args(&builder);
+        assert!(args.contains(&"-Zbuild-std=core".to_string()));
+        assert!(args.contains(&"-Zbuild-std-features=optimize_for_size".to_string()));
+
+        let err =
+            try_command_args(&test_builder().with_build_std(Vec::<String>::new())).unwrap_err();
+        assert!(matches!(err, PvmBuildError::InvalidConfig { .. }));
+    }
+
+    #[test]
+    fn cargo_args_come_last() {
+        let builder = test_builder()
+            .with_locked()
+            .with_cargo_args(["--config", "profile.release.lto='fat'"])
+            .with_cargo_arg("-Ztrim-paths");
+        let args = command_args(&builder);
+        assert_eq!(
+            args[args.len() - 3..],
+            ["--config", "profile.release.lto='fat'", "-Ztrim-paths"]
+        );
+
+        for arg in [
+            "--target-dir",
+            "--manifest-path=other/Cargo.toml",
+            "--release",
+        ] {
+            let err = try_command_args(&test_builder().with_cargo_args([arg])).unwrap_err();
+            assert!(matches!(err, PvmBuildError::InvalidConfig { .. }));
+            assert!(err.to_string().contains(arg), "{err}");
+        }
+    }
+
+    #[test]
+    fn cargo_timings_are_requested() {
+        assert!(!command_args(&test_builder()).contains(&"--timings".to_string()));
+        let args = command_args(&test_builder().with_cargo_timings(true));
+        assert!(args.contains(&"--timings".to_string()));
+    }
+
+    #[test]
+    fn json_messages_are_parsed() {
+        let args = command_args(&test_builder());
+        assert!(
+            args.windows(2)
+                .any(|pair| pair == ["--message-format", "json"])
+        );
+
+        let message: CargoMessage = serde_json::from_str(
+            r#"{"reason":"compiler-message","package_id":"contract","message":{"level":"error","message":"mismatched types","rendered":"error[E0308]: mismatched types\n","spans":[{"file_name":"src/main.rs"}],"children":[]}}"#,
+        )
+        .unwrap();
+        let CargoMessage::CompilerMessage { message, .. } = message else {
+            panic!("not a compiler message: {message:?}");
+        };
+        assert_eq!(message.level, "error");
+        assert_eq!(
+            message.rendered.as_deref(),
+            Some("error[E0308]: mismatched types\n")
+        );
+        assert_eq!(message.spans.len(), 1);
+
+        let artifact: CargoMessage = serde_json::from_str(
+            r#"{"reason":"compiler-artifact","package_id":"contract","executable":"/target/contract","fresh":false}"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            artifact,
+            CargoMessage::CompilerArtifact { executable: Some(path) } if path == Path::new("/target/contract")
+        ));
+
+        let finished: CargoMessage =
+            serde_json::from_str(r#"{"reason":"build-finished","success":true}"#).unwrap();
+        assert!(matches!(finished, CargoMessage::Other));
+    }
+
+    #[test]
+    fn check_mode_runs_cargo_check() {
+        assert_eq!(command_args(&test_builder())[0], "build");
+
+        let mut builder = test_builder();
+        builder.check_only = true;
+        let args = command_args(&builder);
+        assert_eq!(args[0], "check");
+        assert!(args.iter().any(|arg| arg.starts_with("-Zbuild-std=")));
+    }
+
+    #[test]
+    fn check_only_wrappers_are_detected() {
+        assert!(is_check_only_wrapper(Path::new(
+            "/home/dev/.vscode/extensions/rust-analyzer/server/rust-analyzer"
+        )));
+        assert!(is_check_only_wrapper(Path::new(
+            "/home/dev/.rustup/toolchains/nightly/bin/clippy-driver"
+        )));
+        assert!(!is_check_only_wrapper(Path::new("/usr/bin/sccache")));
+    }
+
+    #[test]
+    fn toolchain_is_selected_through_rustup() {
+        assert_eq!(command_env(&test_builder(), "RUSTUP_TOOLCHAIN"), None);
+        let builder = test_builder().with_toolchain("nightly-2025-01-01");
+        assert_eq!(
+            command_env(&builder, "RUSTUP_TOOLCHAIN").as_deref(),
+            Some("nightly-2025-01-01")
+        );
+        assert_eq!(cargo_command(Some("nightly")).get_program(), "cargo");
+    }
+
+    #[test]
+    fn rustc_versions_are_parsed() {
+        assert_eq!(
+            parse_rustc_version("rustc 1.91.1 (ed61e7d7e 2025-11-07)"),
+            Some((1, 91))
+        );
+        assert_eq!(
+            parse_rustc_version("rustc 1.92.0-nightly (caccb4d03 2025-09-24)"),
+            Some((1, 92))
+        );
+        assert_eq!(
+            parse_rustc_version("rustc 1.93.0-beta.2 (0bc5b4b7d 2025-12-01)"),
+            Some((1, 93))
+        );
+        assert_eq!(parse_rustc_version("rustc 1.100.0"), Some((1, 100)));
+        assert_eq!(parse_rustc_version("cargo 1.92.0"), None);
+
+        let err =
+            check_immediate_abort_support(Some("rustc 1.91.1 (ed61e7d7e 2025-11-07)")).unwrap_err();
+        assert!(matches!(err, PvmBuildError::UnsupportedToolchain { .. }));
+        assert!(check_immediate_abort_support(Some("rustc 1.92.0-nightly (a 2025-09-24)")).is_ok());
+        assert!(check_immediate_abort_support(Some("rustc 2.0.0")).is_ok());
+        assert!(check_immediate_abort_support(Some("unknown")).is_ok());
+        assert!(check_immediate_abort_support(None).is_ok());
+    }
+
+    #[test]
+    fn rust_src_is_probed_in_the_sysroot() {
+        let toolchains = tempfile::tempdir().unwrap();
+        let sysroot = toolchains.path().join("nightly-x86_64-unknown-linux-gnu");
+        fs::create_dir_all(sysroot.join("lib/rustlib")).unwrap();
+
+        let err = check_rust_src(&sysroot, None).unwrap_err();
+        assert!(matches!(err, PvmBuildError::RustSrcMissing { .. }));
+        assert_eq!(
+            err.to_string(),
+            "rust-src component is required; run `rustup component add rust-src --toolchain nightly-x86_64-unknown-linux-gnu`"
+        );
+        let err = check_rust_src(&sysroot, Some("nightly-2025-01-01")).unwrap_err();
+        assert!(err.to_string().ends_with("--toolchain nightly-2025-01-01`"));
+
+        let core = sysroot.join("lib/rustlib/src/rust/library/core");
+        fs::create_dir_all(&core).unwrap();
+        fs::write(core.join("Cargo.toml"), "").unwrap();
+        check_rust_src(&sysroot, None).unwrap();
+    }
+
+    #[test]
+    fn target_dir_override() {
+        let builder = test_builder().with_target_dir("/ramdisk/pvm");
+        assert_eq!(builder.target_dir(), Path::new("/ramdisk/pvm"));
+
+        let builder = test_builder().with_target_dir("build");
+        assert_eq!(builder.target_dir(), Path::new("/contract/build"));
+
+        let args = command_args(&builder);
+        let idx = args.iter().position(|arg| arg == "--target-dir").unwrap();
+        assert_eq!(args[idx + 1], "/target/pvmbuild");
+    }
+
+    fn command_env(builder: &PvmBuilder, key: &str) -> Option<String> {
+        let cmd = build_elf_command(
+            builder,
+            &test_package(ContractTarget::Bin("contract".to_string())),
+            &Profile::Release,
+            Path::new("/target.json"),
+        )
+        .unwrap();
+        cmd.get_envs()
+            .find(|(k, _)| *k == key)
+            .and_then(|(_, v)| v)
+            .map(|v| v.to_string_lossy().into_owned())
+    }
+
+    #[test]
+    fn extra_rustflags_are_appended() {
+        let extra = ["-C opt-level=z".to_string(), "--cfg my_flag".to_string()];
+        assert_eq!(
+            nested_rustflags(None, &extra, true),
+            "-C opt-level=z --cfg my_flag -Zunstable-options -Cpanic=immediate-abort"
+        );
+        assert_eq!(
+            nested_rustflags(Some("--cfg inherited"), &extra, true),
+            "--cfg inherited -C opt-level=z --cfg my_flag -Zunstable-options -Cpanic=immediate-abort"
+        );
+        assert_eq!(
+            nested_rustflags(Some(" "), &[], true),
+            "-Zunstable-options -Cpanic=immediate-abort"
+        );
+        assert_eq!(
+            nested_rustflags(None, &extra, false),
+            "-C opt-level=z --cfg my_flag"
+        );
+    }
+
+    #[test]
+    fn binary_env_var_names() {
+        assert_eq!(binary_env_var("mytoken"), "PVM_BINARY_MYTOKEN");
+        assert_eq!(binary_env_var("my-token.v2"), "PVM_BINARY_MY_TOKEN_V2");
+
+        let bins = ["my-token".to_string(), "my_token".to_string()];
+        let err = binary_env_vars(&bins).unwrap_err().to_string();
+        assert!(err.contains("`my-token` and `my_token`"));
+        assert!(err.contains("PVM_BINARY_MY_TOKEN"));
+    }
+
+    #[test]
+    fn generated_module_embeds_every_binary() {
+        let artifact = |name: &str| Artifact {
+            name: name.to_string(),
+            path: PathBuf::from(format!("/target/{name}.release.polkavm")),
+            elf_path: PathBuf::from(format!("/target/pvmbuild/{name}")),
+            kept_elf_path: None,
+            hex_path: None,
+            gzip_path: None,
+            bundle_path: None,
+            code_hash_path: None,
+            size: 3,
+            elf_size: 3,
+            link_ms: 0,
+            code_hash: String::new(),
+            exports: Vec::new(),
+            imports: Vec::new(),
+        };
+        let (token, vault) = (artifact("my-token"), artifact("2vault"));
+
+        let module = generated_module(&[(&token, b"abc"), (&vault, b"")]).unwrap();
+
+        assert!(module.contains(
+            r#"pub const MY_TOKEN: &[u8] = include_bytes!("/target/my-token.release.polkavm");"#
+        ));
+        // keccak256("abc")
+        assert!(module.contains(
+            "pub const MY_TOKEN_CODE_HASH: [u8; 32] = [0x4e, 0x03, 0x65, 0x7a, 0xea, 0x45"
+        ));
+        assert!(module.contains("pub const _2VAULT: &[u8]"));
+        // keccak256("")
+        assert!(module.contains("pub const _2VAULT_CODE_HASH: [u8; 32] = [0xc5, 0xd2, 0x46, 0x01"));
+    }
+
+    #[test]
+    fn code_hash_is_written_next_to_the_binary() {
+        assert_eq!(
+            hex_string(&keccak256(b"abc")),
+            "0x4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+        assert_eq!(
+            code_hash_path(
+                Path::new("/target/contract.release.polkavm"),
+                CodeHash::Keccak256
+            ),
+            Path::new("/target/contract.release.polkavm.keccak")
+        );
+        // sha256("abc")
+        assert_eq!(
+            hex_string(&CodeHash::Sha256.digest(b"abc")),
+            "0xba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(
+            code_hash_path(
+                Path::new("/target/contract.release.polkavm"),
+                CodeHash::Sha256
+            ),
+            Path::new("/target/contract.release.polkavm.sha256")
+        );
+        assert_eq!(test_builder().code_hash_file, None);
+    }
+
+    #[test]
+    fn hex_only_output_keeps_the_code_hash_with_the_hex_file() {
+        let output_dir = Path::new("/artifacts");
+        let builder = test_builder()
+            .with_target_dir("/target/pvmbuild")
+            .with_code_hash_file(CodeHash::Keccak256);
+        assert_eq!(blob_dir(&builder, output_dir), output_dir);
+
+        let builder = builder.with_output_format(OutputFormat::Hex);
+        assert_eq!(
+            blob_dir(&builder, output_dir),
+            Path::new("/target/pvmbuild/polkavm")
+        );
+        // Named after the blob, but written to the output directory with the hex file
+        assert_eq!(
+            code_hash_path(
+                &output_dir.join("contract.release.polkavm"),
+                CodeHash::Keccak256
+            ),
+            Path::new("/artifacts/contract.release.polkavm.keccak")
+        );
+    }
+
+    #[test]
+    fn abi_is_taken_from_the_contract_named_after_the_binary() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("MyToken.sol"), "").unwrap();
+        fs::write(dir.path().join("Other.sol"), "").unwrap();
+        assert_eq!(
+            find_sol_file(dir.path(), "my-token").unwrap(),
+            Some(dir.path().join("MyToken.sol"))
+        );
+        assert_eq!(find_sol_file(dir.path(), "missing").unwrap(), None);
+
+        let output = serde_json::json!({
+            "contracts": {
+                "MyToken.sol": {
+                    "IERC20": { "abi": [{ "type": "function", "name": "transfer" }] },
+                    "MyToken": { "abi": [{ "type": "function", "name": "mint" }] }
+                }
+            }
+        });
+        let abi = contract_abi(&output, "MyToken.sol", "my_token").unwrap();
+        assert_eq!(abi[0]["name"], "mint");
+
+        let err = contract_abi(&output, "MyToken.sol", "other").unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("available contracts: IERC20, MyToken")
+        );
+
+        let single = serde_json::json!({
+            "contracts": { "Token.sol": { "Token": { "abi": [] } } }
+        });
+        assert_eq!(
+            contract_abi(&single, "Token.sol", "contract").unwrap(),
+            serde_json::json!([])
+        );
+    }
+
+    #[test]
+    fn bundle_abi_is_read_from_arrays_and_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        let abi = serde_json::json!([{ "type": "function", "name": "transfer" }]);
+        let bare = dir.path().join("abi.json");
+        fs::write(&bare, abi.to_string()).unwrap();
+        assert_eq!(bundle::read_abi(&bare).unwrap(), abi);
+
+        let artifact = dir.path().join("MyToken.json");
+        fs::write(
+            &artifact,
+            serde_json::json!({ "abi": abi, "bytecode": { "object": "0x" } }).to_string(),
+        )
+        .unwrap();
+        assert_eq!(bundle::read_abi(&artifact).unwrap(), abi);
+
+        let other = dir.path().join("other.json");
+        fs::write(&other, r#"{ "contracts": {} }"#).unwrap();
+        let err = bundle::read_abi(&other).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("is neither an ABI array nor an artifact with an `abi` field")
+        );
+
+        let bundle = bundle::ContractBundle {
+            name: "mytoken".to_string(),
+            bytecode: "0x50564d00".to_string(),
+            code_hash: hex_string(&keccak256(b"PVM\0")),
+            abi,
+            build_info: bundle::BuildInfo {
+                rustc_version: None,
+                polkavm_linker_version: POLKAVM_LINKER_VERSION.to_string(),
+                profile: "release".to_string(),
+                instruction_set: "revive_v1".to_string(),
+                debug_blob: false,
+            },
+        };
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert_eq!(
+            serde_json::from_str::<bundle::ContractBundle>(&json).unwrap(),
+            bundle
+        );
+    }
+
+    #[test]
+    fn size_limits_are_enforced() {
+        check_size("token", 1000, Some(1000), None).unwrap();
+        check_size("token", 1001, None, Some(1000)).unwrap();
+
+        let err = check_size("token", 1001, Some(1000), Some(500)).unwrap_err();
+        assert!(matches!(
+            err,
+            PvmBuildError::SizeLimitExceeded {
+                size: 1001,
+                limit: 1000,
+                ..
+            }
+        ));
+        assert_eq!(
+            err.to_string(),
+            "PolkaVM binary `token` is 1001 bytes, which exceeds the limit of 1000 bytes"
+        );
+    }
+
+    #[test]
+    fn link_errors_are_collected_in_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let elf_paths: Vec<_> = ["a", "b", "c"]
+            .iter()
+            .map(|name| dir.path().join(name))
+            .collect();
+        let links: Vec<_> = elf_paths
+            .iter()
+            .map(|elf| (elf.as_path(), elf.with_extension("polkavm")))
+            .collect();
+        let instruction_set = TargetInstructionSet::ReviveV1;
+
+        let err = link_all(
+            &links,
+            dir.path(),
+            instruction_set,
+            &LinkerOptions::default(),
+        )
+        .unwrap_err();
+        let PvmBuildError::Multiple { errors } = &err else {
+            panic!("expected every link to fail, got {err}");
+        };
+        let messages: Vec<_> = errors.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            messages,
+            elf_paths
+                .iter()
+                .map(|elf| format!("Failed to read ELF from {}", elf.display()))
+                .collect::<Vec<_>>()
+        );
+
+        fs::write(&elf_paths[1], b"not an ELF").unwrap();
+        let err = link_all(
+            &links[1..2],
+            dir.path(),
+            instruction_set,
+            &LinkerOptions::default(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, PvmBuildError::LinkerFailed { elf_path, .. } if elf_path == elf_paths[1])
+        );
+    }
+
+    #[test]
+    fn size_summary_compares_to_the_elf() {
+        assert_eq!(
+            size_summary(12 * 1024, 48 * 1024, Some((10 * 1024, 2 * 1024))),
+            "12.0 KB from 48.0 KB ELF, 75% reduction; code 10.0 KB, data 2.0 KB"
+        );
+        assert_eq!(size_summary(600, 0, None), "600 bytes from 0 bytes ELF");
+        assert_eq!(size_summary(2048, 1024, None), "2.0 KB from 1.0 KB ELF");
+    }
+
+    #[test]
+    fn timings_are_in_seconds() {
+        assert_eq!(seconds(8_321), "8.3s");
+        assert_eq!(seconds(40), "0.0s");
+        assert_eq!(seconds(0), "0.0s");
+    }
+
+    #[test]
+    fn unchanged_elf_is_not_relinked() {
+        let dir = tempfile::tempdir().unwrap();
+        let elf_path = dir.path().join("contract");
+        let output_path = dir.path().join("contract.release.polkavm");
+        let instruction_set = TargetInstructionSet::ReviveV1;
+        let options = LinkerOptions::default();
+        // Not a valid ELF, so any attempt to link it fails
+        fs::write(&elf_path, b"not an ELF").unwrap();
+        fs::write(&output_path, b"blob").unwrap();
+
+        let hash = link_input_hash(b"not an ELF", instruction_set, &options);
+        let hash_dir = dir.path().join("fingerprints");
+        fs::create_dir(&hash_dir).unwrap();
+        fs::write(link_hash_path(&hash_dir, &output_path), format!("{hash}\n")).unwrap();
+        assert_eq!(
+            link_to_polkavm(
+                &elf_path,
+                &output_path,
+                &hash_dir,
+                instruction_set,
+                &options
+            )
+            .unwrap(),
+            4
+        );
+
+        let unstripped = LinkerOptions {
+            strip: false,
+            ..LinkerOptions::default()
+        };
+        assert_ne!(
+            link_input_hash(b"not an ELF", instruction_set, &unstripped),
+            hash
+        );
+        let err = link_to_polkavm(
+            &elf_path,
+            &output_path,
+            &hash_dir,
+            instruction_set,
+            &unstripped,
+        )
+        .unwrap_err();
+        assert!(matches!(err, PvmBuildError::LinkerFailed { .. }));
+        assert!(!link_hash_path(&hash_dir, &output_path).exists());
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 3);
+    }
+
+    #[test]
+    fn verbose_env_values() {
+        assert!(is_truthy(Some("1")));
+        assert!(is_truthy(Some("true")));
+        assert!(!is_truthy(Some("0")));
+        assert!(!is_truthy(Some("")));
+        assert!(!is_truthy(None));
+    }
+
+    #[test]
+    fn nested_warnings_are_forwarded() {
+        assert_eq!(
+            forwarded_warning("warning: unused variable: `x`"),
+            Some("unused variable: `x`")
+        );
+        assert_eq!(
+            forwarded_warning("warning: `contract` (bin \"contract\") generated 2 warnings"),
+            None
+        );
+        assert_eq!(forwarded_warning("   Compiling contract v0.1.0"), None);
+        assert_eq!(forwarded_warning("error: could not compile"), None);
+
+        let rendered = "warning: unused variable: `x`\n --> src/main.rs:5:9\n  |\n5 |     let x = 1;\n  |         ^\n\n";
+        let mut forwarded = Vec::new();
+        assert_eq!(
+            forwarded_diagnostic(Some("token"), rendered, &mut forwarded).as_deref(),
+            Some(
+                "[pvm:token] unused variable: `x`\n --> src/main.rs:5:9\n  |\n5 |     let x = 1;\n  |         ^"
+            )
+        );
+        // Another binary compiling the same module reports the same warning
+        assert_eq!(
+            forwarded_diagnostic(Some("vault"), rendered, &mut forwarded),
+            None
+        );
+        assert_eq!(
+            forwarded_diagnostic(None, "warning: unused import\n", &mut forwarded).as_deref(),
+            Some("[pvm] unused import")
+        );
+
+        let message: CargoMessage = serde_json::from_str(
+            r#"{"reason":"compiler-message","package_id":"contract","target":{"kind":["bin"],"name":"token"},"message":{"level":"warning","message":"unused variable: `x`","rendered":null,"spans":[]}}"#,
+        )
+        .unwrap();
+        let CargoMessage::CompilerMessage { target, .. } = message else {
+            panic!("not a compiler message: {message:?}");
+        };
+        assert_eq!(target.unwrap().name, "token");
+    }
+
+    #[test]
+    fn hex_and_gzip_outputs_round_trip() {
+        let blob = polkavm_linker::assemble(
+            None,
+            "%isa = revive_v1\npub @deploy:\nret\npub @call:\nret\n",
+        )
+        .unwrap();
+
+        let hex = hex_string(&blob);
+        let digits = hex.strip_prefix("0x").unwrap();
+        let decoded: Vec<u8> = (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap())
+            .collect();
+        assert_eq!(decoded, blob);
+
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        let gz = gzip(&blob);
+        assert_eq!(gz[..3], [0x1f, 0x8b, 8]);
+        let (deflated, trailer) = gz[10..].split_at(gz.len() - 18);
+        assert_eq!(
+            miniz_oxide::inflate::decompress_to_vec(deflated).unwrap(),
+            blob
+        );
+        assert_eq!(trailer[..4], crc32(&blob).to_le_bytes());
+        assert_eq!(trailer[4..], (blob.len() as u32).to_le_bytes());
+        assert_eq!(gzip(&blob), gz);
+
+        assert!(OutputFormat::default().raw() && !OutputFormat::default().hex());
+        assert!(OutputFormat::Both.raw() && OutputFormat::Both.hex());
+        assert!(!OutputFormat::Hex.raw());
+    }
+
+    #[test]
+    fn build_output_serializes_to_json() {
+        let output = BuildOutput {
+            artifacts: vec![Artifact {
+                name: "contract".to_string(),
+                path: PathBuf::from("/target/contract.release.polkavm"),
+                elf_path: PathBuf::from("/target/pvmbuild/contract"),
+                kept_elf_path: Some(PathBuf::from("/target/contract.elf")),
+                hex_path: Some(PathBuf::from("/target/contract.release.polkavm.hex")),
+                gzip_path: None,
+                bundle_path: None,
+                code_hash_path: Some(PathBuf::from("/target/contract.release.polkavm.keccak")),
+                size: 1234,
+                elf_size: 5678,
+                link_ms: 12,
+                code_hash: "0x01".to_string(),
+                exports: vec!["deploy".to_string(), "call".to_string()],
+                imports: vec![Import {
+                    index: 0,
+                    name: "call_data_size".to_string(),
+                }],
+            }],
+            rustc_version: Some("rustc 1.92.0-nightly".to_string()),
+            polkavm_linker_version: POLKAVM_LINKER_VERSION.to_string(),
+            cargo_build_ms: 3456,
+            link_ms: 12,
+            total_ms: 3500,
+            compiler_artifacts: Vec::new(),
+            instruction_set: "revive_v1".to_string(),
+            debug_blob: false,
+        };
+
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "artifacts": [{
+                    "name": "contract",
+                    "path": "/target/contract.release.polkavm",
+                    "elf_path": "/target/pvmbuild/contract",
+                    "kept_elf_path": "/target/contract.elf",
+                    "hex_path": "/target/contract.release.polkavm.hex",
+                    "gzip_path": null,
+                    "bundle_path": null,
+                    "code_hash_path": "/target/contract.release.polkavm.keccak",
+                    "size": 1234,
+                    "elf_size": 5678,
+                    "link_ms": 12,
+                    "code_hash": "0x01",
+                    "exports": ["deploy", "call"],
+                    "imports": [{ "index": 0, "name": "call_data_size" }],
+                }],
+                "rustc_version": "rustc 1.92.0-nightly",
+                "polkavm_linker_version": POLKAVM_LINKER_VERSION,
+                "cargo_build_ms": 3456,
+                "link_ms": 12,
+                "total_ms": 3500,
+                "instruction_set": "revive_v1",
+                "debug_blob": false,
+            })
+        );
+
+        let parsed: BuildOutput = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.artifacts[0].size, 1234);
+    }
+
+    #[test]
+    fn polkavm_linker_version_matches_workspace() {
+        let manifest =
+            fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../Cargo.toml"))
+                .unwrap();
+        assert!(manifest.contains(&format!("polkavm-linker = \"{POLKAVM_LINKER_VERSION}\"")));
+    }
+
+    #[test]
+    fn target_bitness_selects_elf_dir() {
+        let target_64 = resolve_target_json(true).unwrap();
+        let target_32 = resolve_target_json(false).unwrap();
+        assert_ne!(target_64, target_32);
+
+        let profile = Profile::Release;
+        let target_dir = Path::new("/target/pvmbuild");
+        assert_eq!(
+            elf_dir(target_dir, &target_64, &profile),
+            target_dir.join("riscv64emac-unknown-none-polkavm/release")
+        );
+        assert_eq!(
+            elf_dir(target_dir, &target_32, &profile),
+            target_dir.join("riscv32emac-unknown-none-polkavm/release")
+        );
+    }
+
+    #[test]
+    fn copy_destination_for_dir_and_file() {
+        let dir = tempfile::tempdir().expect("temp dir");
+
+        assert_eq!(
+            copy_destination(dir.path(), "contract"),
+            dir.path().join("contract.polkavm")
+        );
+
+        let file = dir.path().join("my_contract.polkavm");
+        assert_eq!(copy_destination(&file, "contract"), file);
+    }
+
+    #[test]
+    fn fingerprint_tracks_sources_and_options() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        fs::create_dir(dir.path().join("src")).unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"contract\"\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("src/main.rs"), "fn main() {}\n").unwrap();
+
+        let package = ContractPackage {
+            name: None,
+            cargo_toml: dir.path().join("Cargo.toml"),
+            targets: vec![ContractTarget::Bin("contract".to_string())],
+            target_dir: dir.path().join("target/pvmbuild"),
+        };
+        let fingerprint = |builder: &PvmBuilder, rustc: &str| {
+            build_fingerprint(
+                builder,
+                std::slice::from_ref(&package),
+                &[dir.path().join("Cargo.lock")],
+                &Profile::Release,
+                Path::new("/target.json"),
+                Some(rustc),
+            )
+            .unwrap()
+        };
+        let builder = PvmBuilder::for_manifest(package.cargo_toml.clone());
+
+        let initial = fingerprint(&builder, "rustc 1.92.0");
+        assert_eq!(fingerprint(&builder, "rustc 1.92.0"), initial);
+        assert_ne!(fingerprint(&builder, "rustc 1.93.0"), initial);
+
+        let with_features =
+            PvmBuilder::for_manifest(package.cargo_toml.clone()).with_features(["a"]);
+        assert_ne!(fingerprint(&with_features, "rustc 1.92.0"), initial);
+
+        let with_isa = PvmBuilder::for_manifest(package.cargo_toml.clone())
+            .with_instruction_set(TargetInstructionSet::Latest);
+        assert_ne!(fingerprint(&with_isa, "rustc 1.92.0"), initial);
+
+        fs::write(dir.path().join("src/main.rs"), "fn main() { }\n").unwrap();
+        let edited = fingerprint(&builder, "rustc 1.92.0");
+        assert_ne!(edited, initial);
+
+        // Inputs outside the package, like the workspace lock file
+        fs::write(dir.path().join("Cargo.lock"), "version = 4\n").unwrap();
+        assert_ne!(fingerprint(&builder, "rustc 1.92.0"), edited);
+    }
+
+    #[test]
+    fn reproducible_builds_remap_host_paths() {
+        let rustflags = command_env(&test_builder(), "RUSTFLAGS").unwrap();
+        assert!(!rustflags.contains("--remap-path-prefix"));
+
+        let builder = test_builder().with_reproducible(true);
+        let rustflags = command_env(&builder, "RUSTFLAGS").unwrap();
+        assert!(rustflags.contains("--remap-path-prefix=/contract=/contract"));
+        assert!(rustflags.contains("--remap-path-prefix=/target/pvmbuild=/target"));
+        if env::var_os("SOURCE_DATE_EPOCH").is_none() {
+            assert_eq!(
+                command_env(&builder, "SOURCE_DATE_EPOCH").as_deref(),
+                Some("0")
+            );
+        }
+
+        // rustc applies the last matching remap, so nested paths must come later
+        let paths = host_paths(
+            Path::new("/home/dev/token"),
+            Path::new("/home/dev/token/target/pvmbuild"),
+            Some(PathBuf::from("/home/dev/.rustup/toolchains/nightly")),
+        );
+        let position = |to: &str| paths.iter().position(|(_, p)| *p == to).unwrap();
+        assert!(position("/rustc") < position("/contract"));
+        assert!(position("/contract") < position("/target"));
+
+        let flags = remap_path_prefix_flags(&[
+            (PathBuf::from("/a b"), "/x"),
+            (PathBuf::from("/src"), "/y"),
+        ]);
+        assert_eq!(flags, ["--remap-path-prefix=/src=/y"]);
+    }
+
+    #[test]
+    fn leaked_host_paths_are_detected() {
+        let paths = [
+            (PathBuf::from("/home/dev/token"), "/contract"),
+            (PathBuf::from("/opt/cargo"), "/cargo"),
+        ];
+        let binary = b"PVM\0panicked at /home/dev/token/src/main.rs";
+        assert_eq!(
+            leaked_host_paths(binary, &paths),
+            [Path::new("/home/dev/token")]
+        );
+        assert!(leaked_host_paths(b"PVM\0/contract/src/main.rs", &paths).is_empty());
+
+        let sysroot = PathBuf::from("/opt/rustup/toolchains/nightly-x86_64-unknown-linux-gnu");
+        let paths = host_paths(
+            Path::new("/home/dev/token"),
+            Path::new("/home/dev/token/target/pvmbuild"),
+            Some(sysroot.clone()),
+        );
+        let binary = b"PVM\0/opt/rustup/toolchains/nightly-x86_64-unknown-linux-gnu/lib/rustlib/src/rust/library/core/src/panicking.rs";
+        assert_eq!(leaked_host_paths(binary, &paths), [sysroot.as_path()]);
+    }
+
+    #[test]
+    fn heap_size_is_passed_to_contract_build() {
+        assert_eq!(command_env(&test_builder(), HEAP_SIZE_ENV), None);
+
+        let builder = test_builder().with_heap_size_env(64 * 1024);
+        assert_eq!(
+            command_env(&builder, HEAP_SIZE_ENV).as_deref(),
+            Some("65536")
+        );
+    }
+
+    #[test]
+    fn environment_is_read_through_watched_env() {
+        let source = include_str!("lib.rs");
+        let source = &source[..source.find("#[cfg(test)]\nmod tests").unwrap()];
+        // Cargo's own build script variables, and variables forwarded by name
+        let allowed = [
+            "\"CARGO_MANIFEST_DIR\"",
+            "\"OUT_DIR\"",
+            "\"CARGO\"",
+            "format!(\"CARGO_FEATURE_{}\"",
+            "key",
+        ];
+        for call in ["env::var(", "env::var_os("] {
+            for (index, _) in source.match_indices(call) {
+                let argument = &source[index + call.len()..];
+                assert!(
+                    allowed.iter().any(|arg| argument.starts_with(arg)),
+                    "`{call}{}` bypasses `env_var_os`",
+                    argument.lines().next().unwrap()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn min_stack_size_is_validated() {
+        let options = |size| LinkerOptions {
+            min_stack_size: Some(size),
+            ..LinkerOptions::default()
+        };
+
+        assert!(options(64 * 1024).to_config().is_ok());
+        assert!(options(MAX_MIN_STACK_SIZE).to_config().is_ok());
+
+        let err = options(0).to_config().err().unwrap();
+        assert!(err.to_string().contains("Invalid minimum stack size 0"));
+        assert!(options(u32::MAX).to_config().is_err());
+    }
+
+    #[test]
+    fn profile_cargo_args() {
+        assert_eq!(Profile::Debug.cargo_arg(), "dev");
+        assert_eq!(Profile::Debug.directory(), "debug");
+        assert_eq!(Profile::Release.cargo_arg(), "release");
+        assert_eq!(Profile::Release.directory(), "release");
+        assert_eq!(Profile::from_name("dev"), Profile::Debug);
+
+        let production = Profile::from_name("production");
+        assert_eq!(production.cargo_arg(), "production");
+        assert_eq!(production.directory(), "production");
+    }
+
+    #[test]
+    fn custom_profile_must_be_defined() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let member_dir = dir.path().join("member");
+        fs::create_dir(&member_dir).unwrap();
+        let manifest = member_dir.join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"member\"\n\n[profile.production]\ninherits = \"release\"\n",
+        )
+        .unwrap();
+
+        let production = Profile::Custom("production".to_string());
+        assert!(validate_profile(&production, &manifest).is_ok());
+        assert!(validate_profile(&Profile::Release, &manifest).is_ok());
+        assert!(validate_profile(&Profile::Custom("bench".to_string()), &manifest).is_ok());
+
+        let err = validate_profile(&Profile::Custom("tiny".to_string()), &manifest).unwrap_err();
+        assert!(matches!(err, PvmBuildError::Manifest { ref path, .. } if *path == manifest));
+        assert!(err.to_string().contains("Profile `tiny` is not defined"));
+        assert!(err.to_string().contains("defined profiles: production"));
+
+        // Workspace members inherit the profiles of the workspace root
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"member\"]\n\n[profile.tiny]\ninherits = \"release\"\n",
+        )
+        .unwrap();
+        assert!(validate_profile(&Profile::Custom("tiny".to_string()), &manifest).is_ok());
+    }
+
+    #[test]
+    fn profile_resolution() {
+        let detected = || Profile::Debug;
+        assert_eq!(resolve_profile(None, None, detected), Profile::Debug);
+        assert_eq!(
+            resolve_profile(Some(&Profile::Release), None, detected),
+            Profile::Release
+        );
+        assert_eq!(
+            resolve_profile(Some(&Profile::Release), Some("debug"), detected),
+            Profile::Debug
+        );
+        assert_eq!(
+            resolve_profile(Some(&Profile::Release), Some(" "), detected),
+            Profile::Release
+        );
+    }
+
+    #[test]
+    fn forwarded_features_follow_the_host() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest,
+            "[package]\nname = \"contract\"\n\n[features]\ntestnet = []\n\n\
+             [dependencies]\nserde = { version = \"1\", optional = true }\n",
+        )
+        .unwrap();
+        let forwarded = ["testnet".to_string(), "serde".to_string()];
+
+        assert_eq!(
+            forwarded_features(&forwarded, &manifest, |feature| feature == "testnet").unwrap(),
+            ["testnet"]
+        );
+        assert!(
+            forwarded_features(&forwarded, &manifest, |_| false)
+                .unwrap()
+                .is_empty()
+        );
+
+        let err = forwarded_features(&["mainnet".to_string()], &manifest, |_| true).unwrap_err();
+        assert!(matches!(err, PvmBuildError::Manifest { .. }));
+        assert!(err.to_string().contains(
+            "Forwarded feature `mainnet` is not a feature of the contract; declared features: testnet, serde"
+        ));
+    }
+
+    #[test]
+    fn features_are_joined_into_one_flag() {
+        let args = command_args(
+            &test_builder()
+                .with_features(["a"])
+                .with_features(["b", "c"]),
+        );
+        let idx = args.iter().position(|arg| arg == "--features").unwrap();
+        assert_eq!(args[idx + 1], "a,b,c");
+
+        assert!(!command_args(&test_builder()).contains(&"--features".to_string()));
+    }
+
+    #[test]
+    fn sol_macro_paths_are_extracted() {
+        let content = r#"
+            sol!("MyToken.sol");
+            sol!("interfaces/IErc20.sol");
+            sol! { interface Inline {} }
+        "#;
+        assert_eq!(
+            sol_macro_paths(content),
+            ["MyToken.sol", "interfaces/IErc20.sol"]
+        );
+    }
+
+    #[test]
+    fn rerun_paths_cover_sources_and_sol_files() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let root = dir.path();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"demo\"\n").unwrap();
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("src/demo.rs"), "sol!(\"Demo.sol\");\n").unwrap();
+        fs::write(root.join("src/nested/util.rs"), "").unwrap();
+        fs::write(root.join("src/notes.txt"), "").unwrap();
+
+        let paths = rerun_if_changed_paths(root).unwrap();
+
+        assert!(paths.contains(&root.join("Cargo.toml")));
+        assert!(!paths.contains(&root.join("Cargo.lock")));
+        assert!(paths.contains(&root.join("src")));
+        assert!(paths.contains(&root.join("src/demo.rs")));
+        assert!(paths.contains(&root.join("src/nested/util.rs")));
+        assert!(paths.contains(&root.join("src/notes.txt")));
+        assert!(paths.contains(&root.join("Demo.sol")));
+    }
+
+    #[test]
+    fn lock_file_is_taken_from_the_workspace_root() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let root = dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"contract\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("contract/src")).unwrap();
+        fs::write(
+            root.join("contract/Cargo.toml"),
+            "[package]\nname = \"contract\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("contract/src/lib.rs"), "").unwrap();
+        let member = root.join("contract/Cargo.toml");
+
+        assert_eq!(workspace_lock_file(&member).unwrap(), None);
+        fs::write(root.join("Cargo.lock"), "version = 4\n").unwrap();
+        let lock_file = workspace_lock_file(&member)
+            .unwrap()
+            .expect("lock file found");
+        assert_eq!(
+            fs::canonicalize(lock_file).unwrap(),
+            fs::canonicalize(root.join("Cargo.lock")).unwrap()
+        );
+    }
+
+    #[test]
+    fn cargo_target_dir_follows_the_workspace_and_cargo_config() {
+        // An inherited CARGO_TARGET_DIR overrides both layouts under test
+        if std::env::var_os("CARGO_TARGET_DIR").is_some() {
+            return;
+        }
+        let dir = tempfile::tempdir().expect("temp dir");
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"contract\"]\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("contract/src")).unwrap();
+        fs::write(
+            root.join("contract/Cargo.toml"),
+            "[package]\nname = \"contract\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("contract/src/lib.rs"), "").unwrap();
+        let builder = PvmBuilder::for_manifest(root.join("contract/Cargo.toml"));
+
+        assert_eq!(builder.cargo_target_dir().unwrap(), root.join("target"));
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(
+            root.join(".cargo/config.toml"),
+            "[build]\ntarget-dir = \"elsewhere\"\n",
+        )
+        .unwrap();
+        assert_eq!(builder.cargo_target_dir().unwrap(), root.join("elsewhere"));
+    }
+
+    #[test]
+    fn path_dependencies_are_followed_outside_the_package() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let package = |name: &str, dependencies: &str| {
+            fs::create_dir_all(root.join(name).join("src")).unwrap();
+            fs::write(
+                root.join(name).join("Cargo.toml"),
+                format!(
+                    "[package]\nname = \"{name}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n{dependencies}"
+                ),
+            )
+            .unwrap();
+            fs::write(root.join(name).join("src/lib.rs"), "").unwrap();
+        };
+        package(
+            "contract",
+            "[dependencies]\nshared = { path = \"../shared\" }\n\n[dev-dependencies]\ntesting = { path = \"../testing\" }\n",
+        );
+        package("shared", "[dependencies]\ndeep = { path = \"../deep\" }\n");
+        package("deep", "");
+        package("testing", "");
+
+        let contract = ContractPackage {
+            name: None,
+            cargo_toml: root.join("contract/Cargo.toml"),
+            targets: vec![ContractTarget::Lib("contract".to_string())],
+            target_dir: root.join("target/pvmbuild"),
+        };
+        let dirs = path_dependency_dirs(std::slice::from_ref(&contract)).unwrap();
+        let dirs: Vec<_> = dirs
+            .iter()
+            .map(|dir| fs::canonicalize(dir).unwrap())
+            .collect();
+        assert_eq!(dirs, [root.join("deep"), root.join("shared")]);
+    }
+
+    #[test]
+    fn debug_blob_keeps_symbols_and_panic_messages() {
+        let options = test_builder().effective_linker_options();
+        assert!(options.strip && options.optimize);
+        let rustflags = command_env(&test_builder(), "RUSTFLAGS").unwrap();
+        assert!(rustflags.contains("-Cpanic=immediate-abort"));
+
+        let builder = test_builder()
+            .with_min_stack_size(64 * 1024)
+            .with_debug_blob(true);
+        let options = builder.effective_linker_options();
+        assert!(!options.strip && !options.optimize);
+        assert_eq!(options.min_stack_size, Some(64 * 1024));
+        let rustflags = command_env(&builder, "RUSTFLAGS").unwrap_or_default();
+        assert!(!rustflags.contains("immediate-abort"), "{rustflags}");
+    }
+
+    #[test]
+    fn bits_select_the_target() {
+        assert!(test_builder().is_64_bit);
+        assert!(!test_builder().with_bits(Bits::B32).is_64_bit);
+        assert!(test_builder().with_32bit().with_bits(Bits::B64).is_64_bit);
+
+        let target_json = resolve_target_json(false).unwrap();
+        let name = target_json.file_stem().unwrap().to_str().unwrap();
+        assert!(name.starts_with("riscv32emac"), "{name}");
+        assert_eq!(
+            elf_dir(Path::new("/target"), &target_json, &Profile::Release),
+            Path::new("/target").join(name).join("release")
+        );
+    }
+
+    #[test]
+    fn instruction_set_defaults_to_revive() {
+        let builder = test_builder();
+        assert_eq!(
+            builder.target_instruction_set(),
+            TargetInstructionSet::ReviveV1
+        );
+        assert_eq!(
+            test_builder().with_32bit().target_instruction_set(),
+            TargetInstructionSet::Latest
+        );
+
+        let builder = test_builder().with_instruction_set(TargetInstructionSet::Latest);
+        assert_eq!(
+            builder.target_instruction_set(),
+            TargetInstructionSet::Latest
+        );
+        assert_eq!(
+            instruction_set_name(TargetInstructionSet::ReviveV1),
+            "revive_v1"
+        );
+        assert_eq!(instruction_set_name(TargetInstructionSet::Latest), "latest");
+    }
+
+    #[test]
+    fn symbols_list_exports_and_imports() {
+        let blob = polkavm_linker::assemble(
+            None,
+            "%isa = revive_v1\npub @deploy:\nret\npub @call:\nret\n",
+        )
+        .unwrap();
+        let symbols = symbols::symbols(Path::new("demo.polkavm"), blob).unwrap();
+
+        assert_eq!(symbols.exports, ["call", "deploy"]);
+        assert!(symbols.imports.is_empty());
+        assert_eq!(
+            serde_json::to_value(&symbols).unwrap(),
+            serde_json::json!({ "exports": ["call", "deploy"], "imports": [] })
+        );
+    }
+
+    #[test]
+    fn required_exports_are_checked() {
+        let blob = polkavm_linker::assemble(None, "%isa = revive_v1\npub @call:\nret\n").unwrap();
+        let symbols = symbols::symbols(Path::new("demo.polkavm"), blob).unwrap();
+        let builder = test_builder();
+
+        let err = check_exports("demo", &symbols.exports, &builder.required_exports).unwrap_err();
+        assert!(matches!(err, PvmBuildError::MissingExports { .. }));
+        assert_eq!(
+            err.to_string(),
+            "PolkaVM binary `demo` does not export `deploy`; exports found: `call`. \
+             Entry points need `#[polkavm_derive::polkavm_export]`"
+        );
+
+        check_exports("demo", &symbols.exports, &["call".to_string()]).unwrap();
+        let builder = builder.with_required_exports::<_, String>([]);
+        check_exports("demo", &[], &builder.required_exports).unwrap();
+        let err = check_exports("demo", &[], &["main".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("exports found: none"));
+    }
+
+    #[test]
+    fn imports_are_checked_against_the_allowlist() {
+        let imports = |names: &[&str]| -> Vec<Import> {
+            names
+                .iter()
+                .enumerate()
+                .map(|(index, name)| Import {
+                    index: index as u32,
+                    name: name.to_string(),
+                })
+                .collect()
+        };
+        let dir = tempfile::tempdir().unwrap();
+
+        let (allowed, origin) = ImportAllowlist::default().load(dir.path()).unwrap();
+        symbols::check_imports(
+            "demo",
+            &imports(&["call_data_size", "call_data_copy", "seal_return"]),
+            &allowed,
+            &origin,
+        )
+        .unwrap();
+        let err = symbols::check_imports(
+            "demo",
+            &imports(&["seal_return", "sr25519_verify"]),
+            &allowed,
+            &origin,
+        )
+        .unwrap_err();
+        assert!(matches!(err, PvmBuildError::DisallowedImports { .. }));
+        assert_eq!(
+            err.to_string(),
+            "PolkaVM binary `demo` imports host functions missing from the built-in pallet-revive allowlist: `sr25519_verify`"
+        );
+
+        fs::write(dir.path().join("imports.json"), r#"["sr25519_verify"]"#).unwrap();
+        let builder = test_builder().with_allowed_imports_file("imports.json");
+        let (allowed, origin) = builder.allowed_imports.load(dir.path()).unwrap();
+        symbols::check_imports("demo", &imports(&["sr25519_verify"]), &allowed, &origin).unwrap();
+        let err =
+            symbols::check_imports("demo", &imports(&["call"]), &allowed, &origin).unwrap_err();
+        assert!(err.to_string().contains("imports.json: `call`"));
+
+        let builder = test_builder().with_allowed_imports(["call"]);
+        let (allowed, origin) = builder.allowed_imports.load(dir.path()).unwrap();
+        assert_eq!(allowed, ["call"]);
+        assert_eq!(origin, "`with_allowed_imports`");
+    }
+
+    #[test]
+    fn disassembly_lists_exports_and_jump_targets() {
+        let blob = polkavm_linker::assemble(
+            None,
+            "%isa = revive_v1\npub @deploy:\nret\npub @call:\na0 = a1 + a2\nret\n",
+        )
+        .unwrap();
+        let listing = disassembly::disassemble(Path::new("demo.polkavm"), blob).unwrap();
+
+        assert!(listing.contains("3 instructions"), "{listing}");
+        assert!(listing.contains(";   deploy @0\n"), "{listing}");
+        assert!(listing.contains(";   call: 2 instructions\n"), "{listing}");
+        assert!(listing.contains("\ncall:\n@"), "{listing}");
+
+        let err =
+            disassembly::disassemble(Path::new("bad.polkavm"), b"not a blob".to_vec()).unwrap_err();
+        assert!(matches!(err, PvmBuildError::InvalidBlob { .. }));
+    }
+
+    const ABI_CONSTS_SOURCE: &str = "\
+// Function selectors
+const TRANSFER_SELECTOR: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb]; // transfer(address,uint256)
+const BALANCE_OF_SELECTOR: [u8; 4] = [0x70, 0xa0, 0x82, 0x31]; // balanceOf(address)
+
+// Event signatures
+const TRANSFER_EVENT_SIGNATURE: [u8; 32] = [
+    0xdd, 0xf2, 0x52, 0xad, 0x1b, 0xe2, 0xc8, 0x9b, 0x69, 0xc2, 0xb0, 0x68, 0xfc, 0x37, 0x8d, 0xaa,
+    0x95, 0x2b, 0xa7, 0xf1, 0x63, 0xc4, 0xa1, 0x16, 0x28, 0xf5, 0x5a, 0x4d, 0xf5, 0x23, 0xb3, 0xef,
+];
+";
+
+    fn abi_consts() -> AbiConsts {
+        let constant = |name: &str, kind, signature: &str| AbiConst {
+            name: name.to_string(),
+            kind,
+            signature: signature.to_string(),
+        };
+        AbiConsts {
+            sol_file: "MyToken.sol".into(),
+            contract: "MyToken".to_string(),
+            source: "src/my-token.rs".into(),
+            constants: vec![
+                constant(
+                    "TRANSFER_SELECTOR",
+                    AbiConstKind::Function,
+                    "transfer(address,uint256)",
+                ),
+                constant(
+                    "BALANCE_OF_SELECTOR",
+                    AbiConstKind::Function,
+                    "balanceOf(address)",
+                ),
+                constant(
+                    "TRANSFER_EVENT_SIGNATURE",
+                    AbiConstKind::Event,
+                    "Transfer(address,address,uint256)",
+                ),
+            ],
+        }
+    }
+
+    fn token_abi() -> serde_json::Value {
+        serde_json::json!([
+            {"type": "function", "name": "transfer", "inputs": [{"type": "address"}, {"type": "uint256"}]},
+            {"type": "function", "name": "balanceOf", "inputs": [{"type": "address"}]},
+            {"type": "event", "name": "Transfer", "inputs": [{"type": "address"}, {"type": "address"}, {"type": "uint256"}]}
+        ])
+    }
+
+    #[test]
+    fn generated_abi_constants_match() {
+        assert_eq!(
+            abi_consts().check(ABI_CONSTS_SOURCE, &token_abi()),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn edited_abi_constant_is_reported_with_both_values() {
+        let source =
+            ABI_CONSTS_SOURCE.replace("[0xa9, 0x05, 0x9c, 0xbb]", "[0xa9, 0x05, 0x9c, 0xbc]");
+        assert_eq!(
+            abi_consts().check(&source, &token_abi()),
+            [
+                "`TRANSFER_SELECTOR`: 0xa9059cbc, expected 0xa9059cbb for `transfer(address,uint256)`"
+            ]
+        );
+    }
+
+    #[test]
+    fn changed_and_removed_abi_signatures_are_reported() {
+        let abi = serde_json::json!([
+            {"type": "function", "name": "transfer", "inputs": [{"type": "address"}, {"type": "uint128"}]},
+            {"type": "event", "name": "Transfer", "inputs": [{"type": "address"}, {"type": "address"}, {"type": "uint256"}]}
+        ]);
+        assert_eq!(
+            abi_consts().check(ABI_CONSTS_SOURCE, &abi),
+            [
+                format!(
+                    "`TRANSFER_SELECTOR`: 0xa9059cbb, expected {} for `transfer(address,uint128)` (generated for `transfer(address,uint256)`)",
+                    hex_string(&AbiConstKind::Function.value("transfer(address,uint128)"))
+                ),
+                "`BALANCE_OF_SELECTOR`: `balanceOf(address)` is no longer in the ABI".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn undeclared_abi_constant_is_reported() {
+        let source = ABI_CONSTS_SOURCE.replace("BALANCE_OF_SELECTOR", "BALANCE_SELECTOR");
+        assert_eq!(
+            abi_consts().check(&source, &token_abi()),
+            ["`BALANCE_OF_SELECTOR` is not declared in src/my-token.rs"]
+        );
+    }
+
+    #[test]
+    fn abi_constants_expand_tuple_parameters() {
+        let signature = "place((address,(uint8))[],bytes)";
+        let consts = AbiConsts {
+            constants: vec![AbiConst {
+                name: "PLACE_SELECTOR".to_string(),
+                kind: AbiConstKind::Function,
+                signature: signature.to_string(),
+            }],
+            ..abi_consts()
+        };
+        let selector = AbiConstKind::Function.value(signature);
+        let source = format!(
+            "const PLACE_SELECTOR: [u8; 4] = [{}];",
+            selector
+                .iter()
+                .map(|byte| format!("0x{byte:02x}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let abi = serde_json::json!([{
+            "type": "function",
+            "name": "place",
+            "inputs": [
+                {"type": "tuple[]", "components": [{"type": "address"}, {"type": "tuple", "components": [{"type": "uint8"}]}]},
+                {"type": "bytes"}
+            ]
+        }]);
+        assert_eq!(consts.check(&source, &abi), Vec::<String>::new());
+    }
 }
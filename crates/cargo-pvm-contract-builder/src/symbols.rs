@@ -0,0 +1,102 @@
+//! Exports and imports of linked `.polkavm` blobs.
+
+use crate::{ErrorContext, Import, PvmBuildError, Result};
+use polkavm_linker::ProgramBlob;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Host functions pallet-revive provides, as imported by `pallet-revive-uapi` 0.10.
+const PALLET_REVIVE_IMPORTS: &str = include_str!("pallet_revive_imports.json");
+
+/// Contents of the `<bin>.exports.json` file [`PvmBuilder::with_exports_listing`](crate::PvmBuilder::with_exports_listing) writes.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Symbols {
+    pub exports: Vec<String>,
+    pub imports: Vec<Import>,
+}
+
+/// Read the exported entry points and imported host functions of `bytes`, the
+/// `.polkavm` blob read from `path`.
+pub(crate) fn symbols(path: &Path, bytes: Vec<u8>) -> Result<Symbols> {
+    let blob = ProgramBlob::parse(bytes.into()).map_err(|e| PvmBuildError::InvalidBlob {
+        path: path.to_path_buf(),
+        message: e.to_string(),
+    })?;
+
+    let exports = blob
+        .exports()
+        .map(|export| String::from_utf8_lossy(export.symbol().as_bytes()).into_owned())
+        .collect();
+    let imports = blob
+        .imports()
+        .iter()
+        .enumerate()
+        .map(|(index, symbol)| Import {
+            index: index as u32,
+            name: symbol
+                .map(|symbol| String::from_utf8_lossy(symbol.as_bytes()).into_owned())
+                .unwrap_or_default(),
+        })
+        .collect();
+
+    Ok(Symbols { exports, imports })
+}
+
+/// Where the host functions a binary may import come from.
+#[derive(Clone, Debug, Default)]
+pub(crate) enum ImportAllowlist {
+    /// The host functions of the current pallet-revive.
+    #[default]
+    PalletRevive,
+    /// Names given to `with_allowed_imports`.
+    Names(Vec<String>),
+    /// A JSON array of names in a file.
+    File(PathBuf),
+}
+
+impl ImportAllowlist {
+    /// The allowed names, and a description of where they came from for error messages.
+    pub(crate) fn load(&self, manifest_dir: &Path) -> Result<(Vec<String>, String)> {
+        match self {
+            Self::PalletRevive => Ok((
+                serde_json::from_str(PALLET_REVIVE_IMPORTS)
+                    .context_with(|| "Failed to parse the built-in import allowlist".to_string())?,
+                "the built-in pallet-revive allowlist".to_string(),
+            )),
+            Self::Names(names) => Ok((names.clone(), "`with_allowed_imports`".to_string())),
+            Self::File(path) => {
+                let path = manifest_dir.join(path);
+                let content = fs::read_to_string(&path)
+                    .context_with(|| format!("Failed to read {}", path.display()))?;
+                let names = serde_json::from_str(&content)
+                    .context_with(|| format!("Failed to parse {}", path.display()))?;
+                Ok((names, path.display().to_string()))
+            }
+        }
+    }
+}
+
+/// Fail if `bin` imports host functions that aren't in `allowed`, which came from `origin`.
+pub(crate) fn check_imports(
+    bin: &str,
+    imports: &[Import],
+    allowed: &[String],
+    origin: &str,
+) -> Result<()> {
+    let disallowed: Vec<_> = imports
+        .iter()
+        .filter(|import| !allowed.contains(&import.name))
+        .map(|import| import.name.clone())
+        .collect();
+    if disallowed.is_empty() {
+        return Ok(());
+    }
+    Err(PvmBuildError::DisallowedImports {
+        bin: bin.to_string(),
+        imports: disallowed,
+        allowlist: origin.to_string(),
+    })
+}
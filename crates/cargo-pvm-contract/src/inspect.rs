@@ -0,0 +1,102 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Number of leading bytes shown in the hex dump.
+const HEX_DUMP_LEN: usize = 64;
+
+/// Metadata extracted from a `.polkavm` file.
+#[derive(Debug, Serialize)]
+pub struct BlobInfo {
+    pub size: usize,
+    pub exports: Vec<String>,
+    pub instruction_count: usize,
+    /// Hex encoding of the first bytes of the file.
+    pub head: String,
+}
+
+pub fn read_blob_info(path: &Path) -> Result<BlobInfo> {
+    let bytes =
+        std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    blob_info(bytes).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+pub fn blob_info(bytes: Vec<u8>) -> Result<BlobInfo> {
+    let size = bytes.len();
+    let head = hex(&bytes[..size.min(HEX_DUMP_LEN)]);
+
+    let blob = polkavm_linker::ProgramBlob::parse(bytes.into())
+        .map_err(|e| anyhow::anyhow!("Invalid PolkaVM blob: {e}"))?;
+
+    let exports = blob
+        .exports()
+        .map(|export| String::from_utf8_lossy(export.symbol().as_bytes()).into_owned())
+        .collect();
+    // The iterator keeps yielding traps past the end of the code
+    let code_len = blob.code().len();
+    let instruction_count = blob
+        .instructions()
+        .take_while(|inst| (inst.offset.0 as usize) < code_len)
+        .count();
+
+    Ok(BlobInfo {
+        size,
+        exports,
+        instruction_count,
+        head,
+    })
+}
+
+pub fn render_text(path: &Path, info: &BlobInfo) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "File: {}", path.display());
+    let _ = writeln!(out, "Size: {} bytes", info.size);
+    let _ = writeln!(out, "Exports: {}", info.exports.join(", "));
+    let _ = writeln!(out, "Instructions: {}", info.instruction_count);
+    let _ = writeln!(out, "First {} bytes:", info.head.len() / 2);
+
+    let bytes: Vec<&str> = (0..info.head.len())
+        .step_by(2)
+        .map(|i| &info.head[i..i + 2])
+        .collect();
+    for (line, chunk) in bytes.chunks(16).enumerate() {
+        let _ = writeln!(out, "  {:08x}  {}", line * 16, chunk.join(" "));
+    }
+
+    out
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_blob() -> Vec<u8> {
+        polkavm_linker::assemble(
+            None,
+            "%isa = revive_v1\npub @deploy:\nret\npub @call:\na0 = a1 + a2\nret\n",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn blob_info_lists_exports_and_instructions() {
+        let blob = test_blob();
+        let size = blob.len();
+        let info = blob_info(blob).unwrap();
+
+        assert_eq!(info.size, size);
+        assert_eq!(info.exports, ["call", "deploy"]);
+        assert_eq!(info.instruction_count, 3);
+        assert!(info.head.starts_with("50564d00"));
+    }
+
+    #[test]
+    fn invalid_blob_is_rejected() {
+        assert!(blob_info(b"not a blob".to_vec()).is_err());
+    }
+}
@@ -1,12 +1,18 @@
 use anyhow::{Context, Result};
+use cargo_pvm_contract_builder::{Bits, Profile, PvmBuilder};
 use clap::{Parser, Subcommand, ValueEnum};
 use include_dir::{Dir, include_dir};
-use inquire::{Select, Text};
+use inquire::{Select, Text, validator::Validation};
 use log::debug;
-use std::path::PathBuf;
+use std::io::{BufRead, IsTerminal};
+use std::path::{Path, PathBuf};
 
+mod inspect;
+mod output;
 mod scaffold;
 
+use output::OutputMode;
+
 // Embed the templates directory into the binary
 static TEMPLATES_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/templates");
 
@@ -24,7 +30,18 @@ enum Commands {
 }
 
 #[derive(Parser, Debug, Default)]
+#[command(
+    args_conflicts_with_subcommands = true,
+    after_help = "Environment variables:
+  SOLC_PATH     Path of the solc executable (default: solc from PATH)
+  SOLC_VERSION  solc version to run when solc is managed by solc-select"
+)]
 struct PvmContractArgs {
+    #[command(subcommand)]
+    command: Option<PvmContractCommand>,
+    /// Print machine-readable JSON (newline-delimited messages when scaffolding or building)
+    #[arg(long, global = true)]
+    json: bool,
     #[arg(long, value_enum)]
     init_type: Option<InitType>,
     #[arg(long)]
@@ -35,11 +52,125 @@ struct PvmContractArgs {
     name: Option<String>,
     #[arg(long)]
     sol_file: Option<PathBuf>,
+    /// Contract to scaffold when the Solidity file defines several (defaults to the first non-interface)
+    #[arg(long, value_name = "NAME")]
+    contract_name: Option<String>,
+    /// ABI JSON file (a bare array or a Hardhat/Foundry artifact), scaffolded without solc
+    #[arg(long, value_name = "PATH")]
+    abi_file: Option<PathBuf>,
+    /// Foundry artifact (`out/<File>.sol/<Contract>.json`) to scaffold from
+    #[arg(long, value_name = "PATH")]
+    foundry_artifact: Option<PathBuf>,
+    /// Generate a no-alloc contract without event signature and error selector constants
+    /// (solidity-file, abi-file and foundry-artifact only)
+    #[arg(long)]
+    minimal: bool,
+    /// Guard state-changing functions against reentrant calls
+    #[arg(long)]
+    reentrancy_guard: bool,
+    /// Add unit tests with a mocked host to the generated contract, run with `cargo test-host`
+    #[arg(long)]
+    with_tests: bool,
+    /// Word size of the PolkaVM target the generated project builds for
+    #[arg(long, value_enum, value_name = "BITS")]
+    target_bits: Option<TargetBits>,
+    /// Scaffold into an existing directory, overwriting only previously generated files
+    #[arg(long)]
+    force: bool,
+    /// Don't stage the generated `.gitignore` when scaffolding inside a git repository
+    #[arg(long)]
+    no_git: bool,
+    /// Add the contract to the enclosing Cargo workspace, in `contracts/<name>`
+    #[arg(long)]
+    workspace: bool,
+    /// Root of the workspace to add the contract to (default: the nearest one); implies --workspace
+    #[arg(long, value_name = "PATH")]
+    workspace_root: Option<PathBuf>,
+    /// Read `key=value` answers (e.g. `name=foo`) from a file, or `-` for stdin
+    #[arg(long, value_name = "PATH")]
+    answers_from: Option<PathBuf>,
+}
+
+#[derive(Subcommand, Debug)]
+enum PvmContractCommand {
+    /// Build an existing contract project to PolkaVM bytecode
+    Build(BuildArgs),
+    /// Show metadata of a linked .polkavm file
+    Inspect(InspectArgs),
+    /// List the example contracts available for scaffolding
+    ListExamples(ListExamplesArgs),
+    /// Print the ABI of a Solidity file as JSON
+    Abi(AbiArgs),
+}
+
+#[derive(Parser, Debug)]
+struct BuildArgs {
+    /// Path to the contract's Cargo.toml (defaults to the current directory)
+    #[arg(long, value_name = "PATH")]
+    manifest_path: Option<PathBuf>,
+    /// Build with the release profile
+    #[arg(long)]
+    release: bool,
+    /// Build only the given binary (can be repeated)
+    #[arg(long, value_name = "NAME")]
+    bin: Vec<String>,
+    /// Require Cargo.lock to be up to date
+    #[arg(long)]
+    locked: bool,
+    /// Build without accessing the network
+    #[arg(long)]
+    offline: bool,
+    /// Equivalent to specifying both --locked and --offline
+    #[arg(long)]
+    frozen: bool,
+    /// Also write `<bin>.abi.json` from the project's Solidity file
+    #[arg(long)]
+    abi: bool,
+    /// Check the selector, event and error constants of no-alloc contracts against
+    /// their Solidity file before building
+    #[arg(long)]
+    verify_abi: bool,
+    /// Build the contract with this rustup toolchain instead of the project's
+    #[arg(long, value_name = "TOOLCHAIN")]
+    toolchain: Option<String>,
+    /// Fail if a linked binary is larger than this many bytes
+    #[arg(long, value_name = "BYTES")]
+    max_size: Option<u64>,
+}
+
+#[derive(Parser, Debug)]
+struct InspectArgs {
+    /// The .polkavm file to inspect
+    file: PathBuf,
+}
+
+#[derive(Parser, Debug)]
+struct ListExamplesArgs {
+    /// Also show the first line of each example's Solidity interface
+    #[arg(long)]
+    verbose: bool,
+}
+
+#[derive(Parser, Debug)]
+struct AbiArgs {
+    /// The Solidity interface file
+    sol_file: PathBuf,
+    /// Contract to print when the file defines several (defaults to the first non-interface)
+    #[arg(long, value_name = "NAME")]
+    contract_name: Option<String>,
+    /// Print minified JSON
+    #[arg(long)]
+    compact: bool,
+    /// Add each function's 4-byte selector
+    #[arg(long)]
+    selector: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
 enum InitType {
     SolidityFile,
+    AbiFile,
+    FoundryArtifact,
     Example,
     Blank,
 }
@@ -48,6 +179,8 @@ impl std::fmt::Display for InitType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             InitType::SolidityFile => write!(f, "From a Solidity interface file (.sol)"),
+            InitType::AbiFile => write!(f, "From an ABI JSON file (.json)"),
+            InitType::FoundryArtifact => write!(f, "From a Foundry artifact (out/*.json)"),
             InitType::Example => write!(f, "From an example contract"),
             InitType::Blank => write!(f, "Blank (empty contract)"),
         }
@@ -71,6 +204,23 @@ impl std::fmt::Display for MemoryModel {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+enum TargetBits {
+    #[value(name = "32")]
+    B32,
+    #[value(name = "64")]
+    B64,
+}
+
+impl From<TargetBits> for Bits {
+    fn from(bits: TargetBits) -> Self {
+        match bits {
+            TargetBits::B32 => Bits::B32,
+            TargetBits::B64 => Bits::B64,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 struct ExampleContract {
     name: String,
@@ -122,6 +272,19 @@ impl ExampleContract {
         })
     }
 
+    /// First meaningful line of the Solidity file, skipping the license and pragma.
+    fn description(&self) -> Option<&'static str> {
+        let path = std::path::Path::new(&self.folder).join(&self.sol_filename);
+        TEMPLATES_DIR
+            .get_file(path)?
+            .contents_utf8()?
+            .lines()
+            .map(str::trim)
+            .find(|line| {
+                !line.is_empty() && !line.starts_with("// SPDX") && !line.starts_with("pragma")
+            })
+    }
+
     fn matches(&self, query: &str) -> bool {
         let query = query.trim().to_ascii_lowercase();
         let name = self.name.to_ascii_lowercase();
@@ -154,43 +317,278 @@ fn load_examples() -> Result<Vec<ExampleContract>> {
     Ok(examples)
 }
 
+/// Largest edit distance at which an unknown example name is taken for a typo.
+const MAX_EXAMPLE_TYPO_DISTANCE: usize = 3;
+
 fn find_example(examples: &[ExampleContract], query: &str) -> Result<ExampleContract> {
-    examples
+    if let Some(example) = examples.iter().find(|example| example.matches(query)) {
+        return Ok(example.clone());
+    }
+
+    let normalized = query.trim().to_ascii_lowercase();
+    let closest = examples
         .iter()
-        .find(|example| example.matches(query))
-        .cloned()
-        .ok_or_else(|| anyhow::anyhow!("Unknown example: {query}"))
+        .map(|example| {
+            let distance = strsim::levenshtein(&normalized, &example.name.to_ascii_lowercase());
+            (distance, example)
+        })
+        .min_by_key(|(distance, _)| *distance);
+    match closest {
+        Some((distance, example)) if distance <= MAX_EXAMPLE_TYPO_DISTANCE => {
+            anyhow::bail!(
+                "Unknown example '{query}'. Did you mean '{}'?",
+                example.name
+            )
+        }
+        _ => {
+            let names: Vec<&str> = examples
+                .iter()
+                .map(|example| example.name.as_str())
+                .collect();
+            anyhow::bail!(
+                "Unknown example '{query}'. Available examples: {}",
+                names.join(", ")
+            )
+        }
+    }
 }
 
-fn main() -> Result<()> {
+fn main() {
     env_logger::init();
 
     let Cli { command } = Cli::parse();
-    match command {
-        Commands::PvmContract(args) => init_command(args),
+    let Commands::PvmContract(mut args) = command;
+    let output = OutputMode::from_json_flag(args.json);
+    let result = match args.command.take() {
+        Some(PvmContractCommand::Build(build_args)) => build_command(build_args, output),
+        Some(PvmContractCommand::Inspect(inspect_args)) => inspect_command(inspect_args, output),
+        Some(PvmContractCommand::ListExamples(list_args)) => {
+            list_examples_command(list_args, output)
+        }
+        Some(PvmContractCommand::Abi(abi_args)) => abi_command(abi_args),
+        None => init_command(args, output),
+    };
+
+    if let Err(error) = result {
+        output.error(&error);
+        std::process::exit(1);
     }
 }
 
-fn init_command(args: PvmContractArgs) -> Result<()> {
+fn build_command(args: BuildArgs, output: OutputMode) -> Result<()> {
+    let manifest_path = match args.manifest_path {
+        Some(path) => path,
+        None => std::env::current_dir()?.join("Cargo.toml"),
+    };
+    if !manifest_path.is_file() {
+        anyhow::bail!("No Cargo.toml found at {}", manifest_path.display());
+    }
+    let manifest_path = manifest_path
+        .canonicalize()
+        .with_context(|| format!("Failed to resolve {}", manifest_path.display()))?;
+
+    let profile = if args.release {
+        Profile::Release
+    } else {
+        Profile::Debug
+    };
+    let builder = PvmBuilder::for_manifest(manifest_path);
+    // Same layout as a `cargo build` through the project's build.rs
+    let target_root = builder.cargo_target_dir()?;
+    let mut builder = builder
+        .with_profile(profile)
+        .with_target_dir(target_root.join("pvmbuild"))
+        .with_output_dir(&target_root);
+    if !args.bin.is_empty() {
+        builder = builder.with_bins(args.bin);
+    }
+    if args.locked {
+        builder = builder.with_locked();
+    }
+    if args.offline {
+        builder = builder.with_offline();
+    }
+    if args.frozen {
+        builder = builder.with_frozen();
+    }
+    if args.abi {
+        builder = builder.with_abi_output();
+    }
+    if args.verify_abi {
+        builder = builder.with_verify_abi_consts(true);
+    }
+    if let Some(toolchain) = args.toolchain {
+        builder = builder.with_toolchain(toolchain);
+    }
+    if let Some(bytes) = args.max_size {
+        builder = builder.with_max_size(bytes);
+    }
+
+    let build_output = builder.try_build()?;
+    for artifact in &build_output.artifacts {
+        output.info(format_args!(
+            "{} ({} bytes, code hash {})",
+            artifact.path.display(),
+            artifact.size,
+            artifact.code_hash
+        ));
+    }
+
+    Ok(())
+}
+
+fn inspect_command(args: InspectArgs, output: OutputMode) -> Result<()> {
+    let info = inspect::read_blob_info(&args.file)?;
+    if output == OutputMode::Json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print!("{}", inspect::render_text(&args.file, &info));
+    }
+    Ok(())
+}
+
+fn list_examples_command(args: ListExamplesArgs, output: OutputMode) -> Result<()> {
+    let examples = load_examples()?;
+
+    if output == OutputMode::Json {
+        let entries: Vec<_> = examples
+            .iter()
+            .map(|example| {
+                let mut entry = serde_json::json!({
+                    "name": example.name,
+                    "filename": example.sol_filename,
+                });
+                if args.verbose {
+                    entry["description"] = example.description().into();
+                }
+                entry
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    for example in &examples {
+        println!("{} ({})", example.name, example.sol_filename);
+        if args.verbose
+            && let Some(description) = example.description()
+        {
+            println!("    {description}");
+        }
+    }
+    Ok(())
+}
+
+fn abi_command(args: AbiArgs) -> Result<()> {
+    let abi = scaffold::abi_json(&args.sol_file, args.contract_name.as_deref(), args.selector)?;
+    if args.compact {
+        println!("{}", serde_json::to_string(&abi)?);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&abi)?);
+    }
+    Ok(())
+}
+
+fn init_command(mut args: PvmContractArgs, output: OutputMode) -> Result<()> {
+    // Answers read from a file or stdin fill in whatever was not passed as a flag
+    let answers_from_stdin = args.answers_from.as_deref() == Some(std::path::Path::new("-"));
+    if let Some(path) = args.answers_from.take() {
+        let answers = if answers_from_stdin {
+            read_answers(std::io::stdin().lock())?
+        } else {
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("Failed to open answers file: {}", path.display()))?;
+            read_answers(std::io::BufReader::new(file))?
+        };
+        apply_answers(&mut args, answers)?;
+    }
+
+    // The minimal preset only makes sense without an allocator
+    if args.minimal {
+        if args.memory_model == Some(MemoryModel::AllocWithAlloy) {
+            anyhow::bail!("--minimal cannot be combined with --memory-model alloc-with-alloy");
+        }
+        args.memory_model = Some(MemoryModel::NoAlloc);
+    }
+    // The memory model is filled in once it is known for the chosen init type
+    let mut options = if args.minimal {
+        scaffold::ScaffoldOptions::minimal()
+    } else {
+        scaffold::ScaffoldOptions::standard(false)
+    };
+    options.reentrancy_guard = args.reentrancy_guard;
+    options.with_tests = args.with_tests;
+    options.bits = args.target_bits.map_or(Bits::B64, Bits::from);
+    options.force = args.force;
+    options.git = !args.no_git;
+    options.output = output;
+    if args.workspace || args.workspace_root.is_some() {
+        options.workspace_root = Some(scaffold::find_workspace_root(
+            args.workspace_root.as_deref(),
+        )?);
+    }
+
+    // inquire reads answers from stdin and renders prompts on stderr
+    let interactive =
+        !answers_from_stdin && std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+    if !interactive {
+        let missing = missing_answers(&args);
+        if !missing.is_empty() {
+            anyhow::bail!(
+                "Cannot prompt for answers: not running in a terminal.\n\
+                 Pass the following flags (or use --answers-from) for non-interactive use: {}",
+                missing.join(", ")
+            );
+        }
+    }
+
     // Get init_type from args or prompt
     let init_type = match args.init_type {
         Some(t) => t,
         None => {
-            let init_types = vec![InitType::SolidityFile, InitType::Example, InitType::Blank];
+            let init_types = vec![
+                InitType::SolidityFile,
+                InitType::AbiFile,
+                InitType::FoundryArtifact,
+                InitType::Example,
+                InitType::Blank,
+            ];
             Select::new("How do you want to initialize the project?", init_types)
                 .prompt()
                 .context("Failed to get initialization type")?
         }
     };
+    // Examples ship their own code and blank contracts have no functions to guard
+    // or event/error constants to drop
+    if matches!(init_type, InitType::Example | InitType::Blank) {
+        let init_type = init_type.to_possible_value().expect("no skipped variants");
+        let flags = [
+            ("--minimal", args.minimal),
+            ("--reentrancy-guard", options.reentrancy_guard),
+        ];
+        if let Some((flag, _)) = flags.iter().find(|(_, set)| *set) {
+            anyhow::bail!(
+                "{flag} cannot be combined with --init-type {}",
+                init_type.get_name()
+            );
+        }
+    }
 
-    match init_type {
+    let project_dir = match init_type {
         InitType::Blank => {
             let memory_model = prompt_memory_model(args.memory_model)?;
-            let contract_name = prompt_name(args.name, None)?;
-            check_dir_exists(&contract_name)?;
-            let use_alloc = memory_model == MemoryModel::AllocWithAlloy;
-            debug!("Initializing blank contract: {contract_name} with alloc: {use_alloc}");
-            scaffold::init_blank_contract(&contract_name, use_alloc)
+            let contract_name = prompt_name(args.name, None, interactive)?;
+            check_dir_exists(
+                &contract_name,
+                options.workspace_root.as_deref(),
+                options.force,
+            )?;
+            options.use_alloc = memory_model == MemoryModel::AllocWithAlloy;
+            debug!(
+                "Initializing blank contract: {contract_name} with alloc: {}",
+                options.use_alloc
+            );
+            scaffold::init_blank_contract(&contract_name, &options)
         }
         InitType::Example => {
             let examples = load_examples()?;
@@ -204,15 +602,20 @@ fn init_command(args: PvmContractArgs) -> Result<()> {
             };
 
             let memory_model = prompt_memory_model(args.memory_model)?;
-            let contract_name = prompt_name(args.name, Some(&example.name))?;
+            let contract_name = prompt_name(args.name, Some(&example.name), interactive)?;
 
-            check_dir_exists(&contract_name)?;
+            check_dir_exists(
+                &contract_name,
+                options.workspace_root.as_deref(),
+                options.force,
+            )?;
             debug!(
                 "Initializing from example: {} with memory model: {:?}",
                 example.sol_filename, memory_model
             );
 
-            init_from_example(&example, &contract_name, memory_model)
+            options.use_alloc = memory_model == MemoryModel::AllocWithAlloy;
+            init_from_example(&example, &contract_name, &options)
         }
         InitType::SolidityFile => {
             // Get sol_file from args or prompt
@@ -235,29 +638,225 @@ fn init_command(args: PvmContractArgs) -> Result<()> {
                 anyhow::bail!("Solidity file not found: {}", sol_path.display());
             }
 
-            let default_name = sol_path
+            let default_name = match &args.contract_name {
+                Some(name) => name.clone(),
+                None => sol_path
+                    .file_stem()
+                    .and_then(|s| s.to_str())
+                    .unwrap_or("contract")
+                    .to_string(),
+            };
+
+            let memory_model = prompt_memory_model(args.memory_model)?;
+            let contract_name = prompt_name(args.name, Some(&default_name), interactive)?;
+
+            check_dir_exists(
+                &contract_name,
+                options.workspace_root.as_deref(),
+                options.force,
+            )?;
+            debug!(
+                "Initializing from Solidity file: {} with memory model: {:?}",
+                sol_path.display(),
+                memory_model
+            );
+
+            options.use_alloc = memory_model == MemoryModel::AllocWithAlloy;
+            scaffold::init_from_solidity_file(
+                &sol_path,
+                args.contract_name.as_deref(),
+                &contract_name,
+                &options,
+            )
+        }
+        InitType::AbiFile => {
+            let abi_path = match args.abi_file {
+                Some(path) => path,
+                None => {
+                    let abi_file = Text::new("Enter path to your ABI .json file:")
+                        .with_help_message("A JSON ABI array or a Hardhat/Foundry artifact")
+                        .prompt()
+                        .context("Failed to get ABI file path")?;
+
+                    if abi_file.is_empty() {
+                        anyhow::bail!("ABI file path cannot be empty");
+                    }
+                    PathBuf::from(abi_file)
+                }
+            };
+
+            let abi_json = std::fs::read_to_string(&abi_path)
+                .with_context(|| format!("Failed to read ABI file: {}", abi_path.display()))?;
+
+            let default_name = abi_path
                 .file_stem()
                 .and_then(|s| s.to_str())
                 .unwrap_or("contract")
                 .to_string();
 
             let memory_model = prompt_memory_model(args.memory_model)?;
-            let contract_name = prompt_name(args.name, Some(&default_name))?;
+            let contract_name = prompt_name(args.name, Some(&default_name), interactive)?;
 
-            check_dir_exists(&contract_name)?;
+            check_dir_exists(
+                &contract_name,
+                options.workspace_root.as_deref(),
+                options.force,
+            )?;
             debug!(
-                "Initializing from Solidity file: {} with memory model: {:?}",
-                sol_path.display(),
+                "Initializing from ABI file: {} with memory model: {:?}",
+                abi_path.display(),
                 memory_model
             );
 
-            let sol_file = sol_path.to_str().ok_or_else(|| {
-                anyhow::anyhow!("Solidity file path is not valid UTF-8: {:?}", sol_path)
-            })?;
-            let use_alloc = memory_model == MemoryModel::AllocWithAlloy;
-            scaffold::init_from_solidity_file(sol_file, &contract_name, use_alloc)
+            options.use_alloc = memory_model == MemoryModel::AllocWithAlloy;
+            scaffold::init_from_abi_json(&abi_json, &contract_name, &options)
         }
+        InitType::FoundryArtifact => {
+            let artifact_path = match args.foundry_artifact {
+                Some(path) => path,
+                None => {
+                    let artifact = Text::new("Enter path to your Foundry artifact:")
+                        .with_help_message(
+                            "e.g. out/MyToken.sol/MyToken.json, written by `forge build`",
+                        )
+                        .prompt()
+                        .context("Failed to get Foundry artifact path")?;
+
+                    if artifact.is_empty() {
+                        anyhow::bail!("Foundry artifact path cannot be empty");
+                    }
+                    PathBuf::from(artifact)
+                }
+            };
+
+            if !artifact_path.exists() {
+                anyhow::bail!("Foundry artifact not found: {}", artifact_path.display());
+            }
+
+            let default_name = artifact_path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("contract")
+                .to_string();
+
+            let memory_model = prompt_memory_model(args.memory_model)?;
+            let contract_name = prompt_name(args.name, Some(&default_name), interactive)?;
+
+            check_dir_exists(
+                &contract_name,
+                options.workspace_root.as_deref(),
+                options.force,
+            )?;
+            debug!(
+                "Initializing from Foundry artifact: {} with memory model: {:?}",
+                artifact_path.display(),
+                memory_model
+            );
+
+            options.use_alloc = memory_model == MemoryModel::AllocWithAlloy;
+            scaffold::init_from_foundry_artifact(&artifact_path, &contract_name, &options)
+        }
+    }?;
+    debug!("Created project at {}", project_dir.display());
+    Ok(())
+}
+
+/// Parse `key=value` answer lines, skipping blank lines and `#` comments.
+fn read_answers(reader: impl BufRead) -> Result<Vec<(String, String)>> {
+    let mut answers = Vec::new();
+    for (idx, line) in reader.lines().enumerate() {
+        let line = line.context("Failed to read answers")?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| anyhow::anyhow!("Invalid answer on line {}: {line}", idx + 1))?;
+        answers.push((key.trim().to_string(), value.trim().to_string()));
+    }
+    Ok(answers)
+}
+
+/// Fill in arguments that were not passed on the command line from the given answers.
+fn apply_answers(args: &mut PvmContractArgs, answers: Vec<(String, String)>) -> Result<()> {
+    for (key, value) in answers {
+        match key.as_str() {
+            "init-type" => {
+                let init_type = InitType::from_str(&value, true)
+                    .map_err(|e| anyhow::anyhow!("Invalid init-type answer: {e}"))?;
+                args.init_type.get_or_insert(init_type);
+            }
+            "memory-model" => {
+                let memory_model = MemoryModel::from_str(&value, true)
+                    .map_err(|e| anyhow::anyhow!("Invalid memory-model answer: {e}"))?;
+                args.memory_model.get_or_insert(memory_model);
+            }
+            "example" => {
+                args.example.get_or_insert(value);
+            }
+            "name" => {
+                args.name.get_or_insert(value);
+            }
+            "sol-file" => {
+                args.sol_file.get_or_insert(PathBuf::from(value));
+            }
+            "contract-name" => {
+                args.contract_name.get_or_insert(value);
+            }
+            "abi-file" => {
+                args.abi_file.get_or_insert(PathBuf::from(value));
+            }
+            "foundry-artifact" => {
+                args.foundry_artifact.get_or_insert(PathBuf::from(value));
+            }
+            "target-bits" => {
+                let bits = TargetBits::from_str(&value, true)
+                    .map_err(|e| anyhow::anyhow!("Invalid target-bits answer: {e}"))?;
+                args.target_bits.get_or_insert(bits);
+            }
+            _ => anyhow::bail!("Unknown answer key: {key}"),
+        }
+    }
+    Ok(())
+}
+
+/// List the flags that would otherwise be prompted for.
+fn missing_answers(args: &PvmContractArgs) -> Vec<&'static str> {
+    let mut missing = Vec::new();
+    match args.init_type {
+        None => missing.push("--init-type"),
+        Some(InitType::Blank) => {
+            if args.name.is_none() {
+                missing.push("--name");
+            }
+        }
+        Some(InitType::Example) => {
+            if args.example.is_none() {
+                missing.push("--example");
+            }
+        }
+        Some(InitType::SolidityFile) => {
+            if args.sol_file.is_none() {
+                missing.push("--sol-file");
+            }
+        }
+        Some(InitType::AbiFile) => {
+            if args.abi_file.is_none() {
+                missing.push("--abi-file");
+            }
+        }
+        Some(InitType::FoundryArtifact) => {
+            if args.foundry_artifact.is_none() {
+                missing.push("--foundry-artifact");
+            }
+        }
+    }
+    if args.memory_model.is_none() {
+        missing.push("--memory-model");
     }
+    missing
 }
 
 fn prompt_memory_model(arg: Option<MemoryModel>) -> Result<MemoryModel> {
@@ -272,12 +871,19 @@ fn prompt_memory_model(arg: Option<MemoryModel>) -> Result<MemoryModel> {
     }
 }
 
-fn prompt_name(arg: Option<String>, default: Option<&str>) -> Result<String> {
+fn prompt_name(arg: Option<String>, default: Option<&str>, interactive: bool) -> Result<String> {
     let contract_name = match arg {
         Some(name) => name,
+        None if !interactive => default.unwrap_or_default().to_string(),
         None => {
             let mut prompt = Text::new("What is your contract name?")
-                .with_help_message("This will be the name of the project directory");
+                .with_help_message("This will be the name of the project directory")
+                .with_validator(|name: &str| {
+                    Ok(match scaffold::validate_contract_name(name) {
+                        Ok(()) => Validation::Valid,
+                        Err(err) => Validation::Invalid(err.to_string().into()),
+                    })
+                });
             if let Some(d) = default {
                 prompt = prompt.with_default(d);
             }
@@ -288,6 +894,7 @@ fn prompt_name(arg: Option<String>, default: Option<&str>) -> Result<String> {
     if contract_name.is_empty() {
         anyhow::bail!("Contract name cannot be empty");
     }
+    scaffold::validate_contract_name(&contract_name)?;
 
     Ok(contract_name)
 }
@@ -295,15 +902,14 @@ fn prompt_name(arg: Option<String>, default: Option<&str>) -> Result<String> {
 fn init_from_example(
     example: &ExampleContract,
     contract_name: &str,
-    memory_model: MemoryModel,
-) -> Result<()> {
+    options: &scaffold::ScaffoldOptions,
+) -> Result<PathBuf> {
     let sol_path = format!("{}/{}", example.folder, example.sol_filename);
     let sol_file = TEMPLATES_DIR
         .get_file(&sol_path)
         .ok_or_else(|| anyhow::anyhow!("Example file not found: {sol_path}"))?;
 
-    let use_alloc = memory_model == MemoryModel::AllocWithAlloy;
-    let rust_example_name = if use_alloc {
+    let rust_example_name = if options.use_alloc {
         example.rust_with_alloc.as_str()
     } else {
         example.rust_no_alloc.as_str()
@@ -319,13 +925,13 @@ fn init_from_example(
         &example.sol_filename,
         rust_file.contents(),
         contract_name,
-        use_alloc,
+        options,
     )
 }
 
-fn check_dir_exists(contract_name: &str) -> Result<()> {
-    let target_dir = std::env::current_dir()?.join(contract_name);
-    if target_dir.exists() {
+fn check_dir_exists(contract_name: &str, workspace_root: Option<&Path>, force: bool) -> Result<()> {
+    let target_dir = scaffold::project_dir(contract_name, workspace_root)?;
+    if target_dir.exists() && !force {
         anyhow::bail!("Directory already exists: {target_dir:?}");
     }
     Ok(())
@@ -0,0 +1,60 @@
+use std::fmt::Display;
+
+/// How the CLI reports progress, warnings and errors.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Prose on stdout, warnings and errors on stderr.
+    #[default]
+    Human,
+    /// One `{"level": ..., "message": ...}` object per line on stdout.
+    Json,
+}
+
+impl OutputMode {
+    pub fn from_json_flag(json: bool) -> Self {
+        if json { Self::Json } else { Self::Human }
+    }
+
+    pub fn info(self, message: impl Display) {
+        match self {
+            Self::Human => println!("{message}"),
+            Self::Json => println!("{}", json_line("info", &message)),
+        }
+    }
+
+    pub fn warning(self, message: impl Display) {
+        match self {
+            Self::Human => eprintln!("warning: {message}"),
+            Self::Json => println!("{}", json_line("warning", &message)),
+        }
+    }
+
+    /// Report an error; `{:#}` keeps anyhow's context chain on one line.
+    pub fn error(self, error: &anyhow::Error) {
+        match self {
+            Self::Human => eprintln!("Error: {error:?}"),
+            Self::Json => println!("{}", json_line("error", &format!("{error:#}"))),
+        }
+    }
+}
+
+fn json_line(level: &str, message: &dyn Display) -> serde_json::Value {
+    serde_json::json!({
+        "level": level,
+        "message": message.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_lines_have_level_and_message() {
+        let line = json_line("info", &"Created \"x\"");
+        assert_eq!(
+            line.to_string(),
+            r#"{"level":"info","message":"Created \"x\""}"#
+        );
+    }
+}
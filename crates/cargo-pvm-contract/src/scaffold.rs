@@ -3,12 +3,22 @@
 struct BlankSolTemplate<'a> {
     contract_name: &'a str,
 }
+use crate::output::OutputMode;
 use anyhow::{Context, Result};
 use askama::Template;
+use cargo_pvm_contract_builder::{
+    Bits, PvmBuildError,
+    abi_consts::{ABI_CONSTS_FILE, AbiConst, AbiConstKind, AbiConsts},
+};
 use convert_case::{Case, Casing};
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::io::Write;
-use std::{fs, path::PathBuf, process::Command};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
 use tiny_keccak::{Hasher, Keccak};
 
 #[derive(Template)]
@@ -16,16 +26,31 @@ use tiny_keccak::{Hasher, Keccak};
 struct ContractAllocTemplate<'a> {
     sol_file_name: &'a str,
     functions: Vec<AllocFunctionInfo>,
+    reentrancy_guard: Option<ReentrancyGuardConsts>,
+    constructor: Option<ConstructorInfo>,
+    uses_sol_data: bool,
+    uses_sol_constructor: bool,
+    tests: bool,
 }
 
 #[derive(Template)]
 #[template(path = "scaffold/contract_no_alloc.rs.txt")]
 struct ContractNoAllocTemplate<'a> {
     contract_name_upper: &'a str,
+    abi_helpers: bool,
+    reentrancy_guard: Option<ReentrancyGuardConsts>,
+    constructor: Option<ConstructorInfo>,
     selectors: Vec<SelectorConst>,
     events: Vec<EventConst>,
     errors: Vec<ErrorConst>,
     functions: Vec<NoAllocFunctionInfo>,
+    structs: Vec<TupleStruct>,
+    return_encoders: Vec<ReturnEncoder>,
+    uses_abi_offset: bool,
+    uses_bytes_buffer: bool,
+    uses_address: bool,
+    uses_bool: bool,
+    tests: bool,
 }
 
 const BUILDER_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -38,24 +63,106 @@ struct CargoTomlTemplate<'a> {
     use_alloc: bool,
     builder_version: &'a str,
     builder_path: Option<String>,
+    /// Check the generated ABI constants on every build, see [`AbiConsts`].
+    verify_abi_consts: bool,
+    /// Leave profiles to the workspace root, as cargo ignores them in members.
+    workspace_member: bool,
 }
 
 #[derive(Template)]
 #[template(path = "scaffold/contract_blank.rs.txt")]
-struct ContractBlankTemplate;
+struct ContractBlankTemplate {
+    tests: bool,
+}
 
 #[derive(Template)]
 #[template(path = "scaffold/contract_blank_alloc.rs.txt")]
-struct ContractBlankAllocTemplate;
+struct ContractBlankAllocTemplate {
+    tests: bool,
+}
+
+/// The `#[cfg(test)]` module appended to a contract by `--with-tests`.
+#[derive(Template)]
+#[template(path = "scaffold/contract_tests.rs.txt")]
+struct ContractTestsTemplate {
+    /// Mock the host functions, for contracts that call them.
+    mock: bool,
+    /// The contract dispatches on `functions`' selectors.
+    dispatches: bool,
+    functions: Vec<FunctionTest>,
+    /// A guarded function to re-enter while the reentrancy guard is held.
+    reentrant_call: Option<FunctionTest>,
+}
+
+/// A test calling one contract function, ignored until the function is implemented.
+#[derive(Clone)]
+struct FunctionTest {
+    name: String,
+    test_name: String,
+    /// The `sol!` call type to encode the call with, for alloc contracts.
+    call_type: Option<String>,
+    selector_const: String,
+    min_call_data_len: usize,
+    /// The function runs behind the reentrancy guard.
+    guarded: bool,
+}
+
+#[derive(Template)]
+#[template(path = "scaffold/readme.md.txt")]
+struct ReadmeTemplate<'a> {
+    contract_name: &'a str,
+    bin_source: &'a str,
+    sol_file_name: &'a str,
+    use_alloc: bool,
+    with_tests: bool,
+}
 
 #[derive(Template)]
 #[template(path = "scaffold/build.rs.txt")]
-struct BuildRsTemplate;
+struct BuildRsTemplate {
+    is_32_bit: bool,
+}
 
 struct AllocFunctionInfo {
     name: String,
     name_snake: String,
     call_type: String,
+    guarded: bool,
+    /// The function accepts value, so calls sending some aren't reverted.
+    is_payable: bool,
+    /// The function is `view` or `pure` and must not write storage.
+    read_only: bool,
+    /// `let` bindings pulling array parameters out of the decoded call.
+    array_params: Vec<String>,
+}
+
+/// Storage key and revert selector used by the generated reentrancy guard.
+struct ReentrancyGuardConsts {
+    key_hex: String,
+    key_preimage: &'static str,
+    error_hex: String,
+    error_signature: &'static str,
+}
+
+/// The `deploy` body storing the arguments of a constructor.
+struct ConstructorInfo {
+    /// Shortest call data holding the head of every argument (no-alloc only).
+    min_call_data_len: usize,
+    /// Statements run before the arguments are stored: slicing the call data for
+    /// no-alloc, decoding every argument for alloc.
+    decode_stmts: Vec<String>,
+    args: Vec<ConstructorArg>,
+}
+
+/// A constructor argument and the storage key `deploy` writes it to.
+struct ConstructorArg {
+    key_const: String,
+    key_hex: String,
+    key_preimage: String,
+    /// `<index>: <name> (<type>)`, for the key's comment.
+    description: String,
+    /// Statements decoding the argument (no-alloc only) and storing it.
+    stmts: Vec<String>,
 }
 
 struct SelectorConst {
@@ -81,15 +188,85 @@ struct NoAllocFunctionInfo {
     selector_const: String,
     min_call_data_len: usize,
     params: Vec<ParamDecode>,
+    guarded: bool,
+    /// The function accepts value, so calls sending some aren't reverted.
+    is_payable: bool,
+    /// The function is `view` or `pure` and must not write storage.
+    read_only: bool,
+    /// Name of the generated `encode_*_return` function, if any.
+    return_encoder: Option<String>,
 }
 
 struct ParamDecode {
     decode_line: String,
 }
 
+/// A Rust struct generated for a Solidity tuple in no-alloc mode.
+struct TupleStruct {
+    name: String,
+    signature: String,
+    /// Size of the tuple's head, which is its whole encoding for static tuples.
+    encoded_len: usize,
+    fields: Vec<TupleField>,
+    /// Only static tuples get an `encode` method.
+    encodable: bool,
+}
+
+struct TupleField {
+    name: String,
+    rust_type: String,
+    decode_expr: String,
+    encode_stmts: Vec<String>,
+}
+
+/// `encode_<function>_return` for a function returning static tuples.
+struct ReturnEncoder {
+    fn_name: String,
+    function: String,
+    params: String,
+    encoded_len: usize,
+    encode_stmts: Vec<String>,
+}
+
+/// How a value of an ABI type is read from and written to its encoding.
+struct ValueCodec {
+    rust_type: String,
+    /// Type used for function parameters; structs are passed by reference.
+    param_type: String,
+    decode_expr: String,
+    /// Empty for values that cannot be encoded in place (dynamic tuples).
+    encode_stmts: Vec<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct SolcOutput {
-    contracts: std::collections::HashMap<String, std::collections::HashMap<String, ContractInfo>>,
+    contracts: BTreeMap<String, BTreeMap<String, ContractInfo>>,
+    #[serde(default)]
+    sources: BTreeMap<String, SourceOutput>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct SourceOutput {
+    #[serde(default)]
+    ast: SourceUnit,
+}
+
+/// The parts of a source file's AST needed to tell contracts from interfaces.
+#[derive(Debug, Default, Deserialize)]
+struct SourceUnit {
+    #[serde(default)]
+    nodes: Vec<AstNode>,
+}
+
+#[derive(Debug, Deserialize)]
+struct AstNode {
+    #[serde(rename = "nodeType")]
+    node_type: String,
+    #[serde(default)]
+    name: Option<String>,
+    /// `contract`, `interface` or `library` for contract definitions.
+    #[serde(rename = "contractKind", default)]
+    contract_kind: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -100,6 +277,10 @@ struct ContractInfo {
 #[derive(Debug, Deserialize)]
 struct ContractMetadata {
     output: MetadataOutput,
+    /// The `.sol` file is an interface generated from an ABI, which can't declare the
+    /// constructor, so `sol!` generates no `constructorCall` for it.
+    #[serde(skip)]
+    from_interface: bool,
 }
 
 #[derive(Debug, Deserialize)]
@@ -107,17 +288,15 @@ struct MetadataOutput {
     abi: Vec<AbiItem>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Serialize, Clone)]
 #[serde(tag = "type")]
 enum AbiItem {
     #[serde(rename = "function")]
     Function {
         name: String,
         inputs: Vec<AbiInput>,
-        #[allow(dead_code)]
-        outputs: Vec<AbiOutput>,
+        outputs: Vec<AbiInput>,
         #[serde(rename = "stateMutability")]
-        #[allow(dead_code)]
         state_mutability: String,
     },
     #[serde(rename = "event")]
@@ -125,27 +304,37 @@ enum AbiItem {
     #[serde(rename = "error")]
     Error { name: String, inputs: Vec<AbiInput> },
     #[serde(rename = "constructor")]
-    Constructor {
-        #[allow(dead_code)]
-        inputs: Vec<AbiInput>,
+    Constructor { inputs: Vec<AbiInput> },
+    #[serde(rename = "fallback")]
+    Fallback {
+        #[serde(rename = "stateMutability")]
+        state_mutability: String,
+    },
+    #[serde(rename = "receive")]
+    Receive {
+        #[serde(rename = "stateMutability")]
+        state_mutability: String,
     },
 }
 
-#[derive(Debug, Deserialize, Clone)]
+/// A function input or output, event field or error field.
+#[derive(Debug, Deserialize, Serialize, Clone)]
 struct AbiInput {
     name: String,
     #[serde(rename = "type")]
     type_name: String,
-    #[allow(dead_code)]
+    /// Solidity-level type, e.g. `struct IOrders.Order` for a tuple.
+    #[serde(
+        rename = "internalType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    internal_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     indexed: Option<bool>,
-}
-
-#[derive(Debug, Deserialize, Clone)]
-#[allow(dead_code)]
-struct AbiOutput {
-    name: String,
-    #[serde(rename = "type")]
-    type_name: String,
+    /// Tuple members, present when `type_name` is `tuple` (or an array of tuples).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    components: Vec<AbiInput>,
 }
 
 /// Compute the keccak256 hash of a string
@@ -165,10 +354,169 @@ fn compute_selector(signature: &str) -> [u8; 4] {
 
 /// Build a function signature from name and input types
 fn build_function_signature(name: &str, inputs: &[AbiInput]) -> String {
-    let types: Vec<&str> = inputs.iter().map(|i| i.type_name.as_str()).collect();
+    let types: Vec<String> = inputs.iter().map(canonical_type).collect();
     format!("{}({})", name, types.join(","))
 }
 
+/// The ABI's functions without repeated signatures, each with the name its generated
+/// items use: overloads are numbered `name_0`, `name_1`, ... in declaration order,
+/// like the call types `sol!` generates for them. The index is `None` for other functions.
+fn abi_functions(abi: &[AbiItem]) -> Vec<(&AbiItem, String, Option<usize>)> {
+    let mut signatures = Vec::new();
+    let functions: Vec<(&AbiItem, &String)> = abi
+        .iter()
+        .filter_map(|item| match item {
+            AbiItem::Function { name, inputs, .. } => {
+                let signature = build_function_signature(name, inputs);
+                if signatures.contains(&signature) {
+                    return None;
+                }
+                signatures.push(signature);
+                Some((item, name))
+            }
+            _ => None,
+        })
+        .collect();
+    functions
+        .iter()
+        .enumerate()
+        .map(|(position, &(item, name))| {
+            let same_name = |&(_, other): &&(&AbiItem, &String)| *other == name;
+            if functions.iter().filter(same_name).count() < 2 {
+                return (item, name.clone(), None);
+            }
+            let index = functions[..position].iter().filter(same_name).count();
+            (item, format!("{name}_{index}"), Some(index))
+        })
+        .collect()
+}
+
+/// The type as it appears in signatures, with tuples spelled out as `(T1,T2)`.
+fn canonical_type(input: &AbiInput) -> String {
+    match input.type_name.strip_prefix("tuple") {
+        Some(array_suffix) => {
+            let components: Vec<String> = input.components.iter().map(canonical_type).collect();
+            format!("({}){array_suffix}", components.join(","))
+        }
+        None => input.type_name.clone(),
+    }
+}
+
+/// Whether a function with the given state mutability can modify state.
+fn is_state_changing(state_mutability: &str) -> bool {
+    !matches!(state_mutability, "view" | "pure")
+}
+
+/// Storage key preimage for the reentrancy guard flag. Hashing keeps it clear of
+/// the small integer slots and mapping keys used by the Solidity storage layout.
+const REENTRANCY_GUARD_KEY_PREIMAGE: &str = "cargo-pvm-contract.reentrancy-guard";
+
+/// Error reverted with when a guarded function is re-entered.
+const REENTRANCY_GUARD_ERROR_SIGNATURE: &str = "ReentrancyGuard()";
+
+fn reentrancy_guard_consts(enabled: bool) -> Option<ReentrancyGuardConsts> {
+    enabled.then(|| ReentrancyGuardConsts {
+        key_hex: format_bytes32_multiline(&keccak256(REENTRANCY_GUARD_KEY_PREIMAGE)),
+        key_preimage: REENTRANCY_GUARD_KEY_PREIMAGE,
+        error_hex: format_bytes_as_hex(&compute_selector(REENTRANCY_GUARD_ERROR_SIGNATURE)),
+        error_signature: REENTRANCY_GUARD_ERROR_SIGNATURE,
+    })
+}
+
+/// Split an array type into its element type and length (`None` for dynamic arrays).
+fn split_array_type(type_name: &str) -> Option<(&str, Option<usize>)> {
+    let inner = type_name.strip_suffix(']')?;
+    let open = inner.rfind('[')?;
+    let len = &inner[open + 1..];
+    let len = if len.is_empty() {
+        None
+    } else {
+        Some(len.parse().ok()?)
+    };
+    Some((&inner[..open], len))
+}
+
+/// Whether an ABI type is dynamically sized (encoded behind an offset).
+fn is_dynamic_type(type_name: &str, components: &[AbiInput]) -> bool {
+    if let Some((element, len)) = split_array_type(type_name) {
+        return len.is_none() || is_dynamic_type(element, components);
+    }
+
+    match type_name {
+        "string" | "bytes" => true,
+        "tuple" => components
+            .iter()
+            .any(|c| is_dynamic_type(&c.type_name, &c.components)),
+        _ => false,
+    }
+}
+
+/// Size in bytes an ABI type occupies in the head of the encoding.
+///
+/// Dynamic types only take a single offset word; static tuples and fixed-size
+/// arrays are encoded inline.
+fn abi_head_size(type_name: &str, components: &[AbiInput]) -> usize {
+    if is_dynamic_type(type_name, components) {
+        return 32;
+    }
+
+    if let Some((element, Some(len))) = split_array_type(type_name) {
+        return len * abi_head_size(element, components);
+    }
+
+    match type_name {
+        "tuple" => components
+            .iter()
+            .map(|c| abi_head_size(&c.type_name, &c.components))
+            .sum(),
+        _ => 32,
+    }
+}
+
+/// Minimum call data length for a function: selector plus the head of every input.
+fn min_call_data_len(inputs: &[AbiInput]) -> usize {
+    4 + inputs
+        .iter()
+        .map(|input| abi_head_size(&input.type_name, &input.components))
+        .sum::<usize>()
+}
+
+/// Inputs of the ABI's constructor, if it takes any arguments.
+fn constructor_inputs(abi: &[AbiItem]) -> Option<&[AbiInput]> {
+    abi.iter().find_map(|item| match item {
+        AbiItem::Constructor { inputs } if !inputs.is_empty() => Some(inputs.as_slice()),
+        _ => None,
+    })
+}
+
+/// Storage key preimage prefix for constructor arguments; the argument index is appended.
+/// Hashing keeps the keys clear of the slots used by the Solidity storage layout, like
+/// [`REENTRANCY_GUARD_KEY_PREIMAGE`].
+const CONSTRUCTOR_ARG_KEY_PREFIX: &str = "cargo-pvm-contract.constructor-arg.";
+
+/// Storage key preimage of constructor argument `index`.
+fn constructor_arg_key_preimage(index: usize) -> String {
+    format!("{CONSTRUCTOR_ARG_KEY_PREFIX}{index}")
+}
+
+/// Name of the constant holding the storage key of the constructor argument `param_name`.
+fn constructor_key_const(param_name: &str) -> String {
+    format!("{}_STORAGE_KEY", param_name.to_case(Case::UpperSnake))
+}
+
+/// Constructor argument `index`, stored by `stmts` under its key.
+fn constructor_arg(index: usize, input: &AbiInput, stmts: Vec<String>) -> ConstructorArg {
+    let param_name = rust_ident(&input.name, || format!("param_{index}"));
+    let key_preimage = constructor_arg_key_preimage(index);
+    ConstructorArg {
+        key_const: constructor_key_const(&param_name),
+        key_hex: format_bytes32_multiline(&keccak256(&key_preimage)),
+        key_preimage,
+        description: format!("{index}: {param_name} ({})", canonical_type(input)),
+        stmts,
+    }
+}
+
 /// Format a byte array as Rust hex literal
 fn format_bytes_as_hex(bytes: &[u8]) -> String {
     bytes
@@ -178,6 +526,11 @@ fn format_bytes_as_hex(bytes: &[u8]) -> String {
         .join(", ")
 }
 
+/// Format bytes as a lowercase hex string without separators
+fn hex_lower(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
 /// Format a 32-byte array with line breaks for readability
 fn format_bytes32_multiline(bytes: &[u8; 32]) -> String {
     bytes
@@ -193,88 +546,711 @@ fn format_bytes32_multiline(bytes: &[u8; 32]) -> String {
         .join(",\n    ")
 }
 
-/// Create a new blank contract project.
-pub fn init_blank_contract(contract_name: &str, use_alloc: bool) -> Result<()> {
-    let contract_name = contract_name.to_case(Case::Kebab);
-    let target_dir = std::env::current_dir()?.join(&contract_name);
-    if target_dir.exists() {
-        anyhow::bail!("Directory already exists: {target_dir:?}");
+/// Options shared by all scaffold entry points.
+#[derive(Debug, Clone)]
+pub struct ScaffoldOptions {
+    /// Generate alloy-core + allocator code instead of manual encoding.
+    pub use_alloc: bool,
+    /// Generate event signature and error selector constants.
+    pub abi_helpers: bool,
+    /// Wrap state-changing functions in a reentrancy guard.
+    pub reentrancy_guard: bool,
+    /// Word size of the PolkaVM target the project builds for.
+    pub bits: Bits,
+    /// Append host-side unit tests with a mocked host to the contract.
+    pub with_tests: bool,
+    /// Scaffold into an existing directory, overwriting only files a previous scaffold generated.
+    pub force: bool,
+    /// Stage the generated `.gitignore` when the project is created inside a git repository.
+    pub git: bool,
+    /// Root of the Cargo workspace to add the contract to, in `contracts/<name>`
+    /// (None = a standalone project in the current directory).
+    pub workspace_root: Option<PathBuf>,
+    /// Where progress and warnings are reported.
+    pub output: OutputMode,
+}
+
+impl ScaffoldOptions {
+    /// The default project layout.
+    pub fn standard(use_alloc: bool) -> Self {
+        Self {
+            use_alloc,
+            abi_helpers: true,
+            reentrancy_guard: false,
+            bits: Bits::B64,
+            with_tests: false,
+            force: false,
+            git: true,
+            workspace_root: None,
+            output: OutputMode::Human,
+        }
     }
 
-    fs::create_dir(&target_dir)
-        .with_context(|| format!("Failed to create directory: {target_dir:?}"))?;
+    /// The smallest working project: no allocator and no event/error helpers.
+    pub fn minimal() -> Self {
+        Self {
+            use_alloc: false,
+            abi_helpers: false,
+            reentrancy_guard: false,
+            bits: Bits::B64,
+            with_tests: false,
+            force: false,
+            git: true,
+            workspace_root: None,
+            output: OutputMode::Human,
+        }
+    }
+}
 
-    let (target_json_path, target_json_name) = resolve_target_json()?;
-    let target_json_dest = target_dir.join(target_json_name);
-    fs::copy(&target_json_path, &target_json_dest).with_context(|| {
+/// File listing the files a scaffold generated, relative to the project directory.
+const SCAFFOLD_MANIFEST: &str = ".pvm-scaffold.toml";
+
+/// Writes the files of a new project and records them in [`SCAFFOLD_MANIFEST`].
+///
+/// With `force`, an existing project directory is reused: files recorded by a
+/// previous scaffold are overwritten, anything else that already exists is kept.
+struct ProjectWriter {
+    root: PathBuf,
+    managed: Vec<String>,
+    written: Vec<String>,
+    output: OutputMode,
+}
+
+impl ProjectWriter {
+    fn create(root: &Path, options: &ScaffoldOptions) -> Result<Self> {
+        let mut managed = Vec::new();
+        if root.exists() {
+            if !options.force {
+                anyhow::bail!("Directory already exists: {root:?}");
+            }
+            managed = read_scaffold_manifest(root)?;
+        } else {
+            fs::create_dir_all(root)
+                .with_context(|| format!("Failed to create directory: {root:?}"))?;
+        }
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            managed,
+            written: Vec::new(),
+            output: options.output,
+        })
+    }
+
+    fn write(&mut self, relative_path: &str, contents: impl AsRef<[u8]>) -> Result<()> {
+        let path = self.root.join(relative_path);
+        if path.exists() && !self.managed.iter().any(|managed| managed == relative_path) {
+            self.output.warning(format_args!(
+                "Keeping existing {relative_path} (not generated by cargo-pvm-contract)"
+            ));
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {parent:?}"))?;
+        }
+        fs::write(&path, contents).with_context(|| format!("Failed to write {path:?}"))?;
+        self.written.push(relative_path.to_string());
+        Ok(())
+    }
+
+    /// Record the generated files, keeping files a previous scaffold owned.
+    fn finish(self) -> Result<()> {
+        let mut files = self.managed;
+        for file in self.written {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+        files.sort();
+
+        let mut doc = toml_edit::DocumentMut::new();
+        doc.decor_mut().set_prefix(
+            "# Files generated by cargo-pvm-contract. `--force` only overwrites these.\n",
+        );
+        doc["files"] = toml_edit::value(files.into_iter().collect::<toml_edit::Array>());
+        fs::write(self.root.join(SCAFFOLD_MANIFEST), doc.to_string())
+            .with_context(|| format!("Failed to write {SCAFFOLD_MANIFEST}"))
+    }
+}
+
+/// Files recorded by a previous scaffold, or none if the manifest is missing.
+fn read_scaffold_manifest(root: &Path) -> Result<Vec<String>> {
+    let path = root.join(SCAFFOLD_MANIFEST);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))?;
+    let doc: toml_edit::DocumentMut = content
+        .parse()
+        .with_context(|| format!("Failed to parse {path:?}"))?;
+    Ok(doc
+        .get("files")
+        .and_then(|files| files.as_array())
+        .map(|files| {
+            files
+                .iter()
+                .filter_map(|file| file.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Write the target JSON, cargo config, `.gitignore` and toolchain file.
+fn write_toolchain_files(project: &mut ProjectWriter, options: &ScaffoldOptions) -> Result<()> {
+    let (target_json_path, target_json_name) = resolve_target_json(options.bits)?;
+    let target_json = fs::read(&target_json_path).with_context(|| {
         format!(
-            "Failed to copy target JSON from {} to {}",
-            target_json_path.display(),
-            target_json_dest.display()
+            "Failed to read target JSON from {}",
+            target_json_path.display()
         )
     })?;
+    project.write(&target_json_name, target_json)?;
 
-    let target_json_name = target_json_dest
-        .file_name()
-        .and_then(|name| name.to_str())
-        .ok_or_else(|| anyhow::anyhow!("Target JSON path is missing a file name"))?;
+    let mut cargo_config = format!(
+        "[build]\n target = \"{}\"\n\n[unstable]\n build-std = [\"core\", \"alloc\"]\n\n[env]\n RUSTC_BOOTSTRAP = \"1\"\n",
+        target_json_name
+    );
+    if options.with_tests {
+        // The unit tests need std, so they run on the host rather than the contract target
+        cargo_config.push_str(
+            "\n[alias]\n test-host = \"test --target host-tuple -Zbuild-std=std,panic_unwind\"\n",
+        );
+    }
+    project.write(".cargo/config.toml", cargo_config)?;
 
-    let cargo_config_dir = target_dir.join(".cargo");
-    fs::create_dir(&cargo_config_dir)?;
-    fs::write(
-        cargo_config_dir.join("config.toml"),
-        format!(
-            "[build]\n target = \"{}\"\n\n[unstable]\n build-std = [\"core\", \"alloc\"]\n\n[env]\n RUSTC_BOOTSTRAP = \"1\"\n",
-            target_json_name
-        ),
+    // Build output, solc artifacts and the target's scratch directory
+    let target_name = target_json_name.trim_end_matches(".json");
+    project.write(
+        ".gitignore",
+        format!("/target\n*.polkavm\n/.cargo/registry\n*.sol.json\n/{target_name}/\n"),
     )?;
-
-    fs::write(target_dir.join(".gitignore"), "/target\n*.polkavm\n")?;
-    fs::write(
-        target_dir.join("rust-toolchain.toml"),
+    project.write(
+        "rust-toolchain.toml",
         "[toolchain]\nchannel = \"nightly\"\n",
-    )?;
-    fs::create_dir(target_dir.join("src"))?;
+    )
+}
+
+/// Directory the project named `contract_name` (kebab case) is created in.
+pub fn project_dir(contract_name: &str, workspace_root: Option<&Path>) -> Result<PathBuf> {
+    Ok(match workspace_root {
+        Some(root) => root.join("contracts").join(contract_name),
+        None => std::env::current_dir()?.join(contract_name),
+    })
+}
+
+/// The workspace to scaffold into: `root` if given, otherwise the nearest directory
+/// above the current one whose `Cargo.toml` has a `[workspace]` section.
+pub fn find_workspace_root(root: Option<&Path>) -> Result<PathBuf> {
+    let has_workspace = |dir: &Path| -> Result<bool> {
+        let manifest = dir.join("Cargo.toml");
+        if !manifest.is_file() {
+            return Ok(false);
+        }
+        Ok(read_toml(&manifest)?.get("workspace").is_some())
+    };
+
+    if let Some(root) = root {
+        let root = if root.file_name().is_some_and(|name| name == "Cargo.toml") {
+            root.parent().unwrap_or(Path::new("."))
+        } else {
+            root
+        };
+        if !has_workspace(root)? {
+            anyhow::bail!(
+                "{} has no Cargo.toml with a [workspace] section",
+                root.display()
+            );
+        }
+        return Ok(root.canonicalize()?);
+    }
+
+    let current_dir = std::env::current_dir()?;
+    for dir in current_dir.ancestors() {
+        if has_workspace(dir)? {
+            return Ok(dir.to_path_buf());
+        }
+    }
+    anyhow::bail!(
+        "No Cargo workspace found above {}; pass its root with --workspace-root",
+        current_dir.display()
+    )
+}
+
+fn read_toml(path: &Path) -> Result<toml_edit::DocumentMut> {
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+    content
+        .parse()
+        .with_context(|| format!("Failed to parse {path:?}"))
+}
+
+/// Add `project_dir` to the `members` of the workspace at `root`, unless a glob already covers it.
+fn add_workspace_member(root: &Path, project_dir: &Path, options: &ScaffoldOptions) -> Result<()> {
+    let manifest_path = root.join("Cargo.toml");
+    let mut doc = read_toml(&manifest_path)?;
+    let member = project_dir
+        .strip_prefix(root)
+        .unwrap_or(project_dir)
+        .to_string_lossy()
+        .replace('\\', "/");
+
+    let workspace = doc
+        .get_mut("workspace")
+        .and_then(|workspace| workspace.as_table_like_mut())
+        .with_context(|| format!("{manifest_path:?} has no [workspace] section"))?;
+    let members = workspace
+        .entry("members")
+        .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+        .as_array_mut()
+        .with_context(|| format!("`workspace.members` in {manifest_path:?} is not an array"))?;
+    let parent_glob = member
+        .rsplit_once('/')
+        .map(|(parent, _)| format!("{parent}/*"));
+    let covered = members
+        .iter()
+        .filter_map(|value| value.as_str())
+        .any(|existing| {
+            existing.trim_end_matches('/') == member || Some(existing) == parent_glob.as_deref()
+        });
+    if !covered {
+        members.push(member.as_str());
+        fs::write(&manifest_path, doc.to_string())
+            .with_context(|| format!("Failed to write {manifest_path:?}"))?;
+    }
+
+    if doc
+        .get("profile")
+        .and_then(|profile| profile.get("release"))
+        .is_none()
+    {
+        options.output.warning(format_args!(
+            "Cargo only reads profiles from the workspace root; add the contract's size settings \
+             ([profile.release] with opt-level = \"z\", lto = true, codegen-units = 1 and \
+             panic = \"abort\") to {manifest_path:?}"
+        ));
+    }
+    Ok(())
+}
+
+/// The project directory as shown in "Next steps", relative to the current directory if possible.
+fn cd_path(project_dir: &Path) -> String {
+    std::env::current_dir()
+        .ok()
+        .and_then(|current| {
+            project_dir
+                .strip_prefix(current)
+                .ok()
+                .map(Path::to_path_buf)
+        })
+        .unwrap_or_else(|| project_dir.to_path_buf())
+        .display()
+        .to_string()
+}
+
+/// Stage the project's `.gitignore` if it was created inside a git repository.
+fn stage_gitignore(project_dir: &Path, options: &ScaffoldOptions) {
+    if !options.git || !project_dir.ancestors().any(|dir| dir.join(".git").exists()) {
+        return;
+    }
+
+    let staged = Command::new("git")
+        .current_dir(project_dir)
+        .args(["add", ".gitignore"])
+        .output();
+    match staged {
+        Ok(output) if output.status.success() => {}
+        Ok(output) => options.output.warning(format_args!(
+            "Failed to stage .gitignore: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )),
+        Err(err) => options
+            .output
+            .warning(format_args!("Failed to stage .gitignore: {err}")),
+    }
+}
+
+/// Names cargo refuses as package names, on top of the Rust keywords.
+const RESERVED_NAMES: &[&str] = &["alloc", "core", "proc_macro", "std", "test"];
+
+const RUST_KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "crate",
+    "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl",
+    "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref",
+    "return", "self", "static", "struct", "super", "trait", "true", "try", "type", "typeof",
+    "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Check that `name` makes a valid package, binary and source file name once converted.
+///
+/// Letters, digits, spaces, `-` and `_` are accepted, as `MyToken`, `my token` and
+/// `my-token` all become `my-token`; the name must not start with a digit or be a
+/// Rust keyword.
+pub fn validate_contract_name(name: &str) -> Result<()> {
+    if let Some(c) = name
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && !matches!(c, '-' | '_' | ' '))
+    {
+        anyhow::bail!(
+            "Invalid contract name `{name}`: `{c}` is not allowed; use ASCII letters, digits, spaces, `-` or `_`"
+        );
+    }
+
+    let snake = name.to_case(Case::Snake);
+    if snake.is_empty() {
+        anyhow::bail!("Invalid contract name `{name}`: it must contain a letter");
+    }
+    if snake.starts_with(|c: char| c.is_ascii_digit()) {
+        anyhow::bail!("Invalid contract name `{name}`: it must not start with a digit");
+    }
+    if RUST_KEYWORDS.contains(&snake.as_str()) || RESERVED_NAMES.contains(&snake.as_str()) {
+        anyhow::bail!("Invalid contract name `{name}`: `{snake}` is reserved by Rust");
+    }
+    Ok(())
+}
+
+/// Create a new blank contract project and return its absolute path.
+pub fn init_blank_contract(contract_name: &str, options: &ScaffoldOptions) -> Result<PathBuf> {
+    validate_contract_name(contract_name)?;
+    let contract_name = contract_name.to_case(Case::Kebab);
+    let target_dir = project_dir(&contract_name, options.workspace_root.as_deref())?;
+    let mut project = ProjectWriter::create(&target_dir, options)?;
+
+    write_toolchain_files(&mut project, options)?;
 
     // Write a minimal Solidity interface companion file using the template
     let contract_name_pascal = contract_name.to_case(Case::Pascal);
     let sol_file_name = format!("{}.sol", contract_name_pascal);
-    let sol_content = BlankSolTemplate {
-        contract_name: &contract_name_pascal,
+    let sol_content = render(
+        &BlankSolTemplate {
+            contract_name: &contract_name_pascal,
+        },
+        "blank Solidity interface",
+    )?;
+    project.write(&sol_file_name, sol_content)?;
+
+    let lib_rs_content = generate_blank_contract(options.use_alloc, options.with_tests)?;
+    project.write(&format!("src/{}.rs", contract_name), lib_rs_content)?;
+
+    let build_rs_content = generate_build_rs(options.bits)?;
+    project.write("build.rs", build_rs_content)?;
+
+    let cargo_toml_content = generate_cargo_toml(&contract_name, &contract_name, false, options)?;
+    project.write("Cargo.toml", cargo_toml_content)?;
+
+    let readme = generate_readme(&contract_name, &contract_name, &sol_file_name, options)?;
+    project.write("README.md", readme)?;
+    project.finish()?;
+    if let Some(root) = &options.workspace_root {
+        add_workspace_member(root, &target_dir, options)?;
     }
-    .render()
-    .context("Failed to render blank Solidity interface template")?;
-    fs::write(target_dir.join(&sol_file_name), sol_content)?;
+    stage_gitignore(&target_dir, options);
+
+    options.output.info(format_args!(
+        "Successfully initialized blank contract project: {target_dir:?}\n\nNext steps:\n  cd {}\n  cargo build{}",
+        cd_path(&target_dir),
+        test_step(options)
+    ));
+    Ok(target_dir)
+}
 
-    let lib_rs_content = generate_blank_contract(use_alloc)?;
-    fs::write(
-        target_dir.join(format!("src/{}.rs", contract_name)),
-        lib_rs_content,
-    )?;
+/// Parse a Solidity file with solc and return its ABI as JSON.
+///
+/// With `with_selectors`, every function entry gets a `selector` field holding
+/// its 4-byte selector as a `0x`-prefixed hex string. `contract` picks one of
+/// several contracts in the file, see [`init_from_solidity_file`].
+pub fn abi_json(
+    sol_file: &Path,
+    contract: Option<&str>,
+    with_selectors: bool,
+) -> Result<serde_json::Value> {
+    let sol_content = fs::read(sol_file)
+        .with_context(|| format!("Failed to read Solidity file: {}", sol_file.display()))?;
+    let sol_file_name = sol_file
+        .file_name()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid file name"))?;
 
-    let build_rs_content = generate_build_rs()?;
-    fs::write(target_dir.join("build.rs"), build_rs_content)?;
+    let (metadata, _) = extract_solc_metadata_from_bytes(&sol_content, sol_file_name, contract)?;
 
-    let cargo_toml_content = generate_cargo_toml(&contract_name, &contract_name, use_alloc)?;
-    fs::write(target_dir.join("Cargo.toml"), cargo_toml_content)?;
+    let mut entries = Vec::with_capacity(metadata.output.abi.len());
+    for item in &metadata.output.abi {
+        let mut entry = serde_json::to_value(item)?;
+        if with_selectors && let AbiItem::Function { name, inputs, .. } = item {
+            let selector = compute_selector(&build_function_signature(name, inputs));
+            entry["selector"] = format!("0x{}", hex_lower(&selector)).into();
+        }
+        entries.push(entry);
+    }
 
-    println!("Successfully initialized blank contract project: {target_dir:?}");
-    println!("\nNext steps:");
-    println!("  cd {contract_name}");
-    println!("  cargo build");
-    Ok(())
+    Ok(serde_json::Value::Array(entries))
+}
+
+/// Parse an ABI exported by Hardhat, Foundry or solc.
+///
+/// Accepts either the bare JSON array or a build artifact with an `abi` field.
+fn parse_abi_json(abi_json: &str) -> Result<Vec<AbiItem>> {
+    let value: serde_json::Value =
+        serde_json::from_str(abi_json).context("ABI file is not valid JSON")?;
+    let abi = match value {
+        serde_json::Value::Object(mut artifact) => artifact
+            .remove("abi")
+            .context("Expected a JSON array or an object with an `abi` field")?,
+        abi => abi,
+    };
+    serde_json::from_value(abi).context("Failed to parse ABI")
+}
+
+/// Render a Solidity interface declaring everything in `abi`, for `sol!` to expand.
+fn solidity_interface(interface_name: &str, abi: &[AbiItem]) -> String {
+    let mut structs = Vec::new();
+    let mut members = Vec::new();
+    for item in abi {
+        match item {
+            AbiItem::Function {
+                name,
+                inputs,
+                outputs,
+                state_mutability,
+            } => {
+                let mutability = match state_mutability.as_str() {
+                    "view" | "pure" | "payable" => format!(" {state_mutability}"),
+                    _ => String::new(),
+                };
+                let returns = if outputs.is_empty() {
+                    String::new()
+                } else {
+                    format!(
+                        " returns ({})",
+                        solidity_params(outputs, name, "memory", &mut structs)
+                    )
+                };
+                members.push(format!(
+                    "function {name}({}) external{mutability}{returns};",
+                    solidity_params(inputs, name, "calldata", &mut structs)
+                ));
+            }
+            AbiItem::Event { name, inputs } => {
+                members.push(format!(
+                    "event {name}({});",
+                    solidity_params(inputs, name, "", &mut structs)
+                ));
+            }
+            AbiItem::Error { name, inputs } => {
+                members.push(format!(
+                    "error {name}({});",
+                    solidity_params(inputs, name, "", &mut structs)
+                ));
+            }
+            AbiItem::Fallback { state_mutability } => {
+                let payable = if state_mutability == "payable" {
+                    " payable"
+                } else {
+                    ""
+                };
+                members.push(format!("fallback() external{payable};"));
+            }
+            AbiItem::Receive { .. } => members.push("receive() external payable;".to_string()),
+            // Interfaces cannot declare constructors, but `deploy` decodes the structs
+            // of their arguments
+            AbiItem::Constructor { inputs } => {
+                solidity_params(inputs, "constructor", "", &mut structs);
+            }
+        }
+    }
+
+    let mut interface = format!(
+        "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\n// Generated from an ABI by cargo-pvm-contract\ninterface {interface_name} {{\n"
+    );
+    for (name, fields) in &structs {
+        interface.push_str(&format!("    struct {name} {{\n"));
+        for field in fields {
+            interface.push_str(&format!("        {field};\n"));
+        }
+        interface.push_str("    }\n\n");
+    }
+    for member in members {
+        interface.push_str(&format!("    {member}\n"));
+    }
+    interface.push_str("}\n");
+    interface
+}
+
+/// Comma-separated Solidity parameters, declaring the structs of tuples in `structs`.
+///
+/// `location` is the data location of reference types (empty for events and errors).
+fn solidity_params(
+    params: &[AbiInput],
+    owner: &str,
+    location: &str,
+    structs: &mut Vec<(String, Vec<String>)>,
+) -> String {
+    params
+        .iter()
+        .map(|param| {
+            let mut decl = solidity_type(param, &format!("{owner}_{}", param.name), structs);
+            let is_reference = param.type_name.ends_with(']')
+                || matches!(param.type_name.as_str(), "string" | "bytes" | "tuple");
+            if is_reference && !location.is_empty() {
+                decl.push(' ');
+                decl.push_str(location);
+            }
+            if param.indexed == Some(true) {
+                decl.push_str(" indexed");
+            }
+            if !param.name.is_empty() {
+                decl.push(' ');
+                decl.push_str(&param.name);
+            }
+            decl
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The Solidity type of `param`, naming tuples after their `internalType` or `name_hint`.
+fn solidity_type(
+    param: &AbiInput,
+    name_hint: &str,
+    structs: &mut Vec<(String, Vec<String>)>,
+) -> String {
+    let Some(array_suffix) = param.type_name.strip_prefix("tuple") else {
+        return param.type_name.clone();
+    };
+
+    let name = param
+        .internal_type
+        .as_deref()
+        .map(|ty| split_array_type(ty).map_or(ty, |(element, _)| element))
+        .and_then(struct_name)
+        .unwrap_or(name_hint)
+        .to_case(Case::Pascal);
+    if !structs.iter().any(|(existing, _)| *existing == name) {
+        // Reserve the name first so recursive references don't redeclare it
+        structs.push((name.clone(), Vec::new()));
+        let fields = param
+            .components
+            .iter()
+            .enumerate()
+            .map(|(idx, component)| {
+                let field_name = if component.name.is_empty() {
+                    format!("field{idx}")
+                } else {
+                    component.name.clone()
+                };
+                let field_type = solidity_type(component, &format!("{name}_{field_name}"), structs);
+                format!("{field_type} {field_name}")
+            })
+            .collect();
+        if let Some(entry) = structs.iter_mut().find(|(existing, _)| *existing == name) {
+            entry.1 = fields;
+        }
+    }
+    format!("{name}{array_suffix}")
+}
+
+/// Create a new contract project from an ABI JSON file, without invoking `solc`.
+///
+/// The project gets a Solidity interface generated from the ABI, which alloc-based
+/// contracts expand with `sol!`.
+pub fn init_from_abi_json(
+    abi_json: &str,
+    contract_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
+    validate_contract_name(contract_name)?;
+    let abi = parse_abi_json(abi_json)?;
+    init_from_abi(
+        abi,
+        &contract_name.to_case(Case::Pascal),
+        contract_name,
+        options,
+    )
+}
+
+/// Create a new contract project from a Foundry artifact (`out/<File>.sol/<Contract>.json`).
+///
+/// The ABI is taken from the artifact's `abi` field and the generated Solidity
+/// interface is named after the artifact, i.e. the Solidity contract.
+pub fn init_from_foundry_artifact(
+    artifact_path: &Path,
+    contract_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
+    validate_contract_name(contract_name)?;
+    let artifact = fs::read_to_string(artifact_path).with_context(|| {
+        format!(
+            "Failed to read Foundry artifact: {}",
+            artifact_path.display()
+        )
+    })?;
+    let abi = parse_foundry_artifact(&artifact)
+        .with_context(|| format!("Invalid Foundry artifact: {}", artifact_path.display()))?;
+    let interface_name = artifact_path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .context("Invalid artifact file name")?;
+
+    init_from_abi(abi, interface_name, contract_name, options)
+}
+
+/// The ABI of a Foundry artifact, which must have an `abi` array.
+fn parse_foundry_artifact(artifact: &str) -> Result<Vec<AbiItem>> {
+    let mut artifact: serde_json::Value =
+        serde_json::from_str(artifact).context("Artifact is not valid JSON")?;
+    let abi = artifact
+        .get_mut("abi")
+        .map(serde_json::Value::take)
+        .context("Artifact has no `abi` field; run `forge build` to produce one")?;
+    serde_json::from_value(abi).context("Failed to parse the artifact's ABI")
+}
+
+/// Write a project for `abi`, declared in a generated Solidity interface `interface_name`.
+fn init_from_abi(
+    abi: Vec<AbiItem>,
+    interface_name: &str,
+    contract_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
+    let interface_name = interface_name.to_case(Case::Pascal);
+    let sol_file_name = format!("{interface_name}.sol");
+    let sol_contents = solidity_interface(&interface_name, &abi);
+    let metadata = ContractMetadata {
+        output: MetadataOutput { abi },
+        from_interface: true,
+    };
+
+    write_contract_project(
+        &metadata,
+        &interface_name,
+        &sol_file_name,
+        sol_contents.as_bytes(),
+        None,
+        contract_name,
+        options,
+    )
 }
 
 /// Create a new contract project from a Solidity file.
-pub fn init_from_solidity_file(sol_file: &str, contract_name: &str, use_alloc: bool) -> Result<()> {
-    let sol_path = PathBuf::from(sol_file);
+///
+/// `solidity_contract` names the contract to scaffold when the file defines several;
+/// by default the first one that isn't an interface is used. Like the other `init_*`
+/// functions, this returns the absolute path of the created project directory.
+pub fn init_from_solidity_file(
+    sol_path: &Path,
+    solidity_contract: Option<&str>,
+    contract_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
+    validate_contract_name(contract_name)?;
     if !sol_path.exists() {
-        anyhow::bail!("Solidity file not found: {sol_file}");
+        anyhow::bail!("Solidity file not found: {}", sol_path.display());
     }
 
     let sol_abs_path = sol_path
         .canonicalize()
-        .with_context(|| format!("Failed to get absolute path for {sol_file}"))?;
+        .with_context(|| format!("Failed to get absolute path for {}", sol_path.display()))?;
 
     let sol_file_name = sol_path
         .file_name()
@@ -285,7 +1261,14 @@ pub fn init_from_solidity_file(sol_file: &str, contract_name: &str, use_alloc: b
     let sol_content = fs::read(&sol_abs_path)
         .with_context(|| format!("Failed to read Solidity file: {sol_abs_path:?}"))?;
 
-    init_from_example_files_inner(&sol_content, &sol_file_name, None, contract_name, use_alloc)
+    init_from_example_files_inner(
+        &sol_content,
+        &sol_file_name,
+        solidity_contract,
+        None,
+        contract_name,
+        options,
+    )
 }
 
 pub fn init_from_example_files(
@@ -293,111 +1276,165 @@ pub fn init_from_example_files(
     sol_file_name: &str,
     rust_contents: &[u8],
     contract_name: &str,
-    use_alloc: bool,
-) -> Result<()> {
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
+    validate_contract_name(contract_name)?;
     init_from_example_files_inner(
         sol_contents,
         sol_file_name,
+        None,
         Some(rust_contents),
         contract_name,
-        use_alloc,
+        options,
     )
 }
 
 fn init_from_example_files_inner(
     sol_contents: &[u8],
     sol_file_name: &str,
+    solidity_contract: Option<&str>,
     rust_contents: Option<&[u8]>,
     contract_name: &str,
-    use_alloc: bool,
-) -> Result<()> {
-    let contract_name = contract_name.to_case(Case::Kebab);
-    let sol_file_name = sol_file_name.to_string();
-
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
     log::debug!("Extracting metadata from {sol_file_name}");
     let (metadata, actual_contract_name) =
-        extract_solc_metadata_from_bytes(sol_contents, &sol_file_name)?;
+        extract_solc_metadata_from_bytes(sol_contents, sol_file_name, solidity_contract)?;
+
+    write_contract_project(
+        &metadata,
+        &actual_contract_name,
+        sol_file_name,
+        sol_contents,
+        rust_contents,
+        contract_name,
+        options,
+    )
+}
+
+/// Write a contract project for `metadata`, generating the Rust source unless given,
+/// and return its absolute path.
+fn write_contract_project(
+    metadata: &ContractMetadata,
+    actual_contract_name: &str,
+    sol_file_name: &str,
+    sol_contents: &[u8],
+    rust_contents: Option<&[u8]>,
+    contract_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<PathBuf> {
+    let contract_name = contract_name.to_case(Case::Kebab);
     let actual_contract_kebab = actual_contract_name.to_case(Case::Kebab);
 
     // Create project directory
-    let target_dir = std::env::current_dir()?.join(&contract_name);
-    if target_dir.exists() {
-        anyhow::bail!("Directory already exists: {target_dir:?}");
+    let target_dir = project_dir(&contract_name, options.workspace_root.as_deref())?;
+    let mut project = ProjectWriter::create(&target_dir, options)?;
+
+    // Copy .sol file to project
+    project.write(sol_file_name, sol_contents)?;
+
+    write_toolchain_files(&mut project, options)?;
+
+    // Generate src/{contract}.rs
+
+    let rust_path = format!("src/{}.rs", actual_contract_kebab);
+    let mut abi_consts = None;
+    let lib_rs_content = if let Some(contents) = rust_contents {
+        if options.with_tests {
+            options.output.warning(format_args!(
+                "--with-tests only applies to generated contracts; the example's Rust source is copied as is"
+            ));
+        }
+        String::from_utf8(contents.to_vec()).context("Example Rust file is not valid UTF-8")?
+    } else if options.use_alloc {
+        generate_rust_code_alloc(sol_file_name, metadata, actual_contract_name, options)?
+    } else {
+        let (code, constants) =
+            generate_rust_code_no_alloc(metadata, actual_contract_name, options)?;
+        abi_consts = Some(AbiConsts {
+            sol_file: sol_file_name.into(),
+            contract: actual_contract_name.to_string(),
+            source: rust_path.clone().into(),
+            constants,
+        });
+        code
+    };
+    project.write(&rust_path, lib_rs_content)?;
+    // Lets the build check the constants against the .sol file, see `verify-abi-consts`
+    if let Some(abi_consts) = &abi_consts {
+        let json = serde_json::to_string_pretty(abi_consts)?;
+        project.write(ABI_CONSTS_FILE, json + "\n")?;
     }
-    fs::create_dir(&target_dir)
-        .with_context(|| format!("Failed to create directory: {target_dir:?}"))?;
 
-    let (target_json_path, target_json_name) = resolve_target_json()?;
-    let target_json_dest = target_dir.join(target_json_name);
-    fs::copy(&target_json_path, &target_json_dest).with_context(|| {
-        format!(
-            "Failed to copy target JSON from {} to {}",
-            target_json_path.display(),
-            target_json_dest.display()
-        )
-    })?;
+    let build_rs_content = generate_build_rs(options.bits)?;
+    project.write("build.rs", build_rs_content)?;
 
-    let target_json_name = target_json_dest
-        .file_name()
-        .and_then(|name| name.to_str())
-        .ok_or_else(|| anyhow::anyhow!("Target JSON path is missing a file name"))?;
+    // Create Cargo.toml
+    let cargo_toml_content = generate_cargo_toml(
+        &contract_name,
+        &actual_contract_kebab,
+        abi_consts.is_some(),
+        options,
+    )?;
+    project.write("Cargo.toml", cargo_toml_content)?;
 
-    // Copy .sol file to project
-    let target_sol_path = target_dir.join(&sol_file_name);
-    fs::write(&target_sol_path, sol_contents)
-        .with_context(|| format!("Failed to write {sol_file_name} to {target_sol_path:?}"))?;
-
-    // Create .cargo directory and config
-    let cargo_config_dir = target_dir.join(".cargo");
-    fs::create_dir(&cargo_config_dir)?;
-    fs::write(
-        cargo_config_dir.join("config.toml"),
-        format!(
-            "[build]\n target = \"{}\"\n\n[unstable]\n build-std = [\"core\", \"alloc\"]\n\n[env]\n RUSTC_BOOTSTRAP = \"1\"\n",
-            target_json_name
-        ),
+    let readme = generate_readme(
+        &contract_name,
+        &actual_contract_kebab,
+        sol_file_name,
+        options,
     )?;
+    project.write("README.md", readme)?;
+    project.finish()?;
+    if let Some(root) = &options.workspace_root {
+        add_workspace_member(root, &target_dir, options)?;
+    }
+    stage_gitignore(&target_dir, options);
+
+    options.output.info(format_args!(
+        "Successfully initialized contract project from {sol_file_name}: {target_dir:?}\n\nNext steps:\n  cd {}\n  cargo build{}",
+        cd_path(&target_dir),
+        test_step(options)
+    ));
+    Ok(target_dir)
+}
 
-    // Create .gitignore
-    fs::write(target_dir.join(".gitignore"), "/target\n*.polkavm\n")?;
-    fs::write(
-        target_dir.join("rust-toolchain.toml"),
-        "[toolchain]\nchannel = \"nightly\"\n",
-    )?;
-    // Generate src/{contract}.rs
-    fs::create_dir(target_dir.join("src"))?;
+/// Environment variable pointing at the `solc` executable (default: `solc` from `PATH`).
+const SOLC_PATH_ENV: &str = "SOLC_PATH";
 
-    let lib_rs_content = if let Some(contents) = rust_contents {
-        String::from_utf8(contents.to_vec()).context("Example Rust file is not valid UTF-8")?
-    } else if use_alloc {
-        generate_rust_code_alloc(&sol_file_name, &metadata, &actual_contract_name)?
-    } else {
-        generate_rust_code_no_alloc(&metadata, &actual_contract_name)?
-    };
-    fs::write(
-        target_dir.join(format!("src/{}.rs", actual_contract_kebab)),
-        lib_rs_content,
-    )?;
+/// Oldest solc whose metadata JSON is known to deserialize correctly.
+const MIN_SOLC_VERSION: (u32, u32, u32) = (0, 8, 0);
 
-    let build_rs_content = generate_build_rs()?;
-    fs::write(target_dir.join("build.rs"), build_rs_content)?;
+/// Warn if `solc --version` reports a compiler older than [`MIN_SOLC_VERSION`].
+fn warn_on_old_solc(solc: &std::ffi::OsStr) {
+    let Ok(output) = Command::new(solc).arg("--version").output() else {
+        return;
+    };
+    if let Some(version) = parse_solc_version(&String::from_utf8_lossy(&output.stdout))
+        && version < MIN_SOLC_VERSION
+    {
+        let (major, minor, patch) = version;
+        eprintln!(
+            "warning: solc {major}.{minor}.{patch} is older than 0.8.0; its metadata may not be parsed correctly"
+        );
+    }
+}
 
-    // Create Cargo.toml
-    let cargo_toml_content =
-        generate_cargo_toml(&contract_name, &actual_contract_kebab, use_alloc)?;
-    fs::write(target_dir.join("Cargo.toml"), cargo_toml_content)?;
-
-    println!("Successfully initialized contract project from {sol_file_name}: {target_dir:?}");
-    println!("\nNext steps:");
-    println!("  cd {contract_name}");
-    println!("  cargo build");
-    Ok(())
+/// The version in `solc --version` output, e.g. `Version: 0.8.26+commit.8a97fa7a.Linux.g++`.
+fn parse_solc_version(output: &str) -> Option<(u32, u32, u32)> {
+    let version = output
+        .lines()
+        .find_map(|line| line.strip_prefix("Version: "))?;
+    let mut parts = version.split(['.', '+', '-']);
+    let mut next = || parts.next()?.parse().ok();
+    Some((next()?, next()?, next()?))
 }
 
 /// Internal helpers for template generation.
 fn extract_solc_metadata_from_bytes(
     sol_contents: &[u8],
     sol_file_name: &str,
+    contract: Option<&str>,
 ) -> Result<(ContractMetadata, String)> {
     let sol_content =
         String::from_utf8(sol_contents.to_vec()).context("Solidity file is not valid UTF-8")?;
@@ -412,6 +1449,7 @@ fn extract_solc_metadata_from_bytes(
         "settings": {
             "outputSelection": {
                 "*": {
+                    "": ["ast"],
                     "*": ["metadata"]
                 }
             }
@@ -420,13 +1458,25 @@ fn extract_solc_metadata_from_bytes(
 
     let solc_input_str = serde_json::to_string(&solc_input)?;
 
-    let mut child = Command::new("solc")
+    let solc_path = std::env::var_os(SOLC_PATH_ENV);
+    let solc = solc_path.as_deref().unwrap_or("solc".as_ref());
+    let mut child = Command::new(solc)
         .arg("--standard-json")
         .stdin(std::process::Stdio::piped())
         .stdout(std::process::Stdio::piped())
         .stderr(std::process::Stdio::piped())
         .spawn()
-        .context("Failed to spawn solc. Make sure solc is installed and in PATH.")?;
+        .map_err(|err| match &solc_path {
+            None if err.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::Error::new(PvmBuildError::SolcNotFound)
+            }
+            None => anyhow::Error::new(err).context("Failed to spawn solc"),
+            Some(path) => anyhow::Error::new(err).context(format!(
+                "Failed to spawn solc from {SOLC_PATH_ENV}={}",
+                path.display()
+            )),
+        })?;
+    warn_on_old_solc(solc);
 
     child
         .stdin
@@ -439,8 +1489,10 @@ fn extract_solc_metadata_from_bytes(
         .context("Failed to wait for solc")?;
 
     if !output_result.status.success() {
-        let stderr = String::from_utf8_lossy(&output_result.stderr);
-        anyhow::bail!("solc failed: {stderr}");
+        return Err(PvmBuildError::SolcFailed {
+            stderr: String::from_utf8_lossy(&output_result.stderr).into_owned(),
+        }
+        .into());
     }
 
     log::debug!(
@@ -456,16 +1508,18 @@ fn extract_solc_metadata_from_bytes(
             )
         })?;
 
-    // Extract metadata from the first contract
     let contracts_for_file = solc_output
         .contracts
         .get(sol_file_name)
         .ok_or_else(|| anyhow::anyhow!("No contract found in solc output"))?;
+    let ast_nodes = solc_output
+        .sources
+        .get(sol_file_name)
+        .map(|source| source.ast.nodes.as_slice())
+        .unwrap_or_default();
 
-    let (contract_name, contract_info) = contracts_for_file
-        .iter()
-        .next()
-        .ok_or_else(|| anyhow::anyhow!("No contract found in solc output"))?;
+    let (contract_name, contract_info) =
+        select_contract(contracts_for_file, ast_nodes, contract, sol_file_name)?;
 
     let metadata: ContractMetadata = serde_json::from_str(&contract_info.metadata)
         .context("Failed to parse contract metadata")?;
@@ -473,65 +1527,881 @@ fn extract_solc_metadata_from_bytes(
     Ok((metadata, contract_name.clone()))
 }
 
-fn generate_blank_contract(use_alloc: bool) -> Result<String> {
-    if use_alloc {
-        ContractBlankAllocTemplate
-            .render()
-            .context("Failed to render blank alloc contract template")
+/// Pick the contract to scaffold: `requested` if given, otherwise the first
+/// contract in source order that isn't an interface, falling back to the first one.
+fn select_contract<'a>(
+    contracts: &'a BTreeMap<String, ContractInfo>,
+    ast_nodes: &[AstNode],
+    requested: Option<&str>,
+    sol_file_name: &str,
+) -> Result<(&'a String, &'a ContractInfo)> {
+    if let Some(name) = requested {
+        return contracts.get_key_value(name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Contract `{name}` not found in {sol_file_name}; available contracts: {}",
+                contracts.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        });
+    }
+
+    // BTreeMap order is alphabetical, so prefer the AST for source order
+    let definitions: Vec<_> = ast_nodes
+        .iter()
+        .filter(|node| node.node_type == "ContractDefinition")
+        .filter_map(|node| {
+            let name = node.name.as_deref()?;
+            let entry = contracts.get_key_value(name)?;
+            Some((entry, node.contract_kind.as_deref()))
+        })
+        .collect();
+    definitions
+        .iter()
+        .find(|(_, kind)| *kind != Some("interface"))
+        .or_else(|| definitions.first())
+        .map(|(entry, _)| *entry)
+        .or_else(|| contracts.iter().next())
+        .ok_or_else(|| anyhow::anyhow!("No contract found in solc output"))
+}
+
+fn generate_blank_contract(use_alloc: bool, tests: bool) -> Result<String> {
+    let mut code = if use_alloc {
+        render(
+            &ContractBlankAllocTemplate { tests },
+            "blank alloc contract",
+        )?
+    } else {
+        render(&ContractBlankTemplate { tests }, "blank contract")?
+    };
+    if tests {
+        let template = ContractTestsTemplate {
+            mock: use_alloc,
+            dispatches: false,
+            functions: Vec::new(),
+            reentrant_call: None,
+        };
+        code.push_str(&render(&template, "contract tests")?);
+    }
+    Ok(code)
+}
+
+/// The next-steps line for running the tests generated by `--with-tests`.
+fn test_step(options: &ScaffoldOptions) -> &'static str {
+    if options.with_tests {
+        "\n  cargo test-host"
     } else {
-        ContractBlankTemplate
-            .render()
-            .context("Failed to render blank contract template")
+        ""
     }
 }
 
-fn generate_build_rs() -> Result<String> {
-    BuildRsTemplate
-        .render()
-        .context("Failed to render build.rs template")
+/// Snake-case test names for `names`, numbered when overloads would collide.
+fn test_names<'a>(names: impl IntoIterator<Item = &'a str>) -> Vec<String> {
+    // Items of the generated test module
+    let mut taken = vec![
+        "call_with".to_string(),
+        "mock".to_string(),
+        "reentrant_call_reverts".to_string(),
+        "unknown_selector_traps".to_string(),
+    ];
+    names
+        .into_iter()
+        .map(|name| {
+            let base = rust_ident(name, || "function".to_string());
+            let mut test_name = base.clone();
+            let mut n = 2;
+            while taken.contains(&test_name) {
+                test_name = format!("{base}_{n}");
+                n += 1;
+            }
+            taken.push(test_name.clone());
+            test_name
+        })
+        .collect()
+}
+
+/// The project's `README.md`; the binary is named after the project.
+fn generate_readme(
+    contract_name: &str,
+    bin_source: &str,
+    sol_file_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<String> {
+    render(
+        &ReadmeTemplate {
+            contract_name,
+            bin_source,
+            sol_file_name,
+            use_alloc: options.use_alloc,
+            with_tests: options.with_tests,
+        },
+        "README.md",
+    )
+}
+
+fn generate_build_rs(bits: Bits) -> Result<String> {
+    render(
+        &BuildRsTemplate {
+            is_32_bit: bits == Bits::B32,
+        },
+        "build.rs",
+    )
 }
 
 fn generate_rust_code_alloc(
     sol_file_name: &str,
     metadata: &ContractMetadata,
     contract_name: &str,
+    options: &ScaffoldOptions,
 ) -> Result<String> {
     let contract_name_pascal = contract_name.to_case(Case::Pascal);
 
-    let functions: Vec<AllocFunctionInfo> = metadata
-        .output
-        .abi
-        .iter()
-        .filter_map(|item| match item {
-            AbiItem::Function { name, .. } => Some(AllocFunctionInfo {
-                name: name.clone(),
-                name_snake: name.to_case(Case::Snake),
-                call_type: format!("{contract_name_pascal}::{name}Call"),
-            }),
+    let functions: Vec<AllocFunctionInfo> = abi_functions(&metadata.output.abi)
+        .into_iter()
+        .filter_map(|(item, item_name, _)| match item {
+            AbiItem::Function {
+                name,
+                inputs,
+                state_mutability,
+                ..
+            } => {
+                let name_snake = item_name.to_case(Case::Snake);
+                let array_params = inputs
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, input)| split_array_type(&input.type_name).is_some())
+                    .map(|(idx, input)| {
+                        let param_name = rust_ident(&input.name, || format!("param_{idx}"));
+                        // `sol!` keeps Solidity parameter names and numbers unnamed ones
+                        let field = if input.name.is_empty() {
+                            format!("_{idx}")
+                        } else {
+                            input.name.clone()
+                        };
+                        match sol_data_type(input) {
+                            Some(sol_type) => format!(
+                                "let {param_name}: <{sol_type} as SolType>::RustType = _{name_snake}_call.{field};"
+                            ),
+                            None => format!("let {param_name} = _{name_snake}_call.{field};"),
+                        }
+                    })
+                    .collect();
+                Some(AllocFunctionInfo {
+                    name: name.clone(),
+                    name_snake,
+                    call_type: format!("{contract_name_pascal}::{item_name}Call"),
+                    guarded: options.reentrancy_guard && is_state_changing(state_mutability),
+                    is_payable: state_mutability == "payable",
+                    read_only: !is_state_changing(state_mutability),
+                    array_params,
+                })
+            }
             _ => None,
         })
         .collect();
 
+    let constructor = constructor_inputs(&metadata.output.abi)
+        .map(|inputs| alloc_constructor(inputs, &contract_name_pascal, metadata.from_interface));
+    let uses_sol_constructor = constructor.as_ref().is_some_and(|constructor| {
+        constructor
+            .decode_stmts
+            .iter()
+            .any(|stmt| stmt.contains("SolConstructor"))
+    });
+    let uses_sol_data = constructor.is_some()
+        || functions.iter().any(|function| {
+            function
+                .array_params
+                .iter()
+                .any(|line| line.contains("SolType"))
+        });
+    let tests = options.with_tests.then(|| {
+        let names = test_names(functions.iter().map(|function| function.name.as_str()));
+        functions
+            .iter()
+            .zip(names)
+            .map(|(function, test_name)| FunctionTest {
+                name: function.name.clone(),
+                test_name,
+                call_type: Some(function.call_type.clone()),
+                selector_const: String::new(),
+                min_call_data_len: 0,
+                guarded: function.guarded,
+            })
+            .collect()
+    });
     let template = ContractAllocTemplate {
         sol_file_name,
         functions,
+        reentrancy_guard: reentrancy_guard_consts(options.reentrancy_guard),
+        constructor,
+        uses_sol_data,
+        uses_sol_constructor,
+        tests: tests.is_some(),
+    };
+
+    let mut code = render(&template, "alloc")?;
+    if let Some(functions) = tests {
+        code.push_str(&contract_tests(functions)?);
+    }
+    Ok(code)
+}
+
+/// `deploy` statements decoding the constructor arguments and storing each under its key.
+///
+/// Contracts compiled from Solidity decode through the `constructorCall` type `sol!`
+/// generates for them. Interfaces generated from an ABI have none, so their arguments
+/// are decoded as a tuple of `sol_data` types instead. Static values are stored as
+/// their ABI encoding, `bytes` and `string` as their contents.
+fn alloc_constructor(
+    inputs: &[AbiInput],
+    contract_name_pascal: &str,
+    from_interface: bool,
+) -> ConstructorInfo {
+    let names: Vec<String> = inputs
+        .iter()
+        .enumerate()
+        .map(|(idx, input)| rust_ident(&input.name, || format!("param_{idx}")))
+        .collect();
+    let sol_types: Vec<Option<String>> = inputs
+        .iter()
+        .map(|input| {
+            if from_interface {
+                interface_sol_data_type(input, contract_name_pascal)
+            } else {
+                sol_data_type(input)
+            }
+        })
+        .collect();
+
+    let (decode_stmts, values) = if !from_interface {
+        let call_type = format!("{contract_name_pascal}::constructorCall");
+        let decode_stmts = vec![
+            "let params =".to_string(),
+            format!(
+                "    <<{call_type} as SolConstructor>::Parameters<'_> as SolType>::abi_decode_params("
+            ),
+            "        &call_data, true,".to_string(),
+            "    )".to_string(),
+            "    .expect(\"Failed to decode constructor arguments\");".to_string(),
+            format!("let constructor = {call_type}::new(params);"),
+        ];
+        // `sol!` keeps Solidity parameter names and numbers unnamed ones
+        let values = inputs
+            .iter()
+            .enumerate()
+            .map(|(idx, input)| {
+                Some(if input.name.is_empty() {
+                    format!("constructor._{idx}")
+                } else {
+                    format!("constructor.{}", input.name)
+                })
+            })
+            .collect();
+        (decode_stmts, values)
+    } else if let Some(sol_types) = sol_types.iter().cloned().collect::<Option<Vec<_>>>() {
+        let pattern = if let [name] = names.as_slice() {
+            format!("({name},)")
+        } else {
+            format!("({})", names.join(", "))
+        };
+        let mut decode_stmts = vec![format!("let {pattern} = <(")];
+        decode_stmts.extend(sol_types.iter().map(|sol_type| format!("    {sol_type},")));
+        decode_stmts.push(") as SolType>::abi_decode_params(&call_data, true)".to_string());
+        decode_stmts.push(".expect(\"Failed to decode constructor arguments\");".to_string());
+        (decode_stmts, names.iter().cloned().map(Some).collect())
+    } else {
+        (Vec::new(), vec![None; inputs.len()])
+    };
+
+    let args = inputs
+        .iter()
+        .zip(&names)
+        .zip(values.into_iter().zip(sol_types))
+        .enumerate()
+        .map(|(idx, ((input, name), (value, sol_type)))| {
+            let key = constructor_key_const(name);
+            let value = match value {
+                None => None,
+                Some(value) if input.type_name == "bytes" => Some(format!("&{value}")),
+                Some(value) if input.type_name == "string" => Some(format!("{value}.as_bytes()")),
+                Some(value) => sol_type
+                    .map(|sol_type| format!("&<{sol_type} as SolType>::abi_encode(&{value})")),
+            };
+            let stmt = match value {
+                Some(value) => {
+                    format!("api::set_storage(StorageFlags::empty(), &{key}, {value});")
+                }
+                None => format!(
+                    "// TODO: decode and store {name} of type {}",
+                    input.type_name
+                ),
+            };
+            constructor_arg(idx, input, vec![stmt])
+        })
+        .collect();
+
+    ConstructorInfo {
+        min_call_data_len: 0,
+        decode_stmts,
+        args,
+    }
+}
+
+/// Render the test module for a contract dispatching to `functions`.
+fn contract_tests(functions: Vec<FunctionTest>) -> Result<String> {
+    let reentrant_call = functions.iter().find(|test| test.guarded).cloned();
+    let template = ContractTestsTemplate {
+        mock: true,
+        dispatches: true,
+        functions,
+        reentrant_call,
+    };
+    render(&template, "contract tests")
+}
+
+/// Largest dynamic array a no-alloc contract decodes onto the stack.
+const MAX_DYNAMIC_ARRAY_LEN: usize = 16;
+
+/// The alloy `sol_data` type decoding `input`, e.g. `sol_data::Array<sol_data::Uint<256>>`.
+///
+/// Tuples map to the struct `sol!` generates from their `internalType`; `None` for
+/// tuples without one.
+fn sol_data_type(input: &AbiInput) -> Option<String> {
+    if let Some((element, len)) = split_array_type(&input.type_name) {
+        let element = AbiInput {
+            name: String::new(),
+            type_name: element.to_string(),
+            internal_type: input.internal_type.as_deref().map(|ty| {
+                split_array_type(ty)
+                    .map_or(ty, |(element, _)| element)
+                    .to_string()
+            }),
+            indexed: None,
+            components: input.components.clone(),
+        };
+        let element = sol_data_type(&element)?;
+        return Some(match len {
+            Some(len) => format!("sol_data::FixedArray<{element}, {len}>"),
+            None => format!("sol_data::Array<{element}>"),
+        });
+    }
+
+    let type_name = input.type_name.as_str();
+    let sol_type = match type_name {
+        "address" => "sol_data::Address".to_string(),
+        "bool" => "sol_data::Bool".to_string(),
+        "string" => "sol_data::String".to_string(),
+        "bytes" => "sol_data::Bytes".to_string(),
+        "uint" => "sol_data::Uint<256>".to_string(),
+        "int" => "sol_data::Int<256>".to_string(),
+        "tuple" => input
+            .internal_type
+            .as_deref()?
+            .strip_prefix("struct ")?
+            .replace('.', "::"),
+        _ => {
+            if let Some(bits) = type_name.strip_prefix("uint") {
+                format!("sol_data::Uint<{bits}>")
+            } else if let Some(bits) = type_name.strip_prefix("int") {
+                format!("sol_data::Int<{bits}>")
+            } else if let Some(len) = type_name.strip_prefix("bytes") {
+                format!("sol_data::FixedBytes<{len}>")
+            } else {
+                return None;
+            }
+        }
+    };
+    Some(sol_type)
+}
+
+/// [`sol_data_type`] for a type of the interface generated from an ABI, which declares
+/// the structs of every tuple itself.
+fn interface_sol_data_type(input: &AbiInput, interface_name: &str) -> Option<String> {
+    let mut input = input.clone();
+    if let Some(array_suffix) = input.type_name.strip_prefix("tuple") {
+        let internal_type = input.internal_type.as_deref()?;
+        let element = internal_type.split('[').next().unwrap_or(internal_type);
+        let name = struct_name(element)?.to_case(Case::Pascal);
+        input.internal_type = Some(format!("struct {interface_name}.{name}{array_suffix}"));
+    }
+    sol_data_type(&input)
+}
+
+/// Collects the structs generated for the tuples of a contract's ABI.
+#[derive(Default)]
+struct TupleCodegen {
+    structs: Vec<TupleStruct>,
+    /// Whether any generated code reads a dynamic tuple's offset with `abi_offset`.
+    uses_abi_offset: bool,
+    /// Whether any generated code copies `bytes` or `string` into a `MAX_BYTES_LEN` buffer.
+    uses_bytes_buffer: bool,
+    /// Whether any generated code converts an `address` with `decode_address` or `encode_address`.
+    uses_address: bool,
+    /// Whether any generated code converts a `bool` with `decode_bool` or `encode_bool`.
+    uses_bool: bool,
+}
+
+impl TupleCodegen {
+    /// Codec for the value of `input` whose head starts at `offset` in `data`.
+    ///
+    /// `value` is the expression encoded into `out`, e.g. `self.amount`; `name_hint`
+    /// names the struct of a tuple that has no `internalType`.
+    fn value_codec(
+        &mut self,
+        input: &AbiInput,
+        data: &str,
+        offset: usize,
+        value: &str,
+        name_hint: &str,
+    ) -> ValueCodec {
+        let end = offset + 32;
+        let raw = |len: usize, rust_type: String| ValueCodec {
+            param_type: rust_type.clone(),
+            rust_type,
+            decode_expr: format!("{data}[{}..{}].try_into().unwrap()", end - len, end),
+            encode_stmts: vec![format!(
+                "out[{}..{}].copy_from_slice(&{value});",
+                end - len,
+                end
+            )],
+        };
+
+        if input.type_name == "tuple" {
+            let name = self.tuple_struct(input, name_hint);
+            if is_dynamic_type(&input.type_name, &input.components) {
+                self.uses_abi_offset = true;
+                return ValueCodec {
+                    param_type: format!("&{name}"),
+                    decode_expr: format!("{name}::decode(&{data}[abi_offset({data}, {offset})..])"),
+                    rust_type: name,
+                    encode_stmts: Vec::new(),
+                };
+            }
+            let range = if offset == 0 {
+                format!("..{name}::ENCODED_LEN")
+            } else {
+                format!("{offset}..{offset} + {name}::ENCODED_LEN")
+            };
+            return ValueCodec {
+                param_type: format!("&{name}"),
+                decode_expr: format!("{name}::decode(&{data}[{range}])"),
+                encode_stmts: vec![format!("{value}.encode(&mut out[{range}]);")],
+                rust_type: name,
+            };
+        }
+
+        if input.type_name == "address" {
+            // Addresses are right-aligned in their word, behind 12 zero bytes
+            self.uses_address = true;
+            return ValueCodec {
+                rust_type: "[u8; 20]".to_string(),
+                param_type: "[u8; 20]".to_string(),
+                decode_expr: format!(
+                    "decode_address({data}[{offset}..{end}].try_into().unwrap()).expect(\"Invalid address\")"
+                ),
+                encode_stmts: vec![format!(
+                    "out[{offset}..{end}].copy_from_slice(&encode_address({value}));"
+                )],
+            };
+        }
+
+        if input.type_name == "bool" {
+            self.uses_bool = true;
+            return ValueCodec {
+                rust_type: "bool".to_string(),
+                param_type: "bool".to_string(),
+                decode_expr: format!("decode_bool({data}[{offset}..{end}].try_into().unwrap())"),
+                encode_stmts: vec![format!(
+                    "out[{offset}..{end}].copy_from_slice(&encode_bool({value}));"
+                )],
+            };
+        }
+
+        if let Some((signed, bits)) = native_int_bits(&input.type_name) {
+            let len = bits / 8;
+            let rust_type = format!("{}{bits}", if signed { 'i' } else { 'u' });
+            let mut encode_stmts = Vec::new();
+            if signed {
+                // Negative values are sign-extended to the full word
+                encode_stmts.push(format!(
+                    "out[{offset}..{end}].fill(if {value} < 0 {{ 0xff }} else {{ 0 }});"
+                ));
+            }
+            encode_stmts.push(format!(
+                "out[{}..{end}].copy_from_slice(&{value}.to_be_bytes());",
+                end - len
+            ));
+            return ValueCodec {
+                param_type: rust_type.clone(),
+                decode_expr: format!(
+                    "{rust_type}::from_be_bytes({data}[{}..{end}].try_into().unwrap())",
+                    end - len
+                ),
+                rust_type,
+                encode_stmts,
+            };
+        }
+
+        if let Some(len) = fixed_bytes_len(&input.type_name) {
+            // Fixed-size byte arrays are left-aligned
+            return ValueCodec {
+                rust_type: format!("[u8; {len}]"),
+                param_type: format!("[u8; {len}]"),
+                decode_expr: format!("{data}[{offset}..{}].try_into().unwrap()", offset + len),
+                encode_stmts: vec![format!(
+                    "out[{offset}..{}].copy_from_slice(&{value});",
+                    offset + len
+                )],
+            };
+        }
+
+        // Anything else is kept as its raw head: the whole value for static types
+        // like `uint256`, the offset word for dynamic ones like `string`
+        let len = abi_head_size(&input.type_name, &input.components);
+        let mut codec = raw(len, format!("[u8; {len}]"));
+        codec.decode_expr = format!("{data}[{offset}..{}].try_into().unwrap()", offset + len);
+        if is_dynamic_type(&input.type_name, &input.components) {
+            codec.encode_stmts.clear();
+        } else {
+            codec.encode_stmts = vec![format!(
+                "out[{offset}..{}].copy_from_slice(&{value});",
+                offset + len
+            )];
+        }
+        codec
+    }
+
+    /// Generate the struct for a tuple (once per name) and return its name.
+    fn tuple_struct(&mut self, input: &AbiInput, name_hint: &str) -> String {
+        let name = input
+            .internal_type
+            .as_deref()
+            .and_then(struct_name)
+            .unwrap_or(name_hint)
+            .to_case(Case::Pascal);
+        if self.structs.iter().any(|existing| existing.name == name) {
+            return name;
+        }
+
+        let mut fields = Vec::with_capacity(input.components.len());
+        let mut offset = 0;
+        for (idx, component) in input.components.iter().enumerate() {
+            let field_name = rust_ident(&component.name, || format!("field_{idx}"));
+            let codec = self.value_codec(
+                component,
+                "data",
+                offset,
+                &format!("self.{field_name}"),
+                &format!("{name}_{field_name}"),
+            );
+            offset += abi_head_size(&component.type_name, &component.components);
+            fields.push(TupleField {
+                name: field_name,
+                rust_type: codec.rust_type,
+                decode_expr: codec.decode_expr,
+                encode_stmts: codec.encode_stmts,
+            });
+        }
+
+        self.structs.push(TupleStruct {
+            name: name.clone(),
+            signature: canonical_type(input),
+            encoded_len: offset,
+            encodable: !is_dynamic_type(&input.type_name, &input.components),
+            fields,
+        });
+        name
+    }
+
+    /// Statements copying the `bytes` or `string` value `input`, whose offset word is
+    /// at `offset` in `args`, into a `[u8; MAX_BYTES_LEN]` stack buffer.
+    fn bytes_decode(
+        &mut self,
+        input: &AbiInput,
+        offset: usize,
+        param_name: &str,
+    ) -> Option<Vec<String>> {
+        if !matches!(input.type_name.as_str(), "bytes" | "string") {
+            return None;
+        }
+
+        self.uses_abi_offset = true;
+        self.uses_bytes_buffer = true;
+        let mut lines = vec![
+            format!("let {param_name}_at = abi_offset(args, {offset});"),
+            format!("let {param_name}_len = abi_offset(args, {param_name}_at);"),
+            format!("if {param_name}_len > MAX_BYTES_LEN {{"),
+            format!("    panic!(\"{param_name} is too long\");"),
+            "}".to_string(),
+            format!("let mut {param_name}_buf = [0u8; MAX_BYTES_LEN];"),
+            format!(
+                "{param_name}_buf[..{param_name}_len].copy_from_slice(&args[{param_name}_at + 32..{param_name}_at + 32 + {param_name}_len]);"
+            ),
+        ];
+        if input.type_name == "string" {
+            lines.push(format!(
+                "let {param_name} = core::str::from_utf8(&{param_name}_buf[..{param_name}_len]).expect(\"{param_name} is not valid UTF-8\");"
+            ));
+        } else {
+            lines.push(format!(
+                "let {param_name} = &{param_name}_buf[..{param_name}_len];"
+            ));
+        }
+        Some(lines)
+    }
+
+    /// Statements decoding the array `input` whose head starts at `offset` in `args`.
+    ///
+    /// Arrays are decoded into a stack buffer: fixed-size arrays into `[T; N]`, dynamic
+    /// arrays into `[T; MAX_DYNAMIC_ARRAY_LEN]` sliced to their length. Returns `None`
+    /// for arrays of dynamic elements.
+    fn array_decode(
+        &mut self,
+        input: &AbiInput,
+        offset: usize,
+        param_name: &str,
+        name_hint: &str,
+    ) -> Option<Vec<String>> {
+        let (element, len) = split_array_type(&input.type_name)?;
+        if is_dynamic_type(element, &input.components) {
+            return None;
+        }
+
+        let element = AbiInput {
+            name: String::new(),
+            type_name: element.to_string(),
+            internal_type: input.internal_type.as_deref().map(|ty| {
+                split_array_type(ty)
+                    .map_or(ty, |(element, _)| element)
+                    .to_string()
+            }),
+            indexed: None,
+            components: input.components.clone(),
+        };
+        let element_size = abi_head_size(&element.type_name, &element.components);
+        let codec = self.value_codec(&element, "data", 0, "", name_hint);
+        let rust_type = &codec.rust_type;
+        let decode_expr = &codec.decode_expr;
+
+        let Some(len) = len else {
+            self.uses_abi_offset = true;
+            return Some(vec![
+                format!("let {param_name}_at = abi_offset(args, {offset});"),
+                format!("let {param_name}_len = abi_offset(args, {param_name}_at);"),
+                format!("if {param_name}_len > {MAX_DYNAMIC_ARRAY_LEN} {{"),
+                format!("    panic!(\"Too many {param_name} elements\");"),
+                "}".to_string(),
+                format!(
+                    "let {param_name}: [{rust_type}; {MAX_DYNAMIC_ARRAY_LEN}] = core::array::from_fn(|i| {{"
+                ),
+                format!("    let data = if i < {param_name}_len {{"),
+                format!("        &args[{param_name}_at + 32 + i * {element_size}..]"),
+                "    } else {".to_string(),
+                format!("        &[0u8; {element_size}][..]"),
+                "    };".to_string(),
+                format!("    {decode_expr}"),
+                "});".to_string(),
+                format!("let {param_name} = &{param_name}[..{param_name}_len];"),
+            ]);
+        };
+
+        let start = if offset == 0 {
+            format!("i * {element_size}")
+        } else {
+            format!("{offset} + i * {element_size}")
+        };
+        Some(vec![
+            format!("let {param_name}: [{rust_type}; {len}] = core::array::from_fn(|i| {{"),
+            format!("    let data = &args[{start}..];"),
+            format!("    {decode_expr}"),
+            "});".to_string(),
+        ])
+    }
+
+    /// `encode_<function>_return` for functions returning tuples, if all outputs are static.
+    fn return_encoder(&mut self, function: &str, outputs: &[AbiInput]) -> Option<ReturnEncoder> {
+        if !outputs.iter().any(|output| output.type_name == "tuple")
+            || outputs
+                .iter()
+                .any(|output| is_dynamic_type(&output.type_name, &output.components))
+        {
+            return None;
+        }
+
+        let mut params = Vec::with_capacity(outputs.len());
+        let mut encode_stmts = Vec::new();
+        let mut offset = 0;
+        for (idx, output) in outputs.iter().enumerate() {
+            let param_name = rust_ident(&output.name, || format!("value_{idx}"));
+            let codec = self.value_codec(
+                output,
+                "data",
+                offset,
+                &param_name,
+                &format!("{function}_{param_name}"),
+            );
+            offset += abi_head_size(&output.type_name, &output.components);
+            params.push(format!("{param_name}: {}", codec.param_type));
+            encode_stmts.extend(codec.encode_stmts);
+        }
+
+        Some(ReturnEncoder {
+            fn_name: format!("encode_{}_return", function.to_case(Case::Snake)),
+            function: function.to_string(),
+            params: params.join(", "),
+            encoded_len: offset,
+            encode_stmts,
+        })
+    }
+}
+
+/// Struct name from an `internalType` like `struct IOrders.Order`.
+fn struct_name(internal_type: &str) -> Option<&str> {
+    let path = internal_type.strip_prefix("struct ")?;
+    path.rsplit('.').next().filter(|name| !name.is_empty())
+}
+
+/// Length of `bytes1` through `bytes32`.
+fn fixed_bytes_len(type_name: &str) -> Option<usize> {
+    type_name
+        .strip_prefix("bytes")
+        .and_then(|len| len.parse::<usize>().ok())
+        .filter(|len| (1..=32).contains(len))
+}
+
+/// Signedness and width of integer types that map onto a Rust integer.
+fn native_int_bits(type_name: &str) -> Option<(bool, usize)> {
+    let (signed, bits) = match type_name.strip_prefix("uint") {
+        Some(bits) => (false, bits),
+        None => (true, type_name.strip_prefix("int")?),
     };
+    let bits = bits.parse().ok()?;
+    matches!(bits, 8 | 16 | 32 | 64 | 128).then_some((signed, bits))
+}
+
+/// A snake_case Rust identifier for an ABI name, or `fallback` for unnamed values.
+fn rust_ident(name: &str, fallback: impl FnOnce() -> String) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while", "yield",
+    ];
+
+    if name.is_empty() {
+        return fallback();
+    }
+    let ident = name.to_case(Case::Snake);
+    if KEYWORDS.contains(&ident.as_str()) {
+        format!("{ident}_")
+    } else {
+        ident
+    }
+}
+
+/// `deploy` statements decoding the constructor arguments from `args` with the same
+/// helpers as `call` and storing each under its key.
+///
+/// Static values are stored as their ABI encoding, `bytes` and `string` as their
+/// contents; dynamic arrays and tuples are left as TODOs.
+fn no_alloc_constructor(tuples: &mut TupleCodegen, inputs: &[AbiInput]) -> ConstructorInfo {
+    let mut args = Vec::with_capacity(inputs.len());
+    let mut head_offset = 0;
+    for (idx, input) in inputs.iter().enumerate() {
+        let param_name = rust_ident(&input.name, || format!("param_{idx}"));
+        let key = constructor_key_const(&param_name);
+        let store =
+            |value: &str| format!("api::set_storage(StorageFlags::empty(), &{key}, {value});");
+        let head_size = abi_head_size(&input.type_name, &input.components);
+        let is_dynamic = is_dynamic_type(&input.type_name, &input.components);
+
+        let mut stmts = Vec::new();
+        if matches!(input.type_name.as_str(), "address" | "bool") {
+            let codec = tuples.value_codec(input, "args", head_offset, &param_name, "");
+            stmts.push(format!(
+                "let {param_name}: {} = {};",
+                codec.rust_type, codec.decode_expr
+            ));
+            stmts.push(store(&format!("&encode_{}({param_name})", input.type_name)));
+        } else if input.type_name == "tuple" && !is_dynamic {
+            let codec = tuples.value_codec(
+                input,
+                "args",
+                head_offset,
+                &param_name,
+                &format!("constructor_{param_name}"),
+            );
+            stmts.push(format!("let {param_name} = {};", codec.decode_expr));
+            stmts.push(format!(
+                "let mut {param_name}_encoded = [0u8; {}::ENCODED_LEN];",
+                codec.rust_type
+            ));
+            stmts.push(format!("{param_name}.encode(&mut {param_name}_encoded);"));
+            stmts.push(store(&format!("&{param_name}_encoded")));
+        } else if let Some(lines) = tuples.bytes_decode(input, head_offset, &param_name) {
+            stmts.extend(lines);
+            stmts.push(store(&if input.type_name == "string" {
+                format!("{param_name}.as_bytes()")
+            } else {
+                param_name.clone()
+            }));
+        } else if !is_dynamic {
+            // Integers, `bytesN` and fixed-size arrays are stored as they were encoded
+            stmts.push(store(&format!(
+                "&args[{head_offset}..{}]",
+                head_offset + head_size
+            )));
+        } else {
+            stmts.push(format!(
+                "// TODO: decode and store {param_name} of type {}",
+                input.type_name
+            ));
+        }
+        head_offset += head_size;
+        args.push(constructor_arg(idx, input, stmts));
+    }
 
-    template.render().context("Failed to render alloc template")
+    let decodes_args = args
+        .iter()
+        .flat_map(|arg| &arg.stmts)
+        .any(|stmt| !stmt.starts_with("// TODO"));
+    ConstructorInfo {
+        // The arguments are the whole call data, with no selector in front
+        min_call_data_len: min_call_data_len(inputs) - 4,
+        decode_stmts: decodes_args
+            .then(|| "let args = &call_data[..call_data_len];".to_string())
+            .into_iter()
+            .collect(),
+        args,
+    }
 }
 
-fn generate_rust_code_no_alloc(metadata: &ContractMetadata, contract_name: &str) -> Result<String> {
+fn generate_rust_code_no_alloc(
+    metadata: &ContractMetadata,
+    contract_name: &str,
+    options: &ScaffoldOptions,
+) -> Result<(String, Vec<AbiConst>)> {
     let contract_name_upper = contract_name.to_uppercase();
 
     // Collect function selectors
     let mut selectors = Vec::new();
     let mut functions = Vec::new();
-
-    for item in &metadata.output.abi {
-        if let AbiItem::Function { name, inputs, .. } = item {
+    let mut tuples = TupleCodegen::default();
+    let mut return_encoders = Vec::new();
+
+    for (item, item_name, overload) in abi_functions(&metadata.output.abi) {
+        if let AbiItem::Function {
+            name,
+            inputs,
+            outputs,
+            state_mutability,
+        } = item
+        {
             let signature = build_function_signature(name, inputs);
             let selector = compute_selector(&signature);
-            let const_name = format!("{}_SELECTOR", name.to_case(Case::UpperSnake));
+            let mut const_name = format!("{}_SELECTOR", name.to_case(Case::UpperSnake));
+            if let Some(index) = overload {
+                const_name = format!("{const_name}_{index}");
+            }
 
             selectors.push(SelectorConst {
                 const_name: const_name.clone(),
@@ -541,29 +2411,78 @@ fn generate_rust_code_no_alloc(metadata: &ContractMetadata, contract_name: &str)
 
             // Generate decode params
             let mut params = Vec::new();
+            let mut head_offset = 0;
 
             for (idx, input) in inputs.iter().enumerate() {
-                let param_name = if input.name.is_empty() {
-                    format!("param_{}", idx)
+                let param_name = rust_ident(&input.name, || format!("param_{}", idx));
+
+                let decode_line = if input.type_name == "tuple" {
+                    let codec = tuples.value_codec(
+                        input,
+                        "args",
+                        head_offset,
+                        &param_name,
+                        &format!("{item_name}_{param_name}"),
+                    );
+                    format!("let {param_name} = {};", codec.decode_expr)
+                } else if let Some(lines) = tuples.array_decode(
+                    input,
+                    head_offset,
+                    &param_name,
+                    &format!("{item_name}_{param_name}"),
+                ) {
+                    lines.join("\n            ")
+                } else if let Some(lines) = tuples.bytes_decode(input, head_offset, &param_name) {
+                    lines.join("\n            ")
+                } else if matches!(input.type_name.as_str(), "address" | "bool")
+                    || fixed_bytes_len(&input.type_name).is_some()
+                {
+                    let codec = tuples.value_codec(input, "args", head_offset, &param_name, "");
+                    format!(
+                        "let {param_name}: {} = {};",
+                        codec.rust_type, codec.decode_expr
+                    )
                 } else {
-                    input.name.to_case(Case::Snake)
+                    format!("// TODO: decode {param_name} of type {}", input.type_name)
                 };
-
-                let decode_line =
-                    format!("// TODO: decode {param_name} of type {}", input.type_name);
+                head_offset += abi_head_size(&input.type_name, &input.components);
 
                 params.push(ParamDecode { decode_line });
             }
 
+            if params
+                .iter()
+                .any(|param| !param.decode_line.starts_with("// TODO"))
+            {
+                params.insert(
+                    0,
+                    ParamDecode {
+                        decode_line: "let args = &call_data[4..call_data_len];".to_string(),
+                    },
+                );
+            }
+
+            let return_encoder = tuples.return_encoder(&item_name, outputs);
+
             functions.push(NoAllocFunctionInfo {
                 name: name.clone(),
                 selector_const: const_name,
-                min_call_data_len: 4 + inputs.len() * 32,
+                min_call_data_len: min_call_data_len(inputs),
                 params,
+                guarded: options.reentrancy_guard && is_state_changing(state_mutability),
+                is_payable: state_mutability == "payable",
+                read_only: !is_state_changing(state_mutability),
+                return_encoder: return_encoder
+                    .as_ref()
+                    .map(|encoder| encoder.fn_name.clone()),
             });
+            return_encoders.extend(return_encoder);
         }
     }
 
+    let constructor = constructor_inputs(&metadata.output.abi)
+        .map(|inputs| no_alloc_constructor(&mut tuples, inputs));
+
     // Collect events
     let events: Vec<EventConst> = metadata
         .output
@@ -604,22 +2523,72 @@ fn generate_rust_code_no_alloc(metadata: &ContractMetadata, contract_name: &str)
         })
         .collect();
 
+    let abi_const = |name: &str, kind, signature: &str| AbiConst {
+        name: name.to_string(),
+        kind,
+        signature: signature.to_string(),
+    };
+    let mut abi_consts: Vec<_> = selectors
+        .iter()
+        .map(|sel| abi_const(&sel.const_name, AbiConstKind::Function, &sel.signature))
+        .collect();
+    // The template only declares event and error constants with the ABI helpers
+    if options.abi_helpers {
+        abi_consts.extend(
+            events
+                .iter()
+                .map(|evt| abi_const(&evt.const_name, AbiConstKind::Event, &evt.signature)),
+        );
+        abi_consts.extend(
+            errors
+                .iter()
+                .map(|err| abi_const(&err.const_name, AbiConstKind::Error, &err.signature)),
+        );
+    }
+
+    let tests = options.with_tests.then(|| {
+        let names = test_names(functions.iter().map(|function| function.name.as_str()));
+        functions
+            .iter()
+            .zip(names)
+            .map(|(function, test_name)| FunctionTest {
+                name: function.name.clone(),
+                test_name,
+                call_type: None,
+                selector_const: function.selector_const.clone(),
+                min_call_data_len: function.min_call_data_len,
+                guarded: function.guarded,
+            })
+            .collect()
+    });
     let template = ContractNoAllocTemplate {
         contract_name_upper: &contract_name_upper,
+        abi_helpers: options.abi_helpers,
+        reentrancy_guard: reentrancy_guard_consts(options.reentrancy_guard),
+        constructor,
         selectors,
         events,
         errors,
         functions,
+        uses_abi_offset: tuples.uses_abi_offset,
+        uses_bytes_buffer: tuples.uses_bytes_buffer,
+        uses_address: tuples.uses_address,
+        uses_bool: tuples.uses_bool,
+        structs: tuples.structs,
+        return_encoders,
+        tests: tests.is_some(),
     };
 
-    template
-        .render()
-        .context("Failed to render no-alloc template")
+    let mut code = render(&template, "no-alloc")?;
+    if let Some(functions) = tests {
+        code.push_str(&contract_tests(functions)?);
+    }
+    Ok((code, abi_consts))
 }
 
-fn resolve_target_json() -> Result<(PathBuf, String)> {
+fn resolve_target_json(bits: Bits) -> Result<(PathBuf, String)> {
     let mut args = polkavm_linker::TargetJsonArgs::default();
-    args.is_64_bit = true;
+    args.is_64_bit = bits == Bits::B64;
     let target_json = polkavm_linker::target_json_path(args)
         .map_err(|e| anyhow::anyhow!("Failed to get target JSON: {e}"))?;
 
@@ -632,7 +2601,12 @@ fn resolve_target_json() -> Result<(PathBuf, String)> {
     Ok((target_json, target_name))
 }
 
-fn generate_cargo_toml(contract_name: &str, bin_source: &str, use_alloc: bool) -> Result<String> {
+fn generate_cargo_toml(
+    contract_name: &str,
+    bin_source: &str,
+    verify_abi_consts: bool,
+    options: &ScaffoldOptions,
+) -> Result<String> {
     let builder_path = std::env::var("CARGO_PVM_CONTRACT_BUILDER_PATH")
         .ok()
         .filter(|value| !value.trim().is_empty());
@@ -647,11 +2621,749 @@ fn generate_cargo_toml(contract_name: &str, bin_source: &str, use_alloc: bool) -
     let template = CargoTomlTemplate {
         contract_name,
         bin_source,
-        use_alloc,
+        use_alloc: options.use_alloc,
         builder_version: BUILDER_VERSION,
         builder_path,
+        verify_abi_consts,
+        workspace_member: options.workspace_root.is_some(),
     };
-    template
-        .render()
-        .context("Failed to render Cargo.toml template")
+    render(&template, "Cargo.toml")
+}
+
+/// Render `template`, reporting failures as [`PvmBuildError::TemplateError`].
+fn render(template: &impl Template, what: &str) -> Result<String> {
+    template.render().map_err(|err| {
+        PvmBuildError::TemplateError {
+            context: format!("Failed to render {what} template: {err}"),
+        }
+        .into()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(type_name: &str, components: Vec<AbiInput>) -> AbiInput {
+        AbiInput {
+            name: String::new(),
+            type_name: type_name.to_string(),
+            internal_type: None,
+            indexed: None,
+            components,
+        }
+    }
+
+    #[test]
+    fn build_rs_selects_32_bit_target() {
+        let build_rs = generate_build_rs(Bits::B64).unwrap();
+        assert!(!build_rs.contains("with_bits"));
+
+        let build_rs = generate_build_rs(Bits::B32).unwrap();
+        assert!(build_rs.contains(
+            "PvmBuilder::new()\n        .with_bits(cargo_pvm_contract_builder::Bits::B32)\n        // Reserve"
+        ));
+    }
+
+    #[test]
+    fn solc_versions_are_parsed() {
+        let output = "solc, the solidity compiler commandline interface\nVersion: 0.8.26+commit.8a97fa7a.Linux.g++\n";
+        assert_eq!(parse_solc_version(output), Some((0, 8, 26)));
+        assert_eq!(
+            parse_solc_version("Version: 0.7.6-nightly.2021.1.1+commit.1"),
+            Some((0, 7, 6))
+        );
+        assert!(parse_solc_version("Version: 0.7.6+commit.7338295f").unwrap() < MIN_SOLC_VERSION);
+        assert_eq!(parse_solc_version("solc 0.8.26"), None);
+    }
+
+    #[test]
+    fn contract_names_are_validated() {
+        for name in ["MyToken", "my token", "my-token", "my_token", "erc20"] {
+            validate_contract_name(name).unwrap();
+        }
+
+        let cases = [
+            ("1token", "must not start with a digit"),
+            ("my.token", "`.` is not allowed"),
+            ("tökén", "`ö` is not allowed"),
+            ("--", "must contain a letter"),
+            ("fn", "`fn` is reserved"),
+            ("Self", "`self` is reserved"),
+            ("test", "`test` is reserved"),
+        ];
+        for (name, message) in cases {
+            let err = validate_contract_name(name).unwrap_err();
+            assert!(err.to_string().contains(message), "{name}: {err}");
+        }
+    }
+
+    #[test]
+    fn head_size_of_static_types() {
+        assert_eq!(abi_head_size("uint256", &[]), 32);
+        assert_eq!(abi_head_size("address", &[]), 32);
+        assert_eq!(abi_head_size("bytes4", &[]), 32);
+        assert_eq!(abi_head_size("uint8[3]", &[]), 96);
+        assert_eq!(abi_head_size("uint256[2][3]", &[]), 192);
+    }
+
+    #[test]
+    fn head_size_of_dynamic_types() {
+        assert_eq!(abi_head_size("bytes", &[]), 32);
+        assert_eq!(abi_head_size("string", &[]), 32);
+        assert_eq!(abi_head_size("uint256[]", &[]), 32);
+        assert_eq!(abi_head_size("string[2]", &[]), 32);
+        assert_eq!(abi_head_size("uint256[2][]", &[]), 32);
+    }
+
+    #[test]
+    fn head_size_of_tuples() {
+        let static_pair = vec![input("uint256", vec![]), input("address", vec![])];
+        assert_eq!(abi_head_size("tuple", &static_pair), 64);
+        assert_eq!(abi_head_size("tuple[2]", &static_pair), 128);
+
+        let nested = vec![input("bool", vec![]), input("tuple", static_pair.clone())];
+        assert_eq!(abi_head_size("tuple", &nested), 96);
+
+        let dynamic = vec![input("uint256", vec![]), input("string", vec![])];
+        assert_eq!(abi_head_size("tuple", &dynamic), 32);
+        assert_eq!(abi_head_size("tuple[2]", &dynamic), 32);
+    }
+
+    fn tuple_metadata() -> ContractMetadata {
+        let abi = r#"[
+            {"type": "function", "name": "place", "stateMutability": "nonpayable",
+             "inputs": [
+                {"name": "order", "type": "tuple", "internalType": "struct Orders.Order",
+                 "components": [
+                    {"name": "amount", "type": "uint256"},
+                    {"name": "owner", "type": "address"},
+                    {"name": "meta", "type": "tuple", "internalType": "struct Orders.Meta",
+                     "components": [
+                        {"name": "urgent", "type": "bool"},
+                        {"name": "tag", "type": "bytes4"}
+                     ]}
+                 ]},
+                {"name": "qty", "type": "uint64"}
+             ],
+             "outputs": [
+                {"name": "order", "type": "tuple", "internalType": "struct Orders.Order",
+                 "components": [
+                    {"name": "amount", "type": "uint256"},
+                    {"name": "owner", "type": "address"},
+                    {"name": "meta", "type": "tuple", "internalType": "struct Orders.Meta",
+                     "components": [
+                        {"name": "urgent", "type": "bool"},
+                        {"name": "tag", "type": "bytes4"}
+                     ]}
+                 ]}
+             ]},
+            {"type": "function", "name": "note", "stateMutability": "nonpayable",
+             "inputs": [
+                {"name": "", "type": "tuple",
+                 "components": [
+                    {"name": "text", "type": "string"},
+                    {"name": "level", "type": "int8"}
+                 ]}
+             ],
+             "outputs": []}
+        ]"#;
+        ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        }
+    }
+
+    #[test]
+    fn tuple_signatures_are_expanded() {
+        let metadata = tuple_metadata();
+        let AbiItem::Function { name, inputs, .. } = &metadata.output.abi[0] else {
+            panic!("expected a function");
+        };
+        assert_eq!(
+            build_function_signature(name, inputs),
+            "place((uint256,address,(bool,bytes4)),uint64)"
+        );
+    }
+
+    #[test]
+    fn no_alloc_tuples_get_structs_and_codecs() {
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&tuple_metadata(), "Orders", &options).unwrap();
+
+        assert!(!code.contains("TODO: decode order"));
+        assert!(code.contains("struct Order {"));
+        assert!(code.contains("    meta: Meta,"));
+        assert!(code.contains(
+            "            owner: decode_address(data[32..64].try_into().unwrap()).expect(\"Invalid address\"),"
+        ));
+        assert!(
+            code.contains("            meta: Meta::decode(&data[64..64 + Meta::ENCODED_LEN]),")
+        );
+        assert!(code.contains("            urgent: decode_bool(data[0..32].try_into().unwrap()),"));
+        assert!(code.contains("        self.meta.encode(&mut out[64..64 + Meta::ENCODED_LEN]);"));
+        assert!(code.contains("let order = Order::decode(&args[..Order::ENCODED_LEN]);"));
+        assert!(code.contains("// TODO: decode qty of type uint64"));
+        assert!(code.contains("fn encode_place_return(order: &Order) -> [u8; 128] {"));
+        assert_eq!(code.matches("struct Order {").count(), 1);
+
+        // Dynamic tuples are read through their offset and cannot be encoded in place
+        assert!(code.contains("struct NoteParam0 {"));
+        assert!(code.contains("let param_0 = NoteParam0::decode(&args[abi_offset(args, 0)..]);"));
+        assert!(code.contains("fn abi_offset(data: &[u8], at: usize) -> usize {"));
+        let note_impl = &code[code.find("impl NoteParam0").unwrap()..];
+        let note_impl = &note_impl[..note_impl.find("\n}\n").unwrap()];
+        assert!(!note_impl.contains("fn encode"));
+    }
+
+    fn array_metadata() -> ContractMetadata {
+        let abi = r#"[
+            {"type": "function", "name": "batch", "stateMutability": "nonpayable",
+             "inputs": [
+                {"name": "ids", "type": "uint256[3]"},
+                {"name": "amounts", "type": "uint64[]"},
+                {"name": "points", "type": "tuple[2]", "internalType": "struct Batch.Point[2]",
+                 "components": [
+                    {"name": "x", "type": "int32"},
+                    {"name": "y", "type": "int32"}
+                 ]},
+                {"name": "names", "type": "string[]"}
+             ],
+             "outputs": []}
+        ]"#;
+        ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        }
+    }
+
+    #[test]
+    fn no_alloc_arrays_decode_onto_the_stack() {
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&array_metadata(), "Batch", &options).unwrap();
+
+        // Fixed-size arrays are read inline from consecutive words
+        assert!(code.contains("let ids: [[u8; 32]; 3] = core::array::from_fn(|i| {"));
+        assert!(code.contains("let data = &args[i * 32..];"));
+        assert!(code.contains("let points: [Point; 2] = core::array::from_fn(|i| {"));
+        assert!(code.contains("let data = &args[128 + i * 64..];"));
+        assert!(code.contains("Point::decode(&data[..Point::ENCODED_LEN])"));
+
+        // Dynamic arrays are read through their offset and length, up to a fixed bound
+        assert!(code.contains("let amounts_at = abi_offset(args, 96);"));
+        assert!(code.contains("let amounts_len = abi_offset(args, amounts_at);"));
+        assert!(code.contains("if amounts_len > 16 {"));
+        assert!(code.contains("let amounts: [u64; 16] = core::array::from_fn(|i| {"));
+        assert!(code.contains("&args[amounts_at + 32 + i * 32..]"));
+        assert!(code.contains("u64::from_be_bytes(data[24..32].try_into().unwrap())"));
+        assert!(code.contains("let amounts = &amounts[..amounts_len];"));
+        assert!(code.contains("fn abi_offset(data: &[u8], at: usize) -> usize {"));
+
+        // Arrays of dynamic elements are left to the user
+        assert!(code.contains("// TODO: decode names of type string[]"));
+    }
+
+    #[test]
+    fn alloc_arrays_bind_sol_types() {
+        let options = ScaffoldOptions::standard(true);
+        let code =
+            generate_rust_code_alloc("Batch.sol", &array_metadata(), "Batch", &options).unwrap();
+
+        assert!(code.contains("sol_types::{sol_data, SolCall, SolError, SolEvent, SolType},"));
+        assert!(code.contains(
+            "let ids: <sol_data::FixedArray<sol_data::Uint<256>, 3> as SolType>::RustType = _batch_call.ids;"
+        ));
+        assert!(code.contains(
+            "let amounts: <sol_data::Array<sol_data::Uint<64>> as SolType>::RustType = _batch_call.amounts;"
+        ));
+        assert!(code.contains(
+            "let points: <sol_data::FixedArray<Batch::Point, 2> as SolType>::RustType = _batch_call.points;"
+        ));
+        assert!(code.contains(
+            "let names: <sol_data::Array<sol_data::String> as SolType>::RustType = _batch_call.names;"
+        ));
+    }
+
+    #[test]
+    fn contracts_are_added_to_workspace_members() {
+        let root = tempfile::tempdir().unwrap();
+        let manifest = root.path().join("Cargo.toml");
+        let options = ScaffoldOptions::minimal();
+        let members = || -> Vec<String> {
+            read_toml(&manifest).unwrap()["workspace"]["members"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|member| member.as_str().unwrap().to_string())
+                .collect()
+        };
+
+        fs::write(&manifest, "[workspace]\nresolver = \"2\"\n").unwrap();
+        add_workspace_member(root.path(), &root.path().join("contracts/a"), &options).unwrap();
+        add_workspace_member(root.path(), &root.path().join("contracts/b"), &options).unwrap();
+        add_workspace_member(root.path(), &root.path().join("contracts/a"), &options).unwrap();
+        assert_eq!(members(), ["contracts/a", "contracts/b"]);
+
+        fs::write(&manifest, "[workspace]\nmembers = [\"contracts/*\"]\n").unwrap();
+        add_workspace_member(root.path(), &root.path().join("contracts/c"), &options).unwrap();
+        assert_eq!(members(), ["contracts/*"]);
+
+        fs::write(&manifest, "[package]\nname = \"app\"\n").unwrap();
+        let err = add_workspace_member(root.path(), &root.path().join("contracts/d"), &options)
+            .unwrap_err();
+        assert!(err.to_string().contains("has no [workspace] section"));
+    }
+
+    #[test]
+    fn with_tests_appends_a_mocked_test_module() {
+        let abi = r#"[
+            {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+             "outputs": [{"name": "", "type": "bool"}]},
+            {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "to", "type": "address"}],
+             "outputs": []}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let mut options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Token", &options).unwrap();
+        assert!(code.starts_with("#![no_main]\n#![no_std]\n"));
+        assert!(!code.contains("#[cfg(test)]"));
+
+        options.with_tests = true;
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Token", &options).unwrap();
+        assert!(code.starts_with("#![cfg_attr(not(test), no_main, no_std)]\n\n"));
+        assert!(code.contains("#[cfg(test)]\nuse tests::mock as api;"));
+        assert!(code.contains("#[cfg(not(test))]\n#[panic_handler]"));
+        assert!(code.contains("pub fn return_value(flags: ReturnFlags, data: &[u8]) -> ! {"));
+        assert!(code.contains("fn unknown_selector_traps() {"));
+        assert!(code.contains("fn transfer() {"));
+        assert!(code.contains("fn transfer_2() {"));
+        assert!(code.contains("call_data.resize(68, 0);"));
+
+        options.use_alloc = true;
+        let code = generate_rust_code_alloc("Token.sol", &metadata, "Token", &options).unwrap();
+        assert!(code.contains("#[cfg(not(test))]\n#[global_allocator]"));
+        assert!(code.contains("let call = Token::transfer_0Call::new(Default::default());"));
+        assert!(code.contains("Token::transfer_1Call::abi_decode_returns(&returned.data, true)"));
+
+        let blank = generate_blank_contract(false, true).unwrap();
+        assert!(blank.contains("fn entry_points_return() {"));
+        assert!(!blank.contains("mod mock"));
+    }
+
+    #[test]
+    fn overloaded_functions_are_numbered() {
+        let abi = r#"[
+            {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+             "outputs": []},
+            {"type": "function", "name": "balanceOf", "stateMutability": "view",
+             "inputs": [{"name": "owner", "type": "address"}],
+             "outputs": [{"name": "", "type": "uint256"}]},
+            {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"},
+                        {"name": "data", "type": "bytes"}],
+             "outputs": []},
+            {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "to", "type": "address"}, {"name": "amount", "type": "uint256"}],
+             "outputs": []}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Token", &options).unwrap();
+        assert!(code.contains(
+            "const TRANSFER_SELECTOR_0: [u8; 4] = [0xa9, 0x05, 0x9c, 0xbb]; // transfer(address,uint256)"
+        ));
+        assert!(code.contains(
+            "const TRANSFER_SELECTOR_1: [u8; 4] = [0xbe, 0x45, 0xfd, 0x62]; // transfer(address,uint256,bytes)"
+        ));
+        assert!(code.contains("const BALANCE_OF_SELECTOR: [u8; 4]"));
+        // The repeated signature gets neither a constant nor a match arm
+        assert_eq!(code.matches("TRANSFER_SELECTOR_0 =>").count(), 1);
+        assert!(!code.contains("TRANSFER_SELECTOR_2"));
+        assert!(!code.contains("TRANSFER_SELECTOR:"));
+
+        let options = ScaffoldOptions::standard(true);
+        let code = generate_rust_code_alloc("Token.sol", &metadata, "Token", &options).unwrap();
+        assert!(code.contains("Token::transfer_0Call::SELECTOR => {"));
+        assert!(code.contains(
+            "let _transfer_1_call = Token::transfer_1Call::abi_decode(&call_data, true)"
+        ));
+        assert!(code.contains("Token::balanceOfCall::SELECTOR => {"));
+        assert!(!code.contains("transfer_2Call"));
+        assert!(!code.contains("Token::transferCall"));
+    }
+
+    #[test]
+    fn no_alloc_addresses_are_checked_and_padded() {
+        let abi = r#"[
+            {"type": "function", "name": "transfer", "stateMutability": "nonpayable",
+             "inputs": [{"name": "amount", "type": "uint256"}, {"name": "to", "type": "address"}],
+             "outputs": []},
+            {"type": "function", "name": "position", "stateMutability": "view",
+             "inputs": [],
+             "outputs": [{"name": "", "type": "tuple", "internalType": "struct Position",
+                          "components": [{"name": "size", "type": "uint64"},
+                                         {"name": "owner", "type": "address"}]}]}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Token", &options).unwrap();
+
+        assert!(code.contains(
+            "let to: [u8; 20] = decode_address(args[32..64].try_into().unwrap()).expect(\"Invalid address\");"
+        ));
+        assert!(code.contains("out[32..64].copy_from_slice(&encode_address(self.owner));"));
+        assert!(
+            code.contains("fn decode_address(word: &[u8; 32]) -> Result<[u8; 20], &'static str> {")
+        );
+        assert!(code.contains("fn encode_address(addr: [u8; 20]) -> [u8; 32] {"));
+        assert!(!code.contains("TODO: decode to"));
+
+        let (code, _) = generate_rust_code_no_alloc(&array_metadata(), "Batch", &options).unwrap();
+        assert!(!code.contains("fn decode_address"));
+    }
+
+    #[test]
+    fn no_alloc_bools_revert_when_out_of_range() {
+        let abi = r#"[
+            {"type": "function", "name": "setPaused", "stateMutability": "nonpayable",
+             "inputs": [{"name": "id", "type": "uint64"}, {"name": "paused", "type": "bool"}],
+             "outputs": []},
+            {"type": "function", "name": "state", "stateMutability": "view",
+             "inputs": [],
+             "outputs": [{"name": "", "type": "tuple", "internalType": "struct State",
+                          "components": [{"name": "id", "type": "uint64"},
+                                         {"name": "paused", "type": "bool"}]}]}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Pausable", &options).unwrap();
+
+        assert!(code.contains("let paused: bool = decode_bool(args[32..64].try_into().unwrap());"));
+        assert!(code.contains("out[32..64].copy_from_slice(&encode_bool(self.paused));"));
+        assert!(code.contains("fn decode_bool(word: &[u8; 32]) -> bool {"));
+        assert!(code.contains("api::return_value(ReturnFlags::REVERT, &[]);"));
+        assert!(code.contains("fn encode_bool(val: bool) -> [u8; 32] {"));
+        assert!(!code.contains("TODO: decode paused"));
+
+        let (code, _) = generate_rust_code_no_alloc(&array_metadata(), "Batch", &options).unwrap();
+        assert!(!code.contains("fn decode_bool"));
+    }
+
+    #[test]
+    fn handlers_follow_state_mutability() {
+        let abi = r#"[
+            {"type": "function", "name": "deposit", "stateMutability": "payable",
+             "inputs": [], "outputs": []},
+            {"type": "function", "name": "withdraw", "stateMutability": "nonpayable",
+             "inputs": [], "outputs": []},
+            {"type": "function", "name": "balance", "stateMutability": "view",
+             "inputs": [], "outputs": [{"name": "", "type": "uint256"}]}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let arm = |code: &str, start: &str| {
+            let arm = &code[code.find(start).unwrap()..];
+            arm[..arm.find("\n        }\n").unwrap()].to_string()
+        };
+        let revert =
+            "if value != [0u8; 32] {\n                api::return_value(ReturnFlags::REVERT, &[]);";
+
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Vault", &options).unwrap();
+        let deposit = arm(&code, "DEPOSIT_SELECTOR =>");
+        assert!(deposit.contains("api::value_transferred(&mut value);"));
+        assert!(!deposit.contains(revert));
+        let withdraw = arm(&code, "WITHDRAW_SELECTOR =>");
+        assert!(withdraw.contains(revert));
+        assert!(!withdraw.contains("must not write storage"));
+        let balance = arm(&code, "BALANCE_SELECTOR =>");
+        assert!(balance.contains(revert));
+        assert!(balance.contains("// `view` and `pure` functions must not write storage"));
+
+        let mut options = ScaffoldOptions::standard(true);
+        options.with_tests = true;
+        let code = generate_rust_code_alloc("Vault.sol", &metadata, "Vault", &options).unwrap();
+        let deposit = arm(&code, "Vault::depositCall::SELECTOR =>");
+        assert!(deposit.contains("let _value = U256::from_le_bytes(value);"));
+        assert!(!deposit.contains(revert));
+        assert!(arm(&code, "Vault::withdrawCall::SELECTOR =>").contains(revert));
+        assert!(code.contains("pub fn value_transferred(output: &mut [u8; 32]) {"));
+    }
+
+    #[test]
+    fn deploy_stores_constructor_arguments() {
+        let abi = r#"[
+            {"type": "constructor", "stateMutability": "nonpayable",
+             "inputs": [
+                {"name": "owner", "type": "address"},
+                {"name": "supply", "type": "uint256"},
+                {"name": "name", "type": "string"},
+                {"name": "holders", "type": "address[]"}
+             ]},
+            {"type": "function", "name": "totalSupply", "stateMutability": "view",
+             "inputs": [], "outputs": [{"name": "", "type": "uint256"}]}
+        ]"#;
+        let mut metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let deploy = |code: &str| {
+            let deploy = &code[code.find("pub extern \"C\" fn deploy()").unwrap()..];
+            deploy[..deploy.find("\n}\n").unwrap()].to_string()
+        };
+
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Token", &options).unwrap();
+        assert!(code.contains("// `deploy` stores each constructor argument under its own key"));
+        assert!(code.contains(&format!(
+            "const SUPPLY_STORAGE_KEY: [u8; 32] = [\n    {}\n]; // argument 1: supply (uint256), keccak256(\"cargo-pvm-contract.constructor-arg.1\")",
+            format_bytes32_multiline(&keccak256("cargo-pvm-contract.constructor-arg.1"))
+        )));
+        let body = deploy(&code);
+        assert!(body.contains("if call_data_len < 128 {"));
+        assert!(body.contains("let args = &call_data[..call_data_len];"));
+        assert!(body.contains(
+            "let owner: [u8; 20] = decode_address(args[0..32].try_into().unwrap()).expect(\"Invalid address\");\n    api::set_storage(StorageFlags::empty(), &OWNER_STORAGE_KEY, &encode_address(owner));"
+        ));
+        assert!(body.contains(
+            "api::set_storage(StorageFlags::empty(), &SUPPLY_STORAGE_KEY, &args[32..64]);"
+        ));
+        assert!(body.contains("let name_at = abi_offset(args, 64);"));
+        assert!(body.contains(
+            "api::set_storage(StorageFlags::empty(), &NAME_STORAGE_KEY, name.as_bytes());"
+        ));
+        assert!(body.contains("// TODO: decode and store holders of type address[]"));
+
+        let options = ScaffoldOptions::standard(true);
+        let code = generate_rust_code_alloc("Token.sol", &metadata, "Token", &options).unwrap();
+        assert!(code.contains("SolConstructor"));
+        let body = deploy(&code);
+        assert!(body.contains(
+            "<<Token::constructorCall as SolConstructor>::Parameters<'_> as SolType>::abi_decode_params("
+        ));
+        assert!(body.contains("let constructor = Token::constructorCall::new(params);"));
+        assert!(body.contains(
+            "api::set_storage(StorageFlags::empty(), &SUPPLY_STORAGE_KEY, &<sol_data::Uint<256> as SolType>::abi_encode(&constructor.supply));"
+        ));
+        assert!(body.contains(
+            "api::set_storage(StorageFlags::empty(), &NAME_STORAGE_KEY, constructor.name.as_bytes());"
+        ));
+
+        // Interfaces generated from an ABI have no `constructorCall`
+        metadata.from_interface = true;
+        let code = generate_rust_code_alloc("Token.sol", &metadata, "Token", &options).unwrap();
+        assert!(!code.contains("SolConstructor"));
+        assert!(deploy(&code).contains(
+            "let (owner, supply, name, holders) = <(\n        sol_data::Address,\n        sol_data::Uint<256>,\n        sol_data::String,\n        sol_data::Array<sol_data::Address>,\n    ) as SolType>::abi_decode_params(&call_data, true)"
+        ));
+    }
+
+    #[test]
+    fn interfaces_declare_constructor_structs() {
+        let abi = r#"[
+            {"type": "constructor", "stateMutability": "nonpayable",
+             "inputs": [
+                {"name": "config", "type": "tuple", "internalType": "struct Token.Config",
+                 "components": [{"name": "cap", "type": "uint64"}]}
+             ]}
+        ]"#;
+        let abi: Vec<AbiItem> = serde_json::from_str(abi).unwrap();
+        assert!(
+            solidity_interface("IToken", &abi)
+                .contains("struct Config {\n        uint64 cap;\n    }")
+        );
+
+        let AbiItem::Constructor { inputs } = &abi[0] else {
+            panic!("expected a constructor");
+        };
+        assert_eq!(
+            interface_sol_data_type(&inputs[0], "IToken").as_deref(),
+            Some("IToken::Config")
+        );
+    }
+
+    #[test]
+    fn deploy_is_empty_without_constructor_arguments() {
+        let abi = r#"[
+            {"type": "constructor", "stateMutability": "nonpayable", "inputs": []}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Token", &options).unwrap();
+        assert!(code.contains("pub extern \"C\" fn deploy() {}"));
+        assert!(!code.contains("STORAGE_KEY"));
+    }
+
+    #[test]
+    fn no_alloc_bytes_and_strings_are_copied_to_buffers() {
+        let abi = r#"[
+            {"type": "function", "name": "post", "stateMutability": "nonpayable",
+             "inputs": [
+                {"name": "topic", "type": "bytes32"},
+                {"name": "body", "type": "string"},
+                {"name": "payload", "type": "bytes"},
+                {"name": "tag", "type": "bytes4"}
+             ],
+             "outputs": []}
+        ]"#;
+        let metadata = ContractMetadata {
+            output: MetadataOutput {
+                abi: serde_json::from_str(abi).unwrap(),
+            },
+            from_interface: false,
+        };
+        let options = ScaffoldOptions::standard(false);
+        let (code, _) = generate_rust_code_no_alloc(&metadata, "Board", &options).unwrap();
+
+        assert!(code.contains("const MAX_BYTES_LEN: usize = 256;"));
+        assert!(code.contains("let args = &call_data[4..call_data_len];"));
+        assert!(code.contains("let topic: [u8; 32] = args[0..32].try_into().unwrap();"));
+        assert!(code.contains("let tag: [u8; 4] = args[96..100].try_into().unwrap();"));
+
+        assert!(code.contains("let body_at = abi_offset(args, 32);"));
+        assert!(code.contains("let body_len = abi_offset(args, body_at);"));
+        assert!(code.contains("if body_len > MAX_BYTES_LEN {"));
+        assert!(code.contains(
+            "body_buf[..body_len].copy_from_slice(&args[body_at + 32..body_at + 32 + body_len]);"
+        ));
+        assert!(code.contains("let body = core::str::from_utf8(&body_buf[..body_len])"));
+        assert!(code.contains("let payload = &payload_buf[..payload_len];"));
+        assert!(!code.contains("TODO: decode"));
+    }
+
+    #[test]
+    fn abi_json_accepts_arrays_and_artifacts() {
+        let abi = r#"[{"type": "function", "name": "f", "inputs": [], "outputs": [],
+                       "stateMutability": "view"},
+                      {"type": "fallback", "stateMutability": "nonpayable"}]"#;
+        assert_eq!(parse_abi_json(abi).unwrap().len(), 2);
+        assert_eq!(
+            parse_abi_json(&format!(r#"{{"abi": {abi}, "bytecode": "0x"}}"#))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(parse_abi_json(r#"{"bytecode": "0x"}"#).is_err());
+    }
+
+    #[test]
+    fn foundry_artifacts_need_an_abi() {
+        let artifact = r#"{
+            "abi": [{"type": "function", "name": "f", "inputs": [], "outputs": [],
+                     "stateMutability": "view"}],
+            "bytecode": {"object": "0x6080"},
+            "methodIdentifiers": {"f()": "26121ff0"}
+        }"#;
+        assert_eq!(parse_foundry_artifact(artifact).unwrap().len(), 1);
+
+        let error = parse_foundry_artifact(r#"{"bytecode": {"object": "0x"}}"#).unwrap_err();
+        assert!(error.to_string().contains("no `abi` field"));
+        assert!(parse_foundry_artifact("[]").is_err());
+    }
+
+    #[test]
+    fn solidity_interface_declares_abi() {
+        let interface = solidity_interface("Orders", &tuple_metadata().output.abi);
+
+        assert!(interface.contains("interface Orders {"));
+        assert!(interface.contains("    struct Order {\n        uint256 amount;\n        address owner;\n        Meta meta;\n    }"));
+        assert!(
+            interface
+                .contains("    struct Meta {\n        bool urgent;\n        bytes4 tag;\n    }")
+        );
+        assert!(interface.contains(
+            "    function place(Order calldata order, uint64 qty) external returns (Order memory order);"
+        ));
+        // Tuples without an internalType are named after their function and parameter
+        assert!(
+            interface
+                .contains("    struct Note {\n        string text;\n        int8 level;\n    }")
+        );
+        assert!(interface.contains("    function note(Note calldata) external;"));
+    }
+
+    #[test]
+    fn first_non_interface_contract_is_selected() {
+        let contract = || ContractInfo {
+            metadata: String::new(),
+        };
+        let contracts: BTreeMap<String, ContractInfo> = ["IToken", "Token", "Vault"]
+            .into_iter()
+            .map(|name| (name.to_string(), contract()))
+            .collect();
+        let nodes: Vec<AstNode> = serde_json::from_str(
+            r#"[
+                {"nodeType": "PragmaDirective"},
+                {"nodeType": "ContractDefinition", "name": "IToken", "contractKind": "interface"},
+                {"nodeType": "ContractDefinition", "name": "Vault", "contractKind": "contract"},
+                {"nodeType": "ContractDefinition", "name": "Token", "contractKind": "contract"}
+            ]"#,
+        )
+        .unwrap();
+
+        let select = |nodes: &[AstNode], requested| {
+            select_contract(&contracts, nodes, requested, "Token.sol")
+                .map(|(name, _)| name.as_str())
+        };
+        assert_eq!(select(&nodes, None).unwrap(), "Vault");
+        assert_eq!(select(&nodes, Some("Token")).unwrap(), "Token");
+        assert_eq!(select(&nodes[..2], None).unwrap(), "IToken");
+        assert_eq!(select(&[], None).unwrap(), "IToken");
+
+        let error = select(&nodes, Some("Missing")).unwrap_err().to_string();
+        assert!(error.contains("Contract `Missing` not found in Token.sol"));
+        assert!(error.contains("available contracts: IToken, Token, Vault"));
+    }
+
+    #[test]
+    fn min_call_data_len_counts_static_struct_words() {
+        // f((uint256,address),uint256): the old `4 + inputs.len() * 32` gave 68.
+        let inputs = vec![
+            input(
+                "tuple",
+                vec![input("uint256", vec![]), input("address", vec![])],
+            ),
+            input("uint256", vec![]),
+        ];
+        assert_eq!(min_call_data_len(&inputs), 100);
+    }
 }
@@ -13,9 +13,18 @@ use alloc::vec;
 
 sol!("MyToken.sol");
 
+/// Heap size in bytes, configurable through `PvmBuilder::with_heap_size_env`.
+const HEAP_SIZE: usize = match option_env!("PVM_HEAP_SIZE") {
+    Some(size) => match usize::from_str_radix(size, 10) {
+        Ok(size) => size,
+        Err(_) => panic!("PVM_HEAP_SIZE must be a number of bytes"),
+    },
+    None => 1024,
+};
+
 #[global_allocator]
-static mut ALLOC: picoalloc::Mutex<picoalloc::Allocator<picoalloc::ArrayPointer<1024>>> = {
-    static mut ARRAY: picoalloc::Array<1024> = picoalloc::Array([0u8; 1024]);
+static mut ALLOC: picoalloc::Mutex<picoalloc::Allocator<picoalloc::ArrayPointer<HEAP_SIZE>>> = {
+    static mut ARRAY: picoalloc::Array<HEAP_SIZE> = picoalloc::Array([0u8; HEAP_SIZE]);
 
     picoalloc::Mutex::new(picoalloc::Allocator::new(unsafe {
         picoalloc::ArrayPointer::new(&raw mut ARRAY)
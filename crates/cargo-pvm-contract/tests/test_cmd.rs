@@ -65,3 +65,1873 @@ fn scaffold_mytoken_no_alloc() {
 
     build_scaffolded_project(&project_dir);
 }
+
+fn polkavm_rw_data_size(project_dir: &Path) -> u32 {
+    let blob_path = std::fs::read_dir(project_dir.join("target"))
+        .expect("target dir exists")
+        .map(|entry| entry.expect("dir entry").path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "polkavm"))
+        .expect("PolkaVM blob exists");
+    let bytes = std::fs::read(blob_path).expect("read blob");
+    let parts = polkavm_linker::ProgramParts::from_bytes(bytes.into()).expect("parse blob");
+    parts.rw_data_size
+}
+
+#[test]
+fn scaffold_alloc_with_larger_heap() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-heap", "alloc-with-alloy");
+
+    build_scaffolded_project(&project_dir);
+    let default_size = polkavm_rw_data_size(&project_dir);
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_heap_size_env(64 * 1024)\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+    let larger_size = polkavm_rw_data_size(&project_dir);
+
+    assert!(
+        larger_size >= default_size + 63 * 1024,
+        "rw data grew from {default_size} to only {larger_size} bytes"
+    );
+}
+
+#[test]
+fn extra_rustflags_reach_contract_build() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-cfg", "no-alloc");
+
+    let contract_src = std::fs::read_dir(project_dir.join("src"))
+        .expect("src dir exists")
+        .map(|entry| entry.expect("dir entry").path())
+        .find(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .expect("contract source exists");
+    let mut source = std::fs::read_to_string(&contract_src).expect("read contract source");
+    source
+        .push_str("\n#[cfg(not(pvm_test_flag))]\ncompile_error!(\"pvm_test_flag is not set\");\n");
+    std::fs::write(&contract_src, source).expect("write contract source");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_extra_rustflags(\"--cfg pvm_test_flag\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+}
+
+#[test]
+fn oversized_contract_fails_the_build() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-padded", "no-alloc");
+
+    let contract_src = project_dir.join("src/my-token.rs");
+    let mut source = std::fs::read_to_string(&contract_src).expect("read contract source");
+    source = source.replace(
+        "pub extern \"C\" fn deploy() {}",
+        "pub extern \"C\" fn deploy() {\n    core::hint::black_box(&PADDING);\n}",
+    );
+    source.push_str("\nstatic PADDING: [u8; 65536] = [0xaa; 65536];\n");
+    std::fs::write(&contract_src, source).expect("write contract source");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_max_size(32 * 1024)\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    let output = std::process::Command::new("cargo")
+        .current_dir(&project_dir)
+        .env_remove("CARGO")
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .arg("build")
+        .output()
+        .expect("run cargo build");
+    assert!(!output.status.success(), "oversized contract built");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("exceeds the limit of 32768 bytes"),
+        "unexpected output: {stderr}"
+    );
+}
+
+#[test]
+fn exports_listing_names_entry_points_and_host_functions() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .env("CARGO_PVM_CONTRACT_BUILDER_PATH", builder_path)
+        .arg("pvm-contract")
+        .args(["--init-type", "example", "--example", "Fibonacci"])
+        .args(["--memory-model", "no-alloc", "--name", "fib-exports"])
+        .assert()
+        .success();
+    let project_dir = temp_dir.path().join("fib-exports");
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_exports_listing(true)\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let listing_path = find_file(&project_dir.join("target"), "fibonacci.exports.json")
+        .expect("exports listing exists");
+    let listing: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(listing_path).expect("read listing"))
+            .expect("listing is JSON");
+    let names = |key: &str| -> Vec<String> {
+        listing[key]
+            .as_array()
+            .expect("array")
+            .iter()
+            .map(|entry| {
+                entry
+                    .as_str()
+                    .or_else(|| entry["name"].as_str())
+                    .expect("name")
+                    .to_string()
+            })
+            .collect()
+    };
+    let exports = names("exports");
+    let imports = names("imports");
+    for export in ["deploy", "call"] {
+        assert!(exports.iter().any(|name| name == export), "{exports:?}");
+    }
+    for import in ["call_data_size", "call_data_copy", "return_value"] {
+        assert!(imports.iter().any(|name| name == import), "{imports:?}");
+    }
+}
+
+#[test]
+fn instruction_sets_produce_loadable_blobs() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-isa", "no-alloc");
+
+    for isa in ["ReviveV1", "Latest"] {
+        std::fs::write(
+            project_dir.join("build.rs"),
+            format!(
+                "use cargo_pvm_contract_builder::{{PvmBuilder, TargetInstructionSet}};\n\nfn main() {{\n    PvmBuilder::new()\n        .with_instruction_set(TargetInstructionSet::{isa})\n        .with_instruction_set_in_file_name(true)\n        .build();\n}}\n"
+            ),
+        )
+        .expect("write build.rs");
+        build_scaffolded_project(&project_dir);
+    }
+
+    for isa in ["revive_v1", "latest"] {
+        let blob_path = find_file(
+            &project_dir.join("target"),
+            &format!("my-token.{isa}.debug.polkavm"),
+        )
+        .unwrap_or_else(|| panic!("{isa} blob exists"));
+        let blob = std::fs::read(blob_path).expect("read blob");
+        let program = polkavm_linker::ProgramBlob::parse(blob.into()).expect("blob is loadable");
+        assert!(
+            program
+                .exports()
+                .any(|export| export.symbol().as_bytes() == b"call")
+        );
+    }
+}
+
+#[test]
+fn disassembly_listing_names_the_call_export() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-disassembly", "no-alloc");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_disassembly(true)\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let listing_path =
+        find_file(&project_dir.join("target"), "my-token.pvmasm").expect("listing exists");
+    let listing = std::fs::read_to_string(listing_path).expect("read listing");
+    assert!(
+        listing.contains("\ncall:\n"),
+        "unexpected listing: {listing}"
+    );
+}
+
+#[test]
+fn linked_binary_path_is_exported_to_crate() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-wrapper", "no-alloc");
+
+    // The nested contract build compiles the library too, before the blob exists
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_extra_rustflags(\"--cfg pvm_nested\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+    std::fs::write(
+        project_dir.join("src/lib.rs"),
+        r#"#![no_std]
+
+#[cfg(not(pvm_nested))]
+pub const CONTRACT: &[u8] = include_bytes!(env!("PVM_BINARY_MYTOKEN_WRAPPER"));
+
+#[cfg(not(pvm_nested))]
+const _: () = assert!(
+    CONTRACT.len() > 4
+        && CONTRACT[0] == b'P'
+        && CONTRACT[1] == b'V'
+        && CONTRACT[2] == b'M'
+        && CONTRACT[3] == 0
+);
+"#,
+    )
+    .expect("write lib.rs");
+
+    build_scaffolded_project(&project_dir);
+}
+
+fn find_file(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    for entry in std::fs::read_dir(dir).ok()? {
+        let path = entry.ok()?.path();
+        if path.is_dir() {
+            if let Some(found) = find_file(&path, file_name) {
+                return Some(found);
+            }
+        } else if path.file_name().is_some_and(|name| name == file_name) {
+            return Some(path);
+        }
+    }
+    None
+}
+
+#[test]
+fn generated_module_embeds_every_binary() {
+    use tiny_keccak::{Hasher, Keccak};
+
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-module", "no-alloc");
+
+    // Add a second binary built from the same source
+    std::fs::copy(
+        project_dir.join("src/my-token.rs"),
+        project_dir.join("src/copy.rs"),
+    )
+    .expect("copy contract source");
+    let mut cargo_toml =
+        std::fs::read_to_string(project_dir.join("Cargo.toml")).expect("Cargo.toml exists");
+    cargo_toml = cargo_toml.replacen(
+        "[dependencies]",
+        "[[bin]]\nname = \"mytoken-copy\"\npath = \"src/copy.rs\"\n\n[dependencies]",
+        1,
+    );
+    std::fs::write(project_dir.join("Cargo.toml"), cargo_toml).expect("write Cargo.toml");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_extra_rustflags(\"--cfg pvm_nested\")\n        .with_generated_module(true)\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+    std::fs::write(
+        project_dir.join("src/lib.rs"),
+        r#"#![no_std]
+
+#[cfg(not(pvm_nested))]
+include!(concat!(env!("OUT_DIR"), "/pvm_contracts.rs"));
+
+#[cfg(not(pvm_nested))]
+const _: () = assert!(!MYTOKEN_MODULE.is_empty() && !MYTOKEN_COPY.is_empty());
+"#,
+    )
+    .expect("write lib.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let module_path =
+        find_file(&project_dir.join("target"), "pvm_contracts.rs").expect("module generated");
+    let module = std::fs::read_to_string(module_path).expect("read module");
+    for bin in ["mytoken-module", "mytoken-copy"] {
+        let blob_path = project_dir.join(format!("target/{bin}.debug.polkavm"));
+        let blob = std::fs::read(&blob_path).expect("blob exists");
+
+        let mut hasher = Keccak::v256();
+        hasher.update(&blob);
+        let mut hash = [0u8; 32];
+        hasher.finalize(&mut hash);
+        let hash = hash
+            .iter()
+            .map(|byte| format!("0x{byte:02x}"))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        assert!(module.contains(&format!("{:?}", blob_path.to_str().unwrap())));
+        assert!(module.contains(&hash));
+    }
+}
+
+#[test]
+fn build_subcommand_links_scaffolded_project() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-cli-build", "no-alloc");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(&project_dir)
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .arg("pvm-contract")
+        .arg("build")
+        .arg("--release")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains(
+            "mytoken-cli-build.release.polkavm",
+        ));
+
+    assert!(
+        project_dir
+            .join("target/mytoken-cli-build.release.polkavm")
+            .exists()
+    );
+}
+
+#[test]
+fn unchanged_project_is_not_rebuilt() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-fingerprint", "no-alloc");
+
+    let build = || {
+        Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+            .current_dir(&project_dir)
+            .env_remove("RUSTUP_TOOLCHAIN")
+            .arg("pvm-contract")
+            .arg("build")
+            .arg("--release")
+            .assert()
+            .success()
+    };
+
+    build().stderr(predicates::boolean::PredicateBooleanExt::not(
+        predicates::str::contains("up to date"),
+    ));
+    build().stderr(predicates::str::contains("PolkaVM binaries are up to date"));
+
+    let main_rs = project_dir.join("src/mytoken-fingerprint.rs");
+    let source = std::fs::read_to_string(&main_rs).expect("read contract source");
+    std::fs::write(&main_rs, source + "\n// changed\n").expect("write contract source");
+    build().stderr(predicates::boolean::PredicateBooleanExt::not(
+        predicates::str::contains("up to date"),
+    ));
+}
+
+#[test]
+fn build_subcommand_requires_manifest() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("build")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No Cargo.toml found"));
+}
+
+#[test]
+fn scaffold_32_bit_target() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .args(["--init-type", "blank", "--memory-model", "no-alloc"])
+        .args(["--target-bits", "32", "--name", "blank-32"])
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("blank-32");
+    let config = std::fs::read_to_string(project_dir.join(".cargo/config.toml"))
+        .expect("config.toml exists");
+    assert!(config.contains("riscv32emac"), "{config}");
+    let build_rs = std::fs::read_to_string(project_dir.join("build.rs")).expect("build.rs exists");
+    assert!(build_rs.contains(".with_bits(cargo_pvm_contract_builder::Bits::B32)"));
+}
+
+#[test]
+fn fibonacci_links_for_32_bit_target() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .env("CARGO_PVM_CONTRACT_BUILDER_PATH", builder_path)
+        .arg("pvm-contract")
+        .args(["--init-type", "example", "--example", "Fibonacci"])
+        .args(["--memory-model", "no-alloc", "--target-bits", "32"])
+        .args(["--name", "fib-32"])
+        .assert()
+        .success();
+    let project_dir = temp_dir.path().join("fib-32");
+
+    build_scaffolded_project(&project_dir);
+
+    let blob_path =
+        find_file(&project_dir.join("target"), "fibonacci.debug.polkavm").expect("blob exists");
+    let blob = std::fs::read(blob_path).expect("read blob");
+    let program = polkavm_linker::ProgramBlob::parse(blob.into()).expect("blob is loadable");
+    assert!(!program.is_64_bit());
+}
+
+#[test]
+fn scaffold_blank_uses_local_builder_path() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .env("CARGO_PVM_CONTRACT_BUILDER_PATH", &builder_path)
+        .arg("pvm-contract")
+        .args(["--init-type", "blank", "--memory-model", "no-alloc"])
+        .args(["--name", "blank-local"])
+        .assert()
+        .success();
+
+    let cargo_toml = std::fs::read_to_string(temp_dir.path().join("blank-local/Cargo.toml"))
+        .expect("Cargo.toml exists");
+    assert!(
+        cargo_toml.contains(&format!(
+            r#"cargo-pvm-contract-builder = {{ path = "{}" }}"#,
+            builder_path.display()
+        )),
+        "{cargo_toml}"
+    );
+}
+
+#[test]
+fn scaffold_minimal_rejects_alloc() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--init-type")
+        .arg("blank")
+        .arg("--minimal")
+        .arg("--memory-model")
+        .arg("alloc-with-alloy")
+        .arg("--name")
+        .arg("minimal")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--minimal cannot be combined"));
+}
+
+#[test]
+fn scaffold_minimal_rejects_example() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .args([
+            "pvm-contract",
+            "--init-type",
+            "example",
+            "--example",
+            "MyToken",
+        ])
+        .args(["--minimal", "--name", "minimal"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--minimal cannot be combined with --init-type example",
+        ));
+
+    assert!(!temp_dir.path().join("minimal").exists());
+}
+
+#[test]
+fn scaffold_minimal_from_sol_file() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let sol_file =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/examples/mytoken/MyToken.sol");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--init-type")
+        .arg("solidity-file")
+        .arg("--sol-file")
+        .arg(sol_file)
+        .arg("--minimal")
+        .arg("--name")
+        .arg("mytoken-minimal")
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("mytoken-minimal");
+    let cargo_toml =
+        std::fs::read_to_string(project_dir.join("Cargo.toml")).expect("Cargo.toml exists");
+    assert!(!cargo_toml.contains("alloy-core"));
+    assert!(!cargo_toml.contains("picoalloc"));
+
+    let contract =
+        std::fs::read_to_string(project_dir.join("src/my-token.rs")).expect("contract exists");
+    assert!(contract.contains("_SELECTOR"));
+    assert!(!contract.contains("_EVENT_SIGNATURE"));
+    assert!(!contract.contains("_ERROR"));
+}
+
+#[test]
+fn scaffold_from_abi_file_without_solc() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    // A Foundry-style artifact: the ABI sits under `abi`
+    let abi_path = temp_dir.path().join("Counter.json");
+    std::fs::write(
+        &abi_path,
+        r#"{"abi": [
+            {"type": "function", "name": "increment", "stateMutability": "nonpayable",
+             "inputs": [{"name": "by", "type": "uint64", "internalType": "uint64"}],
+             "outputs": []},
+            {"type": "event", "name": "Incremented", "anonymous": false,
+             "inputs": [{"name": "by", "type": "uint64", "indexed": false}]},
+            {"type": "receive", "stateMutability": "payable"}
+        ]}"#,
+    )
+    .expect("write ABI");
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--init-type")
+        .arg("abi-file")
+        .arg("--abi-file")
+        .arg(&abi_path)
+        .arg("--memory-model")
+        .arg("no-alloc")
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("counter");
+    let contract = std::fs::read_to_string(project_dir.join("src/counter.rs")).expect("contract");
+    assert!(contract.contains("const INCREMENT_SELECTOR: [u8; 4]"));
+    assert!(contract.contains("// increment(uint64)"));
+
+    let interface = std::fs::read_to_string(project_dir.join("Counter.sol")).expect("interface");
+    assert!(interface.contains("interface Counter {"));
+    assert!(interface.contains("function increment(uint64 by) external;"));
+}
+
+#[test]
+fn build_verify_abi_reports_drifted_constants() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let abi_path = temp_dir.path().join("Counter.json");
+    std::fs::write(
+        &abi_path,
+        r#"[{"type": "function", "name": "increment", "stateMutability": "nonpayable",
+             "inputs": [{"name": "by", "type": "uint64", "internalType": "uint64"}],
+             "outputs": []}]"#,
+    )
+    .expect("write ABI");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--init-type")
+        .arg("abi-file")
+        .arg("--abi-file")
+        .arg(&abi_path)
+        .arg("--memory-model")
+        .arg("no-alloc")
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("counter");
+    let cargo_toml = std::fs::read_to_string(project_dir.join("Cargo.toml")).expect("Cargo.toml");
+    assert!(cargo_toml.contains("[package.metadata.pvm]\n"));
+    assert!(cargo_toml.contains("verify-abi-consts = true"));
+    let sidecar: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(project_dir.join("pvm-abi-consts.json")).expect("sidecar"),
+    )
+    .expect("sidecar is JSON");
+    assert_eq!(sidecar["sol_file"], "Counter.sol");
+    assert_eq!(sidecar["source"], "src/counter.rs");
+    assert_eq!(
+        sidecar["constants"][0],
+        serde_json::json!({"name": "INCREMENT_SELECTOR", "kind": "function", "signature": "increment(uint64)"})
+    );
+
+    // Hand-edit the selector the contract dispatches on
+    let contract_path = project_dir.join("src/counter.rs");
+    let contract = std::fs::read_to_string(&contract_path).expect("contract");
+    let declaration = "const INCREMENT_SELECTOR: [u8; 4] = [";
+    let start = contract.find(declaration).expect("selector constant") + declaration.len();
+    let end = start + contract[start..].find(']').expect("end of selector");
+    let selector: String = contract[start..end]
+        .split(", ")
+        .map(|byte| byte.trim_start_matches("0x"))
+        .collect();
+    let edited = format!(
+        "{}0x00, 0x00, 0x00, 0x00{}",
+        &contract[..start],
+        &contract[end..]
+    );
+    std::fs::write(&contract_path, edited).expect("edit contract");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(&project_dir)
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .arg("pvm-contract")
+        .arg("build")
+        .arg("--verify-abi")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("ABI constants in"))
+        .stderr(predicates::str::contains(format!(
+            "`INCREMENT_SELECTOR`: 0x00000000, expected 0x{selector} for `increment(uint64)`"
+        )));
+}
+
+#[test]
+fn scaffold_from_foundry_artifact() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let artifact_dir = temp_dir.path().join("out/Vault.sol");
+    std::fs::create_dir_all(&artifact_dir).expect("create out dir");
+    let artifact_path = artifact_dir.join("Vault.json");
+    std::fs::write(
+        &artifact_path,
+        r#"{
+            "abi": [
+                {"type": "constructor", "inputs": [], "stateMutability": "nonpayable"},
+                {"type": "function", "name": "deposit", "stateMutability": "payable",
+                 "inputs": [], "outputs": []}
+            ],
+            "bytecode": {"object": "0x6080", "linkReferences": {}},
+            "methodIdentifiers": {"deposit()": "d0e30db0"}
+        }"#,
+    )
+    .expect("write artifact");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--init-type")
+        .arg("foundry-artifact")
+        .arg("--foundry-artifact")
+        .arg(&artifact_path)
+        .arg("--memory-model")
+        .arg("no-alloc")
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("vault");
+    let contract = std::fs::read_to_string(project_dir.join("src/vault.rs")).expect("contract");
+    // keccak256("deposit()")[..4]
+    assert!(contract.contains("const DEPOSIT_SELECTOR: [u8; 4] = [0xd0, 0xe3, 0x0d, 0xb0];"));
+    let interface = std::fs::read_to_string(project_dir.join("Vault.sol")).expect("interface");
+    assert!(interface.contains("function deposit() external payable;"));
+}
+
+#[test]
+fn non_interactive_reports_missing_flags() {
+    let cases: [(&[&str], &str); 5] = [
+        (&[], "--init-type, --memory-model"),
+        (&["--init-type", "blank"], "--name, --memory-model"),
+        (&["--init-type", "example"], "--example, --memory-model"),
+        (
+            &["--init-type", "solidity-file", "--memory-model", "no-alloc"],
+            "--sol-file",
+        ),
+        (
+            &["--init-type", "abi-file", "--memory-model", "no-alloc"],
+            "--abi-file",
+        ),
+    ];
+
+    for (args, missing) in cases {
+        let temp_dir = TempDir::new().expect("temp dir");
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+        cmd.current_dir(temp_dir.path())
+            .arg("pvm-contract")
+            .args(args)
+            .write_stdin("")
+            .timeout(std::time::Duration::from_secs(30))
+            .assert()
+            .failure()
+            .stderr(predicates::str::contains("not running in a terminal"))
+            .stderr(predicates::str::contains(missing));
+    }
+}
+
+#[test]
+fn invalid_contract_name_is_rejected() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .args(["--init-type", "blank", "--memory-model", "no-alloc"])
+        .args(["--name", "1token"])
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("must not start with a digit"));
+    assert!(!temp_dir.path().join("1token").exists());
+}
+
+#[test]
+fn misspelled_example_suggests_the_closest_name() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .args(["--init-type", "example", "--example", "MyTokn"])
+        .args(["--memory-model", "no-alloc", "--name", "typo"])
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Unknown example 'MyTokn'. Did you mean 'MyToken'?",
+        ));
+
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .args(["--init-type", "example", "--example", "Uniswap"])
+        .args(["--memory-model", "no-alloc", "--name", "unknown"])
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Available examples: Fibonacci, MyToken",
+        ));
+}
+
+#[test]
+fn non_interactive_answers_from_stdin() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--answers-from")
+        .arg("-")
+        .write_stdin("# blank project\ninit-type=blank\nmemory-model=no-alloc\nname=piped\n")
+        .timeout(std::time::Duration::from_secs(30))
+        .assert()
+        .success();
+
+    assert!(temp_dir.path().join("piped/Cargo.toml").exists());
+}
+
+#[test]
+fn scaffold_reentrancy_guard_skips_view_functions() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let sol_file =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/examples/mytoken/MyToken.sol");
+    let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+    cmd.current_dir(temp_dir.path())
+        .arg("pvm-contract")
+        .arg("--init-type")
+        .arg("solidity-file")
+        .arg("--sol-file")
+        .arg(sol_file)
+        .arg("--memory-model")
+        .arg("no-alloc")
+        .arg("--reentrancy-guard")
+        .arg("--name")
+        .arg("mytoken-guarded")
+        .assert()
+        .success();
+
+    let contract = std::fs::read_to_string(temp_dir.path().join("mytoken-guarded/src/my-token.rs"))
+        .expect("contract exists");
+    assert!(contract.contains("const REENTRANCY_GUARD_KEY: [u8; 32]"));
+    assert!(contract.contains("// ReentrancyGuard()"));
+
+    // transfer and mint are guarded, the totalSupply and balanceOf views are not
+    assert_eq!(contract.matches("non_reentrant(|| {").count(), 2);
+}
+
+#[test]
+fn scaffold_reentrancy_guard_rejects_blank() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .args([
+            "pvm-contract",
+            "--init-type",
+            "blank",
+            "--memory-model",
+            "no-alloc",
+        ])
+        .args(["--reentrancy-guard", "--name", "guarded"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "--reentrancy-guard cannot be combined with --init-type blank",
+        ));
+
+    assert!(!temp_dir.path().join("guarded").exists());
+}
+
+#[test]
+fn reentrancy_guard_test_reverts_reentrant_call() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    let sol_file =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/examples/mytoken/MyToken.sol");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .env("CARGO_PVM_CONTRACT_BUILDER_PATH", builder_path)
+        .args(["pvm-contract", "--init-type", "solidity-file", "--sol-file"])
+        .arg(sol_file)
+        .args(["--memory-model", "no-alloc", "--name", "mytoken-reentrant"])
+        .args(["--reentrancy-guard", "--with-tests"])
+        .assert()
+        .success();
+
+    let output = std::process::Command::new("cargo")
+        .current_dir(temp_dir.path().join("mytoken-reentrant"))
+        // Remove env vars that override rust-toolchain.toml
+        .env_remove("CARGO")
+        .env_remove("RUSTUP_TOOLCHAIN")
+        // The host tests don't need the PolkaVM blob
+        .env("PVM_CHECK_ONLY", "1")
+        .args(["test-host", "reentrant_call_reverts"])
+        .output()
+        .expect("run cargo test-host");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "{stdout}\n{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        stdout.contains("test tests::reentrant_call_reverts ... ok"),
+        "{stdout}"
+    );
+}
+
+#[test]
+fn missing_solc_explains_how_to_install_it() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let sol_file =
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("templates/examples/mytoken/MyToken.sol");
+    let init = |solc_path: Option<&Path>| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+        cmd.current_dir(temp_dir.path())
+            .env("PATH", temp_dir.path())
+            .env_remove("SOLC_PATH")
+            .arg("pvm-contract")
+            .args(["--init-type", "solidity-file", "--memory-model", "no-alloc"])
+            .arg("--sol-file")
+            .arg(&sol_file);
+        if let Some(path) = solc_path {
+            cmd.env("SOLC_PATH", path);
+        }
+        cmd.assert().failure()
+    };
+
+    init(None)
+        .stderr(predicates::str::contains("not found in PATH"))
+        .stderr(predicates::str::contains("npm install -g solc"));
+    init(Some(&temp_dir.path().join("solc-0.8.30"))).stderr(predicates::str::contains(
+        "Failed to spawn solc from SOLC_PATH=",
+    ));
+}
+
+#[test]
+fn help_documents_solc_environment_variables() {
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .args(["pvm-contract", "--help"])
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("SOLC_PATH"))
+        .stdout(predicates::str::contains("SOLC_VERSION"));
+}
+
+#[test]
+fn inspect_reports_exports() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let blob_path = temp_dir.path().join("contract.polkavm");
+    let blob = polkavm_linker::assemble(
+        None,
+        "%isa = revive_v1\npub @deploy:\nret\npub @call:\nret\n",
+    )
+    .expect("assemble blob");
+    std::fs::write(&blob_path, blob).expect("write blob");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .arg("pvm-contract")
+        .arg("inspect")
+        .arg(&blob_path)
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("Exports: call, deploy"))
+        .stdout(predicates::str::contains("00000000  50 56 4d 00"));
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .arg("pvm-contract")
+        .arg("inspect")
+        .arg("--json")
+        .arg(&blob_path)
+        .output()
+        .expect("run inspect");
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    assert_eq!(json["exports"], serde_json::json!(["call", "deploy"]));
+    assert_eq!(json["instruction_count"], 2);
+}
+
+#[test]
+fn build_report_matches_artifacts() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-report", "no-alloc");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_report(\"target\")\n        .with_cargo_timings(true)\n        .with_code_hash_file(cargo_pvm_contract_builder::CodeHash::Keccak256)\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let report = std::fs::read_to_string(project_dir.join("target/pvm-build-report.json"))
+        .expect("report written");
+    let report: cargo_pvm_contract_builder::BuildOutput =
+        serde_json::from_str(&report).expect("valid report");
+
+    assert_eq!(report.artifacts.len(), 1);
+    let artifact = &report.artifacts[0];
+    let on_disk = std::fs::metadata(&artifact.path)
+        .expect("blob exists")
+        .len();
+    assert_eq!(artifact.size, on_disk);
+
+    let mut hash_path = artifact.path.clone().into_os_string();
+    hash_path.push(".keccak");
+    assert_eq!(
+        artifact.code_hash_path.as_deref(),
+        Some(Path::new(&hash_path))
+    );
+    let hash = std::fs::read_to_string(hash_path).expect("code hash written");
+    assert_eq!(hash.trim_end(), artifact.code_hash);
+    assert_eq!(artifact.code_hash.len(), 66);
+    assert_eq!(artifact.kept_elf_path, None);
+
+    assert!(report.cargo_build_ms > 0);
+    assert!(report.total_ms >= report.cargo_build_ms + report.link_ms);
+    assert!(project_dir.join("target/cargo-timing.html").exists());
+}
+
+#[test]
+fn debug_blob_is_named_and_reported() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-debug-blob", "no-alloc");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_debug_blob(true)\n        .with_report(\"target\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let report = std::fs::read_to_string(project_dir.join("target/pvm-build-report.json"))
+        .expect("report written");
+    let report: cargo_pvm_contract_builder::BuildOutput =
+        serde_json::from_str(&report).expect("valid report");
+    assert!(report.debug_blob);
+    let artifact = &report.artifacts[0];
+    assert!(
+        artifact
+            .path
+            .to_string_lossy()
+            .ends_with(".debug.debug.polkavm"),
+        "{}",
+        artifact.path.display()
+    );
+}
+
+#[test]
+fn cargo_args_reach_contract_build() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-cargo-args", "no-alloc");
+
+    // Only compiles if the `--config` reaches the nested build
+    let contract_src = project_dir.join("src/my-token.rs");
+    let source = std::fs::read_to_string(&contract_src)
+        .expect("read contract source")
+        .replace(
+            "pub extern \"C\" fn deploy() {}",
+            "pub extern \"C\" fn deploy() {\n    core::hint::black_box(env!(\"PVM_MARKER\").as_bytes());\n}",
+        );
+    std::fs::write(&contract_src, source).expect("write contract source");
+    std::fs::write(
+        project_dir.join("build.rs"),
+        r#"fn main() {
+    cargo_pvm_contract_builder::PvmBuilder::new()
+        .with_cargo_args(["--config", "env.PVM_MARKER='cargo-args-marker'"])
+        .with_keep_elf(true)
+        .build();
+}
+"#,
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let elf = std::fs::read(project_dir.join("target/my-token.elf")).expect("read ELF");
+    assert!(
+        elf.windows(b"cargo-args-marker".len())
+            .any(|window| window == b"cargo-args-marker")
+    );
+}
+
+#[test]
+fn contract_sees_configured_env() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-env", "no-alloc");
+
+    let contract_src = project_dir.join("src/my-token.rs");
+    let source = std::fs::read_to_string(&contract_src)
+        .expect("read contract source")
+        .replace(
+            "pub extern \"C\" fn deploy() {}",
+            "pub extern \"C\" fn deploy() {\n    core::hint::black_box(env!(\"MY_CONTRACT_VERSION\").as_bytes());\n}",
+        );
+    std::fs::write(&contract_src, source).expect("write contract source");
+    std::fs::write(
+        project_dir.join("build.rs"),
+        r#"fn main() {
+    cargo_pvm_contract_builder::PvmBuilder::new()
+        .with_clean_env(true)
+        .with_env("MY_CONTRACT_VERSION", "contract-version-1.2.3")
+        .with_keep_elf(true)
+        .build();
+}
+"#,
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let elf = std::fs::read(project_dir.join("target/my-token.elf")).expect("read ELF");
+    let marker = b"contract-version-1.2.3";
+    assert!(elf.windows(marker.len()).any(|window| window == marker));
+}
+
+#[test]
+fn no_alloc_contract_builds_with_core_only() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-core-only", "no-alloc");
+    let blob_path = project_dir.join("target/my-token.debug.polkavm");
+
+    for build_std in ["", "\n        .with_build_std([\"core\"])"] {
+        std::fs::write(
+            project_dir.join("build.rs"),
+            format!(
+                "fn main() {{\n    cargo_pvm_contract_builder::PvmBuilder::new(){build_std}\n        .build();\n}}\n"
+            ),
+        )
+        .expect("write build.rs");
+        build_scaffolded_project(&project_dir);
+
+        let blob = std::fs::read(&blob_path).expect("read blob");
+        let program = polkavm_linker::ProgramBlob::parse(blob.into()).expect("blob is loadable");
+        assert!(
+            program
+                .exports()
+                .any(|export| export.symbol().as_bytes() == b"call")
+        );
+    }
+}
+
+#[test]
+fn kept_elf_is_copied_next_to_the_binary() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-keep-elf", "no-alloc");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_keep_elf(true)\n        .with_report(\"target\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let report = std::fs::read_to_string(project_dir.join("target/pvm-build-report.json"))
+        .expect("report written");
+    let report: cargo_pvm_contract_builder::BuildOutput =
+        serde_json::from_str(&report).expect("valid report");
+    let artifact = &report.artifacts[0];
+    let kept = artifact.kept_elf_path.as_ref().expect("ELF kept");
+    assert_eq!(kept, &project_dir.join("target/my-token.elf"));
+    assert_eq!(
+        std::fs::read(kept).expect("read kept ELF"),
+        std::fs::read(&artifact.elf_path).expect("read ELF")
+    );
+}
+
+#[test]
+fn list_examples_as_json() {
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .arg("pvm-contract")
+        .arg("list-examples")
+        .arg("--json")
+        .output()
+        .expect("run list-examples");
+    assert!(output.status.success());
+
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid JSON");
+    let examples = json.as_array().expect("array of examples");
+    assert!(
+        examples
+            .iter()
+            .any(|example| example["name"] == "MyToken" && example["filename"] == "MyToken.sol")
+    );
+}
+
+#[test]
+fn scaffold_converted_to_cdylib_builds() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-lib", "no-alloc");
+
+    let cargo_toml =
+        std::fs::read_to_string(project_dir.join("Cargo.toml")).expect("Cargo.toml exists");
+    let cargo_toml = cargo_toml.replacen(
+        "[[bin]]\nname = \"mytoken-lib\"\n",
+        "[lib]\ncrate-type = [\"cdylib\"]\n",
+        1,
+    );
+    assert!(cargo_toml.contains("[lib]"));
+    std::fs::write(project_dir.join("Cargo.toml"), cargo_toml).expect("write Cargo.toml");
+
+    build_scaffolded_project(&project_dir);
+
+    assert!(
+        project_dir
+            .join("target/mytoken_lib.debug.polkavm")
+            .exists()
+    );
+}
+
+#[test]
+fn abi_prints_function_selectors() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let sol_path = temp_dir.path().join("Counter.sol");
+    std::fs::write(
+        &sol_path,
+        "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\ninterface Counter {\n    function increment(uint256 by) external;\n}\n",
+    )
+    .expect("write sol file");
+
+    let output = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .arg("pvm-contract")
+        .arg("abi")
+        .arg("--compact")
+        .arg("--selector")
+        .arg(&sol_path)
+        .output()
+        .expect("run abi");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).expect("utf-8 output");
+    assert_eq!(stdout.trim().lines().count(), 1);
+    let abi: serde_json::Value = serde_json::from_str(&stdout).expect("valid JSON");
+    assert_eq!(abi[0]["name"], "increment");
+    // keccak256("increment(uint256)")[..4]
+    assert_eq!(abi[0]["selector"], "0x7cf5dab0");
+}
+
+#[test]
+fn unknown_contract_name_lists_available_contracts() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let sol_path = temp_dir.path().join("Counter.sol");
+    std::fs::write(
+        &sol_path,
+        "// SPDX-License-Identifier: MIT\npragma solidity ^0.8.0;\n\ninterface Counter {\n    function increment(uint256 by) external;\n}\n",
+    )
+    .expect("write sol file");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .arg("pvm-contract")
+        .arg("abi")
+        .arg("--contract-name")
+        .arg("Missing")
+        .arg(&sol_path)
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "Contract `Missing` not found in Counter.sol; available contracts: Counter",
+        ));
+}
+
+#[test]
+fn force_rescaffold_keeps_unmanaged_files() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let scaffold = |force: bool| {
+        let mut cmd = Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"));
+        cmd.current_dir(temp_dir.path())
+            .arg("pvm-contract")
+            .arg("--init-type")
+            .arg("blank")
+            .arg("--memory-model")
+            .arg("no-alloc")
+            .arg("--name")
+            .arg("forced");
+        if force {
+            cmd.arg("--force");
+        }
+        cmd.assert()
+    };
+
+    scaffold(false).success();
+    let project_dir = temp_dir.path().join("forced");
+    let manifest = std::fs::read_to_string(project_dir.join(".pvm-scaffold.toml"))
+        .expect("scaffold manifest exists");
+    assert!(manifest.contains("\"Cargo.toml\""));
+
+    std::fs::write(project_dir.join("Cargo.toml"), "# edited\n").expect("edit Cargo.toml");
+    std::fs::write(project_dir.join("NOTES.md"), "keep me\n").expect("write user file");
+
+    scaffold(false)
+        .failure()
+        .stderr(predicates::str::contains("Directory already exists"));
+    scaffold(true).success();
+
+    let cargo_toml =
+        std::fs::read_to_string(project_dir.join("Cargo.toml")).expect("Cargo.toml exists");
+    assert!(cargo_toml.contains("[package]"));
+    let notes = std::fs::read_to_string(project_dir.join("NOTES.md")).expect("user file kept");
+    assert_eq!(notes, "keep me\n");
+}
+
+/// Scaffold `token-a` and `token-b` into a workspace with a `wrapper` crate whose
+/// build script runs `PvmBuilder::new()` followed by `builder_calls`.
+fn scaffold_token_workspace(temp_dir: &TempDir, builder_calls: &str) -> PathBuf {
+    scaffold_example(temp_dir, "token-a", "no-alloc");
+    scaffold_example(temp_dir, "token-b", "no-alloc");
+
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    let wrapper_dir = temp_dir.path().join("wrapper");
+    std::fs::create_dir_all(wrapper_dir.join("src")).expect("create wrapper");
+    std::fs::write(
+        wrapper_dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"wrapper\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[build-dependencies]\ncargo-pvm-contract-builder = {{ path = {:?} }}\n",
+            builder_path.display().to_string()
+        ),
+    )
+    .expect("write wrapper Cargo.toml");
+    std::fs::write(
+        wrapper_dir.join("build.rs"),
+        format!(
+            "fn main() {{\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        {builder_calls}\n        .build();\n}}\n"
+        ),
+    )
+    .expect("write wrapper build.rs");
+    std::fs::write(
+        wrapper_dir.join("src/lib.rs"),
+        "pub const TOKEN_A: &[u8] = include_bytes!(env!(\"PVM_BINARY_TOKEN_A\"));\npub const TOKEN_B: &[u8] = include_bytes!(env!(\"PVM_BINARY_TOKEN_B\"));\n",
+    )
+    .expect("write wrapper lib.rs");
+    std::fs::write(
+        temp_dir.path().join("Cargo.toml"),
+        "[workspace]\nmembers = [\"token-a\", \"token-b\", \"wrapper\"]\nresolver = \"2\"\n",
+    )
+    .expect("write workspace Cargo.toml");
+    std::fs::write(
+        temp_dir.path().join("rust-toolchain.toml"),
+        "[toolchain]\nchannel = \"nightly\"\n",
+    )
+    .expect("write rust-toolchain.toml");
+
+    wrapper_dir
+}
+
+/// Count the files under `dir` whose name satisfies `matches`.
+fn count_files(dir: &Path, matches: &dyn Fn(&str) -> bool) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .map(|path| {
+            if path.is_dir() {
+                count_files(&path, matches)
+            } else {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .is_some_and(matches) as usize
+            }
+        })
+        .sum()
+}
+
+#[test]
+fn workspace_builder_builds_every_member() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let wrapper_dir = scaffold_token_workspace(&temp_dir, ".with_workspace()");
+
+    build_scaffolded_project(&wrapper_dir);
+
+    let pvmbuild = temp_dir.path().join("target/pvmbuild");
+    assert!(find_file(&pvmbuild.join("token-a"), "token-a").is_some());
+    assert!(find_file(&pvmbuild.join("token-b"), "token-b").is_some());
+    let target_dir = temp_dir.path().join("target");
+    assert!(find_file(&target_dir, "token-a.debug.polkavm").is_some());
+    assert!(find_file(&target_dir, "token-b.debug.polkavm").is_some());
+}
+
+#[test]
+fn shared_target_dir_compiles_core_once() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let wrapper_dir = scaffold_token_workspace(
+        &temp_dir,
+        ".with_workspace()\n        .with_shared_target_dir()",
+    );
+
+    build_scaffolded_project(&wrapper_dir);
+
+    let pvmbuild = temp_dir.path().join("target/pvmbuild");
+    assert!(!pvmbuild.join("token-a").exists());
+    assert_eq!(
+        count_files(&pvmbuild, &|name| name.starts_with("libcore-")
+            && name.ends_with(".rlib")),
+        1
+    );
+    let target_dir = temp_dir.path().join("target");
+    assert!(find_file(&target_dir, "token-a.debug.polkavm").is_some());
+    assert!(find_file(&target_dir, "token-b.debug.polkavm").is_some());
+}
+
+#[test]
+fn json_output_reports_scaffold_result_and_errors() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let scaffold = || {
+        Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+            .current_dir(temp_dir.path())
+            .arg("pvm-contract")
+            .arg("--json")
+            .arg("--init-type")
+            .arg("blank")
+            .arg("--memory-model")
+            .arg("no-alloc")
+            .arg("--name")
+            .arg("json-output")
+            .output()
+            .expect("run scaffold")
+    };
+    let messages = |stdout: Vec<u8>| -> Vec<serde_json::Value> {
+        String::from_utf8(stdout)
+            .expect("utf-8 output")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("one JSON object per line"))
+            .collect()
+    };
+
+    let output = scaffold();
+    assert!(output.status.success());
+    let messages_ok = messages(output.stdout);
+    assert_eq!(messages_ok.len(), 1);
+    assert_eq!(messages_ok[0]["level"], "info");
+    assert!(
+        messages_ok[0]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Successfully initialized blank contract project")
+    );
+
+    let output = scaffold();
+    assert!(!output.status.success());
+    let messages_err = messages(output.stdout);
+    assert_eq!(messages_err.len(), 1);
+    assert_eq!(messages_err[0]["level"], "error");
+    assert!(
+        messages_err[0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Directory already exists")
+    );
+}
+
+#[test]
+fn custom_profile_builds_into_profile_directory() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-production", "no-alloc");
+
+    let cargo_toml_path = project_dir.join("Cargo.toml");
+    let mut cargo_toml = std::fs::read_to_string(&cargo_toml_path).expect("read Cargo.toml");
+    cargo_toml.push_str(
+        "\n[profile.production]\ninherits = \"release\"\nopt-level = \"z\"\nlto = \"fat\"\ncodegen-units = 1\n",
+    );
+    std::fs::write(&cargo_toml_path, cargo_toml).expect("write Cargo.toml");
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_profile_name(\"production\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let target_dir = project_dir.join("target");
+    assert!(find_file(&target_dir, "mytoken-production.production.polkavm").is_some());
+    let pvmbuild = target_dir.join("pvmbuild");
+    let elf = find_file(&pvmbuild, "mytoken-production").expect("ELF exists");
+    assert_eq!(
+        elf.parent().and_then(|dir| dir.file_name()),
+        Some(std::ffi::OsStr::new("production"))
+    );
+}
+
+#[test]
+fn undefined_custom_profile_is_rejected() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-no-profile", "no-alloc");
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_profile_name(\"production\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    let output = std::process::Command::new("cargo")
+        .current_dir(&project_dir)
+        .env_remove("CARGO")
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .arg("build")
+        .output()
+        .expect("run cargo build");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Profile `production` is not defined")
+    );
+}
+
+#[test]
+fn reproducible_builds_match_across_directories() {
+    let build = |temp_dir: &TempDir| {
+        let project_dir = scaffold_example(temp_dir, "mytoken-reproducible", "no-alloc");
+        std::fs::write(
+            project_dir.join("build.rs"),
+            "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_reproducible(true)\n        .build();\n}\n",
+        )
+        .expect("write build.rs");
+        build_scaffolded_project(&project_dir);
+
+        let blob = find_file(
+            &project_dir.join("target"),
+            "mytoken-reproducible.debug.polkavm",
+        )
+        .expect("linked binary exists");
+        std::fs::read(blob).expect("read linked binary")
+    };
+
+    let first_dir = TempDir::new().expect("temp dir");
+    let second_dir = TempDir::new().expect("temp dir");
+    assert!(build(&first_dir) == build(&second_dir));
+}
+
+#[test]
+fn host_crate_builds_contract_from_manifest_path() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let contract_dir = scaffold_example(&temp_dir, "mytoken-external", "no-alloc");
+
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    let host_dir = temp_dir.path().join("host");
+    std::fs::create_dir_all(host_dir.join("src")).expect("create host crate");
+    std::fs::write(
+        host_dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"host\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[build-dependencies]\ncargo-pvm-contract-builder = {{ path = {:?} }}\n",
+            builder_path.display().to_string()
+        ),
+    )
+    .expect("write host Cargo.toml");
+    std::fs::write(host_dir.join("src/main.rs"), "fn main() {}\n").expect("write main.rs");
+    std::fs::write(
+        host_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new_with_manifest(\"../mytoken-external/Cargo.toml\")\n        .with_output_dir(\"target\")\n        .with_report(\"target\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+    std::fs::copy(
+        contract_dir.join("rust-toolchain.toml"),
+        host_dir.join("rust-toolchain.toml"),
+    )
+    .expect("copy rust-toolchain.toml");
+
+    build_scaffolded_project(&host_dir);
+
+    // Relative paths other than the manifest's resolve against the contract
+    let report = std::fs::read_to_string(contract_dir.join("target/pvm-build-report.json"))
+        .expect("report written");
+    let report: cargo_pvm_contract_builder::BuildOutput =
+        serde_json::from_str(&report).expect("valid report");
+    let artifact = &report.artifacts[0];
+    assert!(artifact.path.exists());
+    assert!(
+        artifact
+            .elf_path
+            .starts_with(host_dir.join("target/pvmbuild/mytoken-external")),
+        "{}",
+        artifact.elf_path.display()
+    );
+}
+
+#[test]
+fn scaffold_with_tests_adds_host_test_alias() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .args(["pvm-contract", "--init-type", "blank"])
+        .args(["--memory-model", "alloc-with-alloy", "--name", "tested"])
+        .arg("--with-tests")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("cargo test-host"));
+
+    let project_dir = temp_dir.path().join("tested");
+    let config = std::fs::read_to_string(project_dir.join(".cargo/config.toml"))
+        .expect("cargo config exists");
+    assert!(config.contains("test-host = \"test --target host-tuple"));
+    let contract =
+        std::fs::read_to_string(project_dir.join("src/tested.rs")).expect("contract exists");
+    assert!(contract.contains("fn empty_call_returns_nothing() {"));
+}
+
+#[test]
+fn forwarded_feature_changes_the_contract() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let builder_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("../cargo-pvm-contract-builder");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .env("CARGO_PVM_CONTRACT_BUILDER_PATH", &builder_path)
+        .args(["pvm-contract", "--init-type", "blank"])
+        .args(["--memory-model", "no-alloc", "--name", "flagged"])
+        .assert()
+        .success();
+
+    let contract_dir = temp_dir.path().join("flagged");
+    let manifest_path = contract_dir.join("Cargo.toml");
+    let manifest = std::fs::read_to_string(&manifest_path).expect("Cargo.toml exists");
+    std::fs::write(
+        &manifest_path,
+        format!("{manifest}\n[features]\ntestnet = []\n"),
+    )
+    .expect("write Cargo.toml");
+    let source_path = contract_dir.join("src/flagged.rs");
+    let source = std::fs::read_to_string(&source_path).expect("contract exists");
+    std::fs::write(
+        &source_path,
+        source.replace(
+            "pub extern \"C\" fn call() {}",
+            "pub extern \"C\" fn call() {\n    #[cfg(feature = \"testnet\")]\n    unsafe {\n        core::arch::asm!(\"unimp\");\n    }\n}",
+        ),
+    )
+    .expect("write contract");
+
+    let host_dir = temp_dir.path().join("host");
+    std::fs::create_dir_all(host_dir.join("src")).expect("create host crate");
+    std::fs::write(
+        host_dir.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"host\"\nversion = \"0.1.0\"\nedition = \"2024\"\n\n[features]\ntestnet = []\n\n[build-dependencies]\ncargo-pvm-contract-builder = {{ path = {:?} }}\n",
+            builder_path.display().to_string()
+        ),
+    )
+    .expect("write host Cargo.toml");
+    std::fs::write(host_dir.join("src/main.rs"), "fn main() {}\n").expect("write main.rs");
+    std::fs::write(
+        host_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new_with_manifest(\"../flagged/Cargo.toml\")\n        .with_forwarded_features([\"testnet\"])\n        .with_report(\"target\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+    std::fs::copy(
+        contract_dir.join("rust-toolchain.toml"),
+        host_dir.join("rust-toolchain.toml"),
+    )
+    .expect("copy rust-toolchain.toml");
+
+    let build = |features: &[&str]| {
+        let status = std::process::Command::new("cargo")
+            .current_dir(&host_dir)
+            .env_remove("CARGO")
+            .env_remove("RUSTUP_TOOLCHAIN")
+            .arg("build")
+            .args(features)
+            .status()
+            .expect("run cargo build");
+        assert!(status.success(), "cargo build failed");
+
+        let report = std::fs::read_to_string(contract_dir.join("target/pvm-build-report.json"))
+            .expect("report written");
+        let report: cargo_pvm_contract_builder::BuildOutput =
+            serde_json::from_str(&report).expect("valid report");
+        std::fs::read(&report.artifacts[0].path).expect("blob written")
+    };
+
+    let mainnet = build(&[]);
+    let testnet = build(&["--features", "testnet"]);
+    assert_ne!(mainnet, testnet);
+    assert_eq!(build(&[]), mainnet);
+}
+
+#[test]
+fn scaffold_writes_readme() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-readme", "no-alloc");
+
+    let readme = std::fs::read_to_string(project_dir.join("README.md")).expect("README exists");
+    assert!(readme.starts_with("# mytoken-readme\n"));
+    assert!(readme.contains("without an allocator"));
+    assert!(readme.contains("`target/mytoken-readme.release.polkavm`"));
+    assert!(readme.contains("`src/my-token.rs`"));
+    assert!(readme.contains("`MyToken.sol`"));
+    assert!(!readme.contains("cargo test-host"));
+}
+
+#[test]
+fn scaffold_stages_gitignore_in_git_repository() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .current_dir(temp_dir.path())
+            .args(args)
+            .output()
+            .expect("run git");
+        assert!(output.status.success(), "git {args:?} failed");
+        String::from_utf8(output.stdout).expect("utf-8 output")
+    };
+    git(&["init", "--quiet"]);
+
+    for (name, extra) in [("staged", None), ("unstaged", Some("--no-git"))] {
+        Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+            .current_dir(temp_dir.path())
+            .args(["pvm-contract", "--init-type", "blank"])
+            .args(["--memory-model", "no-alloc", "--name", name])
+            .args(extra)
+            .assert()
+            .success();
+    }
+
+    let gitignore = std::fs::read_to_string(temp_dir.path().join("staged/.gitignore"))
+        .expect(".gitignore exists");
+    assert!(gitignore.contains("*.sol.json\n"));
+    assert!(gitignore.contains("/riscv64emac-unknown-none-polkavm/\n"));
+
+    let status = git(&["status", "--porcelain", "--untracked-files=all"]);
+    assert!(status.contains("A  staged/.gitignore"), "{status}");
+    assert!(status.contains("?? staged/Cargo.toml"), "{status}");
+    assert!(status.contains("?? unstaged/.gitignore"), "{status}");
+}
+
+#[test]
+fn contract_without_deploy_export_fails_to_build() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(temp_dir.path())
+        .args(["pvm-contract", "--init-type", "blank"])
+        .args(["--memory-model", "no-alloc", "--name", "exportless"])
+        .assert()
+        .success();
+
+    let project_dir = temp_dir.path().join("exportless");
+    let source_path = project_dir.join("src/exportless.rs");
+    let source = std::fs::read_to_string(&source_path).expect("contract exists");
+    let without_deploy = source.replace(
+        "#[no_mangle]\n#[polkavm_derive::polkavm_export]\npub extern \"C\" fn deploy() {}",
+        "pub extern \"C\" fn deploy() {}",
+    );
+    assert_ne!(source, without_deploy);
+    std::fs::write(&source_path, without_deploy).expect("write contract");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(&project_dir)
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .args(["pvm-contract", "build"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "PolkaVM binary `exportless` does not export `deploy`; exports found: `call`",
+        ));
+}
+
+#[test]
+fn scaffold_adds_contract_to_enclosing_workspace() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let root = temp_dir.path();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        "[workspace]\nresolver = \"2\"\nmembers = [\"app\"]\n",
+    )
+    .expect("write workspace Cargo.toml");
+    std::fs::create_dir_all(root.join("app/src")).expect("create app");
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(root.join("app/src"))
+        .args(["pvm-contract", "--init-type", "blank"])
+        .args(["--memory-model", "no-alloc", "--name", "member"])
+        .arg("--workspace")
+        .assert()
+        .success()
+        .stdout(predicates::str::contains("contracts/member\n  cargo build"))
+        .stderr(predicates::str::contains(
+            "Cargo only reads profiles from the workspace root",
+        ));
+
+    let workspace = std::fs::read_to_string(root.join("Cargo.toml")).expect("workspace exists");
+    assert!(
+        workspace.contains(r#"members = ["app", "contracts/member"]"#),
+        "{workspace}"
+    );
+    let cargo_toml = std::fs::read_to_string(root.join("contracts/member/Cargo.toml"))
+        .expect("member Cargo.toml exists");
+    assert!(cargo_toml.contains("name = \"member\""));
+    assert!(!cargo_toml.contains("[workspace]"));
+    assert!(!cargo_toml.contains("[profile.release]"));
+    assert!(!root.join("app/src/member").exists());
+
+    // An explicit root works from outside the workspace, and a missing one is reported
+    let outside = TempDir::new().expect("temp dir");
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(outside.path())
+        .args(["pvm-contract", "--init-type", "blank"])
+        .args(["--memory-model", "no-alloc", "--name", "other"])
+        .arg("--workspace-root")
+        .arg(root)
+        .assert()
+        .success();
+    assert!(root.join("contracts/other/Cargo.toml").exists());
+
+    Command::new(assert_cmd::cargo::cargo_bin!("cargo-pvm-contract"))
+        .current_dir(outside.path())
+        .args(["pvm-contract", "--init-type", "blank"])
+        .args(["--memory-model", "no-alloc", "--name", "lost"])
+        .arg("--workspace")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("No Cargo workspace found"));
+}
+
+#[test]
+fn hex_output_decodes_to_the_blob() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-hex", "no-alloc");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_output_dir(\"artifacts\")\n        .with_output_format(cargo_pvm_contract_builder::OutputFormat::Hex)\n        .with_gzip_output(true)\n        .with_code_hash_file(cargo_pvm_contract_builder::CodeHash::Keccak256)\n        .with_report(\"artifacts\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let report = std::fs::read_to_string(project_dir.join("artifacts/pvm-build-report.json"))
+        .expect("report written");
+    let report: cargo_pvm_contract_builder::BuildOutput =
+        serde_json::from_str(&report).expect("valid report");
+    let artifact = &report.artifacts[0];
+    let blob = std::fs::read(&artifact.path).expect("blob exists");
+
+    // Only the hex encoding lands in the output directory
+    let artifacts_dir = project_dir.join("artifacts");
+    assert!(!artifacts_dir.join("mytoken-hex.debug.polkavm").exists());
+    let hex_path = artifact.hex_path.as_ref().expect("hex path reported");
+    assert_eq!(
+        hex_path,
+        &artifacts_dir.join("mytoken-hex.debug.polkavm.hex")
+    );
+    let hex = std::fs::read_to_string(hex_path).expect("hex written");
+    let digits = hex.strip_prefix("0x").expect("0x prefix");
+    let decoded: Vec<u8> = (0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).expect("hex digit"))
+        .collect();
+    assert_eq!(decoded, blob);
+
+    assert_eq!(
+        artifact.gzip_path.as_deref(),
+        Some(artifacts_dir.join("mytoken-hex.debug.polkavm.gz").as_path())
+    );
+    // The code hash is named after the blob but kept with the hex file
+    let hash_path = artifacts_dir.join("mytoken-hex.debug.polkavm.keccak");
+    assert_eq!(
+        artifact.code_hash_path.as_deref(),
+        Some(hash_path.as_path())
+    );
+    assert!(hash_path.exists());
+}
+
+#[test]
+fn bundle_combines_bytecode_and_abi() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    // Named after MyToken.sol, which the bundle's ABI is compiled from
+    let project_dir = scaffold_example(&temp_dir, "mytoken", "no-alloc");
+
+    std::fs::write(
+        project_dir.join("build.rs"),
+        "fn main() {\n    cargo_pvm_contract_builder::PvmBuilder::new()\n        .with_output_dir(\"artifacts\")\n        .with_bundle(cargo_pvm_contract_builder::AbiSource::Solidity)\n        .with_report(\"artifacts\")\n        .build();\n}\n",
+    )
+    .expect("write build.rs");
+
+    build_scaffolded_project(&project_dir);
+
+    let report = std::fs::read_to_string(project_dir.join("artifacts/pvm-build-report.json"))
+        .expect("report written");
+    let report: cargo_pvm_contract_builder::BuildOutput =
+        serde_json::from_str(&report).expect("valid report");
+    let artifact = &report.artifacts[0];
+    let bundle_path = artifact.bundle_path.as_ref().expect("bundle path reported");
+    assert_eq!(
+        bundle_path,
+        &project_dir.join("artifacts/mytoken.contract.json")
+    );
+
+    let bundle = std::fs::read_to_string(bundle_path).expect("bundle written");
+    let bundle: cargo_pvm_contract_builder::bundle::ContractBundle =
+        serde_json::from_str(&bundle).expect("valid bundle");
+    assert_eq!(bundle.name, "mytoken");
+    assert_eq!(bundle.code_hash, artifact.code_hash);
+    let blob = std::fs::read(&artifact.path).expect("blob exists");
+    let hex: String = blob.iter().map(|byte| format!("{byte:02x}")).collect();
+    assert_eq!(bundle.bytecode, format!("0x{hex}"));
+    assert!(
+        bundle
+            .abi
+            .as_array()
+            .expect("ABI array")
+            .iter()
+            .any(|item| item["name"] == "transfer")
+    );
+    assert_eq!(bundle.build_info.profile, "dev");
+    assert_eq!(
+        bundle.build_info.polkavm_linker_version,
+        report.polkavm_linker_version
+    );
+}
+
+#[test]
+fn contract_warnings_surface_in_the_outer_build() {
+    let temp_dir = TempDir::new().expect("temp dir");
+    let project_dir = scaffold_example(&temp_dir, "mytoken-warnings", "no-alloc");
+
+    let contract_src = project_dir.join("src/my-token.rs");
+    let source = std::fs::read_to_string(&contract_src).expect("read contract source");
+    let source = source.replace(
+        "pub extern \"C\" fn deploy() {}",
+        "pub extern \"C\" fn deploy() {\n    let unused_value = 1;\n}",
+    );
+    std::fs::write(&contract_src, source).expect("write contract source");
+
+    let output = std::process::Command::new("cargo")
+        .current_dir(&project_dir)
+        .env_remove("CARGO")
+        .env_remove("RUSTUP_TOOLCHAIN")
+        .arg("build")
+        .output()
+        .expect("run cargo build");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "cargo build failed: {stderr}");
+    assert!(
+        stderr.contains("[pvm:mytoken-warnings] unused variable: `unused_value`"),
+        "unexpected output: {stderr}"
+    );
+    assert!(
+        stderr.contains("src/my-token.rs"),
+        "unexpected output: {stderr}"
+    );
+}